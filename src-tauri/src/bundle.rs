@@ -0,0 +1,634 @@
+//! Model bundles, for moving models to machines without a network. A bundle
+//! is a plain tar archive laid out like Ollama's model directory, manifests
+//! first and blobs after, so `tar -x` into a models directory works too.
+//! Importing checks every blob against its digest and size before the
+//! manifest that makes the model visible is written.
+
+use crate::endpoint::Endpoint;
+use crate::error::AppError;
+use crate::model_store::{self, Manifest, ModelName, StoreWriters};
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+use std::collections::HashSet;
+use std::io::{self, Read, Write};
+use std::path::Path;
+use tauri::ipc::Channel;
+use tauri::State;
+
+const BLOCK: usize = 512;
+
+/// Largest size the 11 octal digits of a tar header hold; anything bigger
+/// uses the GNU base-256 form that every current tar reads.
+const OCTAL_SIZE_LIMIT: u64 = 8u64.pow(11);
+
+/// Manifests are small JSON files; refuse anything claiming to be one that
+/// isn't.
+const MAX_MANIFEST_SIZE: u64 = 1024 * 1024;
+
+/// Report progress at most this often, in bytes.
+const REPORT_EVERY: u64 = 16 * 1024 * 1024;
+
+#[derive(Debug, Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct BundleProgress {
+    pub completed: u64,
+    pub total: u64,
+}
+
+struct Progress<'a> {
+    done: u64,
+    total: u64,
+    reported: u64,
+    report: &'a dyn Fn(BundleProgress),
+}
+
+impl<'a> Progress<'a> {
+    fn new(total: u64, report: &'a dyn Fn(BundleProgress)) -> Self {
+        Progress {
+            done: 0,
+            total,
+            reported: 0,
+            report,
+        }
+    }
+
+    fn add(&mut self, bytes: u64) {
+        self.done += bytes;
+        if self.done - self.reported >= REPORT_EVERY {
+            self.reported = self.done;
+            (self.report)(BundleProgress {
+                completed: self.done.min(self.total),
+                total: self.total,
+            });
+        }
+    }
+
+    fn finish(&self) {
+        (self.report)(BundleProgress {
+            completed: self.total,
+            total: self.total,
+        });
+    }
+}
+
+/// Copy `from` into `to` in chunks, showing each chunk to `inspect`.
+/// Returns the number of bytes copied.
+fn copy_chunks(
+    from: &mut impl Read,
+    to: &mut impl Write,
+    mut inspect: impl FnMut(&[u8]),
+) -> io::Result<u64> {
+    let mut buf = vec![0; 1024 * 1024];
+    let mut copied = 0;
+    loop {
+        let read = from.read(&mut buf)?;
+        if read == 0 {
+            return Ok(copied);
+        }
+        inspect(&buf[..read]);
+        to.write_all(&buf[..read])?;
+        copied += read as u64;
+    }
+}
+
+// ── Tar ─────────────────────────────────────────────────────────────
+
+fn octal(field: &mut [u8], value: u64) {
+    let digits = format!("{value:0width$o}", width = field.len() - 1);
+    field[..digits.len()].copy_from_slice(digits.as_bytes());
+}
+
+/// A ustar header for a regular file. Long paths are split across the
+/// name and prefix fields.
+fn header(path: &str, size: u64) -> Result<[u8; BLOCK], AppError> {
+    let (prefix, name) = match path.len() {
+        0..=100 => ("", path),
+        _ => path
+            .char_indices()
+            .filter(|(i, c)| *c == '/' && *i <= 155 && path.len() - i - 1 <= 100)
+            .map(|(i, _)| (&path[..i], &path[i + 1..]))
+            .next()
+            .ok_or_else(|| AppError::InvalidInput(format!("Path too long for a bundle: {path}")))?,
+    };
+
+    let mut block = [0u8; BLOCK];
+    block[..name.len()].copy_from_slice(name.as_bytes());
+    octal(&mut block[100..108], 0o644);
+    octal(&mut block[108..116], 0);
+    octal(&mut block[116..124], 0);
+    if size < OCTAL_SIZE_LIMIT {
+        octal(&mut block[124..136], size);
+    } else {
+        block[124] = 0x80;
+        block[128..136].copy_from_slice(&size.to_be_bytes());
+    }
+    octal(
+        &mut block[136..148],
+        chrono::Utc::now().timestamp().max(0) as u64,
+    );
+    block[156] = b'0';
+    block[257..263].copy_from_slice(b"ustar\0");
+    block[263..265].copy_from_slice(b"00");
+    block[345..345 + prefix.len()].copy_from_slice(prefix.as_bytes());
+
+    block[148..156].fill(b' ');
+    let sum: u32 = block.iter().map(|b| *b as u32).sum();
+    octal(&mut block[148..155], sum as u64);
+    Ok(block)
+}
+
+fn padding(size: u64) -> usize {
+    (BLOCK - (size % BLOCK as u64) as usize) % BLOCK
+}
+
+struct Entry {
+    path: String,
+    size: u64,
+    is_file: bool,
+}
+
+fn field_str(field: &[u8]) -> String {
+    let end = field.iter().position(|b| *b == 0).unwrap_or(field.len());
+    String::from_utf8_lossy(&field[..end]).to_string()
+}
+
+fn parse_octal(field: &[u8]) -> Option<u64> {
+    let text = field_str(field);
+    let text = text.trim();
+    if text.is_empty() {
+        return Some(0);
+    }
+    u64::from_str_radix(text, 8).ok()
+}
+
+/// Parse a header block; `None` at the zero block that ends the archive.
+fn read_header(block: &[u8; BLOCK]) -> Result<Option<Entry>, AppError> {
+    if block.iter().all(|b| *b == 0) {
+        return Ok(None);
+    }
+    let damaged = || AppError::InvalidInput("The bundle is damaged".to_string());
+
+    let stored = parse_octal(&block[148..156]).ok_or_else(damaged)?;
+    let sum: u64 = block
+        .iter()
+        .enumerate()
+        .map(|(i, b)| if (148..156).contains(&i) { b' ' } else { *b } as u64)
+        .sum();
+    if stored != sum {
+        return Err(damaged());
+    }
+
+    let size = if block[124] & 0x80 != 0 {
+        block[128..136]
+            .iter()
+            .fold(0u64, |acc, b| (acc << 8) | *b as u64)
+    } else {
+        parse_octal(&block[124..136]).ok_or_else(damaged)?
+    };
+    let name = field_str(&block[..100]);
+    let prefix = field_str(&block[345..500]);
+    let path = if prefix.is_empty() {
+        name
+    } else {
+        format!("{prefix}/{name}")
+    };
+    Ok(Some(Entry {
+        path,
+        size,
+        is_file: matches!(block[156], b'0' | 0),
+    }))
+}
+
+// ── Export ──────────────────────────────────────────────────────────
+
+/// Write `name`'s manifest and blobs from the store in `dir` to `out`,
+/// which is saved at `out_path`.
+fn write_bundle(
+    dir: &Path,
+    name: &str,
+    out: &mut impl Write,
+    out_path: &Path,
+    report: &dyn Fn(BundleProgress),
+) -> Result<(), AppError> {
+    let entry = ModelName::parse(name).manifest_entry();
+    let manifest_path = dir.join(&entry);
+    let raw = std::fs::read(&manifest_path).map_err(|e| match e.kind() {
+        io::ErrorKind::NotFound => AppError::ModelNotFound(format!("{name} is not installed")),
+        _ => AppError::io("Cannot read manifest", &manifest_path, e),
+    })?;
+    let manifest: Manifest = serde_json::from_slice(&raw)
+        .map_err(|e| AppError::BadResponse(format!("Unreadable manifest for {name}: {e}")))?;
+
+    let mut blobs: Vec<(String, u64)> = Vec::new();
+    for layer in manifest.blobs() {
+        let entry = model_store::blob_entry(&layer.digest);
+        if !blobs.iter().any(|(e, _)| *e == entry) {
+            blobs.push((entry, layer.size));
+        }
+    }
+    let mut progress = Progress::new(blobs.iter().map(|(_, size)| size).sum(), report);
+    let write_err = |e: io::Error| AppError::io("Failed to write bundle", out_path, e);
+
+    out.write_all(&header(&entry, raw.len() as u64)?)
+        .and_then(|()| out.write_all(&raw))
+        .and_then(|()| out.write_all(&[0; BLOCK][..padding(raw.len() as u64)]))
+        .map_err(write_err)?;
+
+    for (entry, size) in blobs {
+        let path = dir.join(&entry);
+        let mut file =
+            std::fs::File::open(&path).map_err(|e| AppError::io("Cannot read blob", &path, e))?;
+        out.write_all(&header(&entry, size)?).map_err(write_err)?;
+        let copied = copy_chunks(&mut (&mut file).take(size), out, |chunk| {
+            progress.add(chunk.len() as u64)
+        })
+        .map_err(|e| AppError::io("Failed to copy blob", &path, e))?;
+        // The header already promised `size` bytes
+        if copied != size {
+            return Err(AppError::Io {
+                message: "A model file is shorter than its manifest says".to_string(),
+                details: format!("{}: expected {size} bytes, read {copied}", path.display()),
+            });
+        }
+        out.write_all(&[0; BLOCK][..padding(size)])
+            .map_err(write_err)?;
+    }
+    out.write_all(&[0; BLOCK * 2]).map_err(write_err)?;
+    progress.finish();
+    Ok(())
+}
+
+fn export(
+    dir: &Path,
+    name: &str,
+    path: &Path,
+    report: &dyn Fn(BundleProgress),
+) -> Result<(), AppError> {
+    // Write beside the target so a failed export leaves no half bundle
+    let partial = path.with_extension("partial");
+    let result = std::fs::File::create(&partial)
+        .map_err(|e| AppError::io("Cannot create bundle", &partial, e))
+        .and_then(|file| {
+            let mut out = io::BufWriter::new(file);
+            write_bundle(dir, name, &mut out, &partial, report)?;
+            out.flush()
+                .map_err(|e| AppError::io("Failed to write bundle", &partial, e))
+        })
+        .and_then(|()| {
+            std::fs::rename(&partial, path).map_err(|e| AppError::io("Cannot save bundle", path, e))
+        });
+    if result.is_err() {
+        let _ = std::fs::remove_file(&partial);
+    }
+    result
+}
+
+// ── Import ──────────────────────────────────────────────────────────
+
+/// The blob digest for a `blobs/sha256-<hex>` entry.
+fn blob_digest(entry: &str) -> Option<String> {
+    let hex = entry.strip_prefix("blobs/sha256-")?;
+    (hex.len() == 64 && hex.bytes().all(|b| b.is_ascii_hexdigit()))
+        .then(|| format!("sha256:{}", hex.to_ascii_lowercase()))
+}
+
+/// Whether a manifest entry is safe to create under the models directory.
+fn is_manifest_entry(entry: &str) -> bool {
+    ModelName::from_manifest_entry(entry).is_some()
+        && entry
+            .split('/')
+            .all(|part| !part.is_empty() && part != "." && part != ".." && !part.contains('\\'))
+}
+
+fn has_blob(dir: &Path, digest: &str, size: u64) -> bool {
+    std::fs::metadata(dir.join(model_store::blob_entry(digest)))
+        .is_ok_and(|meta| meta.len() == size)
+}
+
+/// Bytes the manifests' blobs add to the store in `dir`, leaving out the
+/// ones it already has.
+fn missing_bytes(dir: &Path, manifests: &[(String, Vec<u8>, Manifest)]) -> u64 {
+    let mut seen = HashSet::new();
+    manifests
+        .iter()
+        .flat_map(|(_, _, manifest)| manifest.blobs())
+        .filter(|layer| seen.insert(layer.digest.as_str()))
+        .filter(|layer| !has_blob(dir, &layer.digest, layer.size))
+        .map(|layer| layer.size)
+        .sum()
+}
+
+/// Stream one blob into the store, hashing as it goes. It only takes its
+/// real name once the digest matches.
+fn install_blob(
+    dir: &Path,
+    digest: &str,
+    data: &mut impl Read,
+    progress: &mut Progress,
+) -> Result<(), AppError> {
+    let target = dir.join(model_store::blob_entry(digest));
+    let temp = target.with_extension("import");
+    let mut file =
+        std::fs::File::create(&temp).map_err(|e| AppError::io("Cannot write blob", &temp, e))?;
+    let mut hasher = Sha256::new();
+    let copied = copy_chunks(data, &mut file, |chunk| {
+        hasher.update(chunk);
+        progress.add(chunk.len() as u64);
+    });
+    if let Err(e) = copied {
+        let _ = std::fs::remove_file(&temp);
+        return Err(AppError::io("Cannot write blob", &temp, e));
+    }
+
+    let hex: String = hasher
+        .finalize()
+        .iter()
+        .map(|b| format!("{b:02x}"))
+        .collect();
+    if format!("sha256:{hex}") != digest {
+        let _ = std::fs::remove_file(&temp);
+        return Err(AppError::InvalidInput(format!(
+            "The bundle is damaged: {digest} does not match its contents"
+        )));
+    }
+    std::fs::rename(&temp, &target).map_err(|e| AppError::io("Cannot install blob", &target, e))
+}
+
+/// Install every model in the bundle read from `input` into `dir`,
+/// returning their names.
+fn read_bundle(
+    dir: &Path,
+    input: &mut impl Read,
+    total: u64,
+    report: &dyn Fn(BundleProgress),
+) -> Result<Vec<String>, AppError> {
+    let not_a_bundle = || AppError::InvalidInput("Not a model bundle".to_string());
+    let mut progress = Progress::new(total, report);
+    let mut manifests: Vec<(String, Vec<u8>, Manifest)> = Vec::new();
+    let mut space_checked = false;
+    std::fs::create_dir_all(dir.join("blobs"))
+        .map_err(|e| AppError::io("Cannot create models directory", dir, e))?;
+
+    let mut block = [0u8; BLOCK];
+    loop {
+        input
+            .read_exact(&mut block)
+            .map_err(|_| AppError::InvalidInput("The bundle is truncated".to_string()))?;
+        let Some(entry) = read_header(&block)? else {
+            break;
+        };
+        let mut data = (&mut *input).take(entry.size);
+
+        if !entry.is_file {
+            io::copy(&mut data, &mut io::sink()).map_err(|_| not_a_bundle())?;
+        } else if is_manifest_entry(&entry.path) {
+            if entry.size > MAX_MANIFEST_SIZE {
+                return Err(not_a_bundle());
+            }
+            let mut raw = Vec::new();
+            data.read_to_end(&mut raw).map_err(|_| not_a_bundle())?;
+            let manifest = serde_json::from_slice(&raw).map_err(|_| not_a_bundle())?;
+            manifests.push((entry.path, raw, manifest));
+        } else if let Some(digest) = blob_digest(&entry.path) {
+            // Manifests come first, so by the first blob we know what's needed
+            if !space_checked {
+                model_store::ensure_space(dir, missing_bytes(dir, &manifests))?;
+                space_checked = true;
+            }
+            let expected = manifests
+                .iter()
+                .flat_map(|(_, _, m)| m.blobs())
+                .find(|layer| layer.digest == digest)
+                .map(|layer| layer.size);
+            if expected != Some(entry.size) {
+                return Err(AppError::InvalidInput(format!(
+                    "The bundle has a blob its manifests don't list: {digest}"
+                )));
+            }
+            if has_blob(dir, &digest, entry.size) {
+                // Already installed, presumably shared with another model
+                io::copy(&mut data, &mut io::sink()).map_err(|_| not_a_bundle())?;
+                progress.add(entry.size);
+            } else {
+                install_blob(dir, &digest, &mut data, &mut progress)?;
+            }
+        } else {
+            return Err(not_a_bundle());
+        }
+
+        if data.limit() > 0 {
+            return Err(AppError::InvalidInput(
+                "The bundle is truncated".to_string(),
+            ));
+        }
+        let mut pad = [0u8; BLOCK];
+        input
+            .read_exact(&mut pad[..padding(entry.size)])
+            .map_err(|_| AppError::InvalidInput("The bundle is truncated".to_string()))?;
+    }
+
+    if manifests.is_empty() {
+        return Err(not_a_bundle());
+    }
+    for (_, _, manifest) in &manifests {
+        for layer in manifest.blobs() {
+            if !dir.join(model_store::blob_entry(&layer.digest)).is_file() {
+                return Err(AppError::InvalidInput(format!(
+                    "The bundle is missing {}",
+                    layer.digest
+                )));
+            }
+        }
+    }
+
+    // Every blob is in place, so the models can appear
+    let mut names = Vec::new();
+    for (entry, raw, _) in manifests {
+        let path = dir.join(&entry);
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)
+                .map_err(|e| AppError::io("Cannot create manifest folder", parent, e))?;
+        }
+        std::fs::write(&path, raw).map_err(|e| AppError::io("Cannot write manifest", &path, e))?;
+        names.extend(ModelName::from_manifest_entry(&entry).map(|n| n.display()));
+    }
+    progress.finish();
+    Ok(names)
+}
+
+// ── Commands ────────────────────────────────────────────────────────
+
+/// Write an installed model to a bundle at `path`.
+#[tauri::command]
+pub async fn export_model(
+    endpoint: State<'_, Endpoint>,
+    name: String,
+    path: String,
+    on_progress: Channel<BundleProgress>,
+) -> Result<(), AppError> {
+    model_store::require_local(&endpoint)?;
+    tokio::task::spawn_blocking(move || {
+        let report = |progress: BundleProgress| {
+            let _ = on_progress.send(progress);
+        };
+        export(
+            &model_store::models_dir()?,
+            &name,
+            Path::new(&path),
+            &report,
+        )
+    })
+    .await
+    .map_err(|e| AppError::Internal(e.to_string()))?
+}
+
+/// Install the models in the bundle at `path`, returning their names. Ollama
+/// lists them straight away.
+#[tauri::command]
+pub async fn import_bundle(
+    endpoint: State<'_, Endpoint>,
+    writers: State<'_, StoreWriters>,
+    path: String,
+    on_progress: Channel<BundleProgress>,
+) -> Result<Vec<String>, AppError> {
+    model_store::require_local(&endpoint)?;
+    // Blobs land before their manifests
+    let _writing = writers.begin()?;
+    tokio::task::spawn_blocking(move || {
+        let report = |progress: BundleProgress| {
+            let _ = on_progress.send(progress);
+        };
+        let dir = model_store::models_dir()?;
+        let file =
+            std::fs::File::open(&path).map_err(|e| AppError::io("Cannot open bundle", &path, e))?;
+        let total = file
+            .metadata()
+            .map_err(|e| AppError::io("Cannot open bundle", &path, e))?
+            .len();
+        read_bundle(&dir, &mut io::BufReader::new(file), total, &report)
+    })
+    .await
+    .map_err(|e| AppError::Internal(e.to_string()))?
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn temp_dir(name: &str) -> std::path::PathBuf {
+        let dir =
+            std::env::temp_dir().join(format!("innertalk-bundle-{name}-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(dir.join("blobs")).unwrap();
+        dir
+    }
+
+    fn sha256(data: &[u8]) -> String {
+        let hex: String = Sha256::digest(data)
+            .iter()
+            .map(|b| format!("{b:02x}"))
+            .collect();
+        format!("sha256:{hex}")
+    }
+
+    /// A store holding `tiny:latest`, returning the bundle it exports to.
+    fn exported_bundle(source: &Path) -> Vec<u8> {
+        let (config, weights) = (b"{}".to_vec(), vec![7u8; 1500]);
+        for data in [&config, &weights] {
+            std::fs::write(source.join(model_store::blob_entry(&sha256(data))), data).unwrap();
+        }
+        let manifest = json!({
+            "config": { "digest": sha256(&config), "size": config.len() },
+            "layers": [{ "digest": sha256(&weights), "size": weights.len() }],
+        });
+        let entry = source.join(ModelName::parse("tiny").manifest_entry());
+        std::fs::create_dir_all(entry.parent().unwrap()).unwrap();
+        std::fs::write(entry, manifest.to_string()).unwrap();
+
+        let mut bundle = Vec::new();
+        write_bundle(source, "tiny", &mut bundle, Path::new("tiny.tar"), &|_| {}).unwrap();
+        bundle
+    }
+
+    #[test]
+    fn bundles_round_trip_between_stores() {
+        let (source, target) = (temp_dir("source"), temp_dir("target"));
+        let bundle = exported_bundle(&source);
+        assert_eq!(bundle.len() % BLOCK, 0);
+
+        let names = read_bundle(
+            &target,
+            &mut bundle.as_slice(),
+            bundle.len() as u64,
+            &|_| {},
+        )
+        .unwrap();
+        assert_eq!(names, vec!["tiny:latest"]);
+        let entry = ModelName::parse("tiny").manifest_entry();
+        assert_eq!(
+            std::fs::read(target.join(&entry)).unwrap(),
+            std::fs::read(source.join(&entry)).unwrap()
+        );
+        let _ = std::fs::remove_dir_all(&source);
+        let _ = std::fs::remove_dir_all(&target);
+    }
+
+    #[test]
+    fn damaged_blobs_are_rejected_and_leave_no_model() {
+        let (source, target) = (temp_dir("damaged-source"), temp_dir("damaged-target"));
+        let mut bundle = exported_bundle(&source);
+        // Flip a byte in the weights, the last entry before the end blocks
+        let last = bundle.len() - BLOCK * 2 - padding(1500) - 1;
+        bundle[last] ^= 0xff;
+
+        let err = read_bundle(&target, &mut bundle.as_slice(), 0, &|_| {}).unwrap_err();
+        assert!(err.to_string().contains("does not match"), "{err}");
+        assert!(!target
+            .join(ModelName::parse("tiny").manifest_entry())
+            .exists());
+        let _ = std::fs::remove_dir_all(&source);
+        let _ = std::fs::remove_dir_all(&target);
+    }
+
+    #[test]
+    fn space_needed_leaves_out_blobs_already_installed() {
+        let (source, empty) = (temp_dir("space-source"), temp_dir("space-empty"));
+        exported_bundle(&source);
+        let entry = ModelName::parse("tiny").manifest_entry();
+        let raw = std::fs::read(source.join(&entry)).unwrap();
+        let manifests = vec![(entry, raw.clone(), serde_json::from_slice(&raw).unwrap())];
+
+        assert_eq!(missing_bytes(&source, &manifests), 0);
+        assert_eq!(missing_bytes(&empty, &manifests), 2 + 1500);
+        let _ = std::fs::remove_dir_all(&source);
+        let _ = std::fs::remove_dir_all(&empty);
+    }
+
+    #[test]
+    fn exports_refuse_blobs_shorter_than_their_manifest() {
+        let source = temp_dir("short");
+        exported_bundle(&source);
+        let weights = sha256(&[7u8; 1500]);
+        std::fs::write(source.join(model_store::blob_entry(&weights)), [7u8; 1000]).unwrap();
+
+        let mut bundle = Vec::new();
+        let err =
+            write_bundle(&source, "tiny", &mut bundle, Path::new("tiny.tar"), &|_| {}).unwrap_err();
+        assert!(err.to_string().contains("shorter"), "{err}");
+        let _ = std::fs::remove_dir_all(&source);
+    }
+
+    #[test]
+    fn headers_handle_long_paths_and_huge_sizes() {
+        let path = format!("manifests/{}/ns/model/tag", "h".repeat(90));
+        let block = header(&path, 20 * 1024 * 1024 * 1024).unwrap();
+        let entry = read_header(&block).unwrap().unwrap();
+        assert_eq!(entry.path, path);
+        assert_eq!(entry.size, 20 * 1024 * 1024 * 1024);
+
+        assert!(!is_manifest_entry("manifests/../../etc/x"));
+        assert!(!is_manifest_entry("manifests/a/../b/c"));
+        assert_eq!(blob_digest("blobs/sha256-short"), None);
+    }
+}
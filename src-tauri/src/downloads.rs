@@ -0,0 +1,616 @@
+//! Model download manager. Pulls run one at a time from a queue that is saved
+//! to disk, so downloads left unfinished pick up again after a restart.
+//! Pausing just stops the stream: Ollama keeps partially downloaded layers and
+//! resumes them on the next pull of the same model.
+//!
+//! Ollama reports progress one layer at a time, so the byte counts in its
+//! status lines reset whenever it moves to the next layer. `PullTracker` keeps
+//! every layer's counts and turns them into whole-model progress with a
+//! smoothed speed and an ETA.
+
+use crate::endpoint::Endpoint;
+use crate::error::AppError;
+use crate::http::HttpClient;
+use crate::model_store::{self, StoreWriters};
+use crate::ollama::PullProgress;
+use crate::sidecar;
+use crate::storage;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use tauri::async_runtime::JoinHandle;
+use tauri::{Emitter, Manager, State};
+
+const QUEUE_FILE: &str = "downloads.json";
+
+/// Emitted with the `Download` whenever one changes.
+pub const DOWNLOAD_EVENT: &str = "download-updated";
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub enum DownloadState {
+    Queued,
+    Downloading,
+    Paused,
+    Done,
+    Failed,
+    Cancelled,
+}
+
+impl DownloadState {
+    /// Still wanted: kept in the queue file and restarted on launch.
+    fn is_pending(self) -> bool {
+        matches!(
+            self,
+            DownloadState::Queued | DownloadState::Downloading | DownloadState::Paused
+        )
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct Download {
+    pub id: String,
+    pub model: String,
+    pub state: DownloadState,
+    /// Ollama's last status line, like "pulling manifest".
+    #[serde(default)]
+    pub status: String,
+    #[serde(default, skip_deserializing)]
+    pub phase: Option<PullPhase>,
+    /// Bytes of all layers seen so far. The total grows as Ollama reaches
+    /// layers it hasn't reported yet.
+    #[serde(default)]
+    pub completed: Option<u64>,
+    #[serde(default)]
+    pub total: Option<u64>,
+    #[serde(default, skip_deserializing)]
+    pub bytes_per_second: Option<f64>,
+    #[serde(default, skip_deserializing)]
+    pub eta_seconds: Option<u64>,
+    #[serde(default, skip_deserializing, skip_serializing_if = "Option::is_none")]
+    pub error: Option<AppError>,
+}
+
+#[derive(Default)]
+struct Queue {
+    items: Vec<Download>,
+    /// The download currently streaming, if any.
+    active: Option<(String, JoinHandle<()>)>,
+}
+
+impl Queue {
+    fn get_mut(&mut self, id: &str) -> Result<&mut Download, AppError> {
+        self.items
+            .iter_mut()
+            .find(|d| d.id == id)
+            .ok_or_else(|| AppError::InvalidInput(format!("No download with id {id}")))
+    }
+
+    /// Stop the active download if it is `id`.
+    fn stop(&mut self, id: &str) {
+        if self.active.as_ref().is_some_and(|(active, _)| active == id) {
+            if let Some((_, task)) = self.active.take() {
+                task.abort();
+            }
+        }
+    }
+}
+
+pub struct Downloads(Mutex<Queue>);
+
+// ── Progress ────────────────────────────────────────────────────────
+
+/// Weight of the newest sample in the smoothed speed.
+const SPEED_SMOOTHING: f64 = 0.3;
+
+/// Shortest span a speed sample covers, so bursts of status lines don't make
+/// the rate swing.
+const SPEED_SAMPLE: Duration = Duration::from_millis(500);
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub enum PullPhase {
+    PullingManifest,
+    Pulling,
+    Verifying,
+    WritingManifest,
+    /// Removing layers no model uses any more.
+    Cleanup,
+}
+
+impl PullPhase {
+    fn from_status(status: &str) -> Option<Self> {
+        match status {
+            "pulling manifest" => Some(PullPhase::PullingManifest),
+            "writing manifest" => Some(PullPhase::WritingManifest),
+            s if s.starts_with("pulling ") => Some(PullPhase::Pulling),
+            s if s.starts_with("verifying") => Some(PullPhase::Verifying),
+            s if s.starts_with("removing") => Some(PullPhase::Cleanup),
+            _ => None,
+        }
+    }
+}
+
+/// Folds Ollama's per-layer status lines into progress for the whole model.
+#[derive(Default)]
+struct PullTracker {
+    /// Completed and total bytes per layer digest.
+    layers: HashMap<String, (u64, u64)>,
+    phase: Option<PullPhase>,
+    /// Bytes fetched by this pull, not counting what was on disk already.
+    fetched: u64,
+    /// When the current speed sample started, and `fetched` at that point.
+    sample: Option<(Instant, u64)>,
+    bytes_per_second: Option<f64>,
+}
+
+impl PullTracker {
+    fn update(&mut self, progress: &PullProgress, now: Instant) {
+        if let Some(phase) = PullPhase::from_status(&progress.status) {
+            self.phase = Some(phase);
+        }
+        if let (Some(digest), Some(total)) = (&progress.digest, progress.total) {
+            let completed = progress.completed.unwrap_or(0).min(total);
+            match self.layers.get_mut(digest) {
+                Some(layer) => {
+                    self.fetched += completed.saturating_sub(layer.0);
+                    *layer = (completed.max(layer.0), total);
+                }
+                // A layer that was partly fetched before a pause first
+                // reports what is already on disk; that isn't speed
+                None => {
+                    self.layers.insert(digest.clone(), (completed, total));
+                }
+            }
+        }
+        self.sample_speed(now);
+    }
+
+    fn sample_speed(&mut self, now: Instant) {
+        let Some((started, fetched)) = self.sample else {
+            self.sample = Some((now, self.fetched));
+            return;
+        };
+        let elapsed = now.duration_since(started);
+        if elapsed < SPEED_SAMPLE {
+            return;
+        }
+        let rate = (self.fetched - fetched) as f64 / elapsed.as_secs_f64();
+        self.bytes_per_second = Some(match self.bytes_per_second {
+            Some(speed) => speed + SPEED_SMOOTHING * (rate - speed),
+            None => rate,
+        });
+        self.sample = Some((now, self.fetched));
+    }
+
+    fn apply(&self, download: &mut Download) {
+        let (completed, total) = self
+            .layers
+            .values()
+            .fold((0, 0), |(c, t), (layer_c, layer_t)| {
+                (c + layer_c, t + layer_t)
+            });
+        if total > 0 {
+            download.completed = Some(completed);
+            download.total = Some(total);
+        }
+        download.phase = self.phase;
+        // Speed only means something while layers are streaming
+        let speed = self
+            .bytes_per_second
+            .filter(|_| self.phase == Some(PullPhase::Pulling));
+        download.bytes_per_second = speed;
+        download.eta_seconds = speed
+            .filter(|s| *s > 0.0)
+            .map(|s| ((total - completed) as f64 / s).ceil() as u64);
+    }
+}
+
+/// Drop the rate of a download that stopped streaming.
+fn clear_rate(download: &mut Download) {
+    download.bytes_per_second = None;
+    download.eta_seconds = None;
+}
+
+fn next_id() -> String {
+    static COUNTER: AtomicU64 = AtomicU64::new(0);
+    format!(
+        "dl-{}-{}",
+        chrono::Utc::now().timestamp_millis(),
+        COUNTER.fetch_add(1, Ordering::Relaxed)
+    )
+}
+
+impl Downloads {
+    /// Whether any download is queued, running or paused.
+    pub fn has_pending(&self) -> Result<bool, AppError> {
+        Ok(self.0.lock()?.items.iter().any(|d| d.state.is_pending()))
+    }
+
+    /// Read the saved queue. Downloads interrupted by the last exit go back
+    /// to `Queued`; call `resume_pending` once the server is up.
+    pub fn load(app: &tauri::AppHandle) -> Self {
+        let path = storage::storage_dir(app).join(QUEUE_FILE);
+        let mut items: Vec<Download> = std::fs::read_to_string(&path)
+            .ok()
+            .and_then(|data| {
+                serde_json::from_str(&data)
+                    .map_err(|e| log::warn!("Ignoring unreadable download queue: {e}"))
+                    .ok()
+            })
+            .unwrap_or_default();
+        for item in &mut items {
+            if item.state == DownloadState::Downloading {
+                item.state = DownloadState::Queued;
+            }
+        }
+        Downloads(Mutex::new(Queue {
+            items,
+            active: None,
+        }))
+    }
+}
+
+fn save(app: &tauri::AppHandle, queue: &Queue) {
+    let pending: Vec<&Download> = queue
+        .items
+        .iter()
+        .filter(|d| d.state.is_pending())
+        .collect();
+    let path = storage::storage_dir(app).join(QUEUE_FILE);
+    let result = serde_json::to_string(&pending)
+        .map_err(AppError::from)
+        .and_then(|data| {
+            std::fs::write(&path, data)
+                .map_err(|e| AppError::io("Failed to save download queue", &path, e))
+        });
+    if let Err(e) = result {
+        log::warn!("{e}");
+    }
+}
+
+fn notify(app: &tauri::AppHandle, download: &Download) {
+    let _ = app.emit(DOWNLOAD_EVENT, download);
+}
+
+/// Start the next queued download unless one is already running.
+fn pump(app: &tauri::AppHandle) {
+    let downloads = app.state::<Downloads>();
+    let Ok(mut queue) = downloads.0.lock() else {
+        return;
+    };
+    if queue.active.is_some() {
+        return;
+    }
+    let Some(download) = queue
+        .items
+        .iter_mut()
+        .find(|d| d.state == DownloadState::Queued)
+    else {
+        return;
+    };
+
+    download.state = DownloadState::Downloading;
+    download.error = None;
+    let id = download.id.clone();
+    let model = download.model.clone();
+    notify(app, download);
+
+    let task_app = app.clone();
+    let task_id = id.clone();
+    // Commands calling this may run outside the async runtime
+    let task = tauri::async_runtime::spawn(async move {
+        let app = task_app;
+        let provider = app.state::<Endpoint>().provider(&app.state::<HttpClient>());
+        let tracker = Mutex::new(PullTracker::default());
+        let on_progress = |progress: PullProgress| {
+            let Ok(mut tracker) = tracker.lock() else {
+                return;
+            };
+            tracker.update(&progress, Instant::now());
+            let downloads = app.state::<Downloads>();
+            let Ok(mut queue) = downloads.0.lock() else {
+                return;
+            };
+            if let Ok(download) = queue.get_mut(&task_id) {
+                download.status = progress.status;
+                tracker.apply(download);
+                notify(&app, download);
+            }
+        };
+        let result = match preflight(&app, &model).await {
+            Ok(()) => provider.pull_model(&model, &on_progress).await,
+            Err(e) => Err(e),
+        };
+        finish(&app, &task_id, result);
+    });
+    queue.active = Some((id, task));
+    save(app, &queue);
+}
+
+/// Checks before a pull starts. Disk space is only known for the bundled
+/// Ollama, whose store is on this machine.
+async fn preflight(app: &tauri::AppHandle, model: &str) -> Result<(), AppError> {
+    if !app.state::<Endpoint>().uses_sidecar() {
+        return Ok(());
+    }
+    model_store::check_space_for_pull(&app.state::<HttpClient>(), model).await
+}
+
+fn finish(app: &tauri::AppHandle, id: &str, result: Result<(), AppError>) {
+    {
+        let downloads = app.state::<Downloads>();
+        let Ok(mut queue) = downloads.0.lock() else {
+            return;
+        };
+        if queue
+            .active
+            .as_ref()
+            .is_some_and(|(active, _)| active == id)
+        {
+            queue.active = None;
+        }
+        // Paused or cancelled while finishing up
+        let Some(index) = queue
+            .items
+            .iter()
+            .position(|d| d.id == id && d.state == DownloadState::Downloading)
+        else {
+            return;
+        };
+
+        match result {
+            Ok(()) => {
+                let mut download = queue.items.remove(index);
+                download.state = DownloadState::Done;
+                notify(app, &download);
+            }
+            Err(e) => {
+                log::warn!("Download of {} failed: {e}", queue.items[index].model);
+                let download = &mut queue.items[index];
+                download.state = DownloadState::Failed;
+                clear_rate(download);
+                download.error = Some(e);
+                notify(app, download);
+            }
+        }
+        save(app, &queue);
+    }
+    pump(app);
+}
+
+/// Restart downloads left over from the last session once the server
+/// answers.
+pub fn resume_pending(app: &tauri::AppHandle) {
+    let app = app.clone();
+    tauri::async_runtime::spawn(async move {
+        let has_pending = app
+            .state::<Downloads>()
+            .0
+            .lock()
+            .is_ok_and(|q| q.items.iter().any(|d| d.state == DownloadState::Queued));
+        if !has_pending {
+            return;
+        }
+        let provider = app.state::<Endpoint>().provider(&app.state::<HttpClient>());
+        if sidecar::wait_for_ready(provider.as_ref(), 60, 500).await {
+            pump(&app);
+        }
+    });
+}
+
+// ── Commands ────────────────────────────────────────────────────────
+
+/// Add `name` to the queue. If it is already queued, that download is
+/// returned instead.
+#[tauri::command]
+pub fn queue_pull(
+    app_handle: tauri::AppHandle,
+    downloads: State<'_, Downloads>,
+    writers: State<'_, StoreWriters>,
+    name: String,
+) -> Result<Download, AppError> {
+    let name = name.trim().to_string();
+    if name.is_empty() {
+        return Err(AppError::InvalidInput("Model name is empty".to_string()));
+    }
+
+    let download = {
+        // Pulls write blobs before their manifest
+        let _store = writers.hold_off_cleanup()?;
+        let mut queue = downloads.0.lock()?;
+        if let Some(existing) = queue
+            .items
+            .iter()
+            .find(|d| d.model == name && d.state.is_pending())
+        {
+            return Ok(existing.clone());
+        }
+        queue.items.retain(|d| d.model != name);
+        let download = Download {
+            id: next_id(),
+            model: name,
+            state: DownloadState::Queued,
+            status: String::new(),
+            phase: None,
+            completed: None,
+            total: None,
+            bytes_per_second: None,
+            eta_seconds: None,
+            error: None,
+        };
+        queue.items.push(download.clone());
+        save(&app_handle, &queue);
+        download
+    };
+    notify(&app_handle, &download);
+    pump(&app_handle);
+    Ok(download)
+}
+
+#[tauri::command]
+pub fn list_downloads(downloads: State<'_, Downloads>) -> Result<Vec<Download>, AppError> {
+    Ok(downloads.0.lock()?.items.clone())
+}
+
+/// Stop a download and drop it from the queue. Layers already fetched stay
+/// on disk until Ollama prunes unused blobs.
+#[tauri::command]
+pub fn cancel_pull(
+    app_handle: tauri::AppHandle,
+    downloads: State<'_, Downloads>,
+    id: String,
+) -> Result<(), AppError> {
+    {
+        let mut queue = downloads.0.lock()?;
+        queue.stop(&id);
+        let index = queue
+            .items
+            .iter()
+            .position(|d| d.id == id)
+            .ok_or_else(|| AppError::InvalidInput(format!("No download with id {id}")))?;
+        let mut download = queue.items.remove(index);
+        download.state = DownloadState::Cancelled;
+        notify(&app_handle, &download);
+        save(&app_handle, &queue);
+    }
+    pump(&app_handle);
+    Ok(())
+}
+
+#[tauri::command]
+pub fn pause_pull(
+    app_handle: tauri::AppHandle,
+    downloads: State<'_, Downloads>,
+    id: String,
+) -> Result<(), AppError> {
+    {
+        let mut queue = downloads.0.lock()?;
+        queue.stop(&id);
+        let download = queue.get_mut(&id)?;
+        if !matches!(
+            download.state,
+            DownloadState::Queued | DownloadState::Downloading
+        ) {
+            return Ok(());
+        }
+        download.state = DownloadState::Paused;
+        clear_rate(download);
+        notify(&app_handle, download);
+        save(&app_handle, &queue);
+    }
+    pump(&app_handle);
+    Ok(())
+}
+
+/// Put a paused or failed download back in the queue.
+#[tauri::command]
+pub fn resume_pull(
+    app_handle: tauri::AppHandle,
+    downloads: State<'_, Downloads>,
+    writers: State<'_, StoreWriters>,
+    id: String,
+) -> Result<(), AppError> {
+    {
+        let _store = writers.hold_off_cleanup()?;
+        let mut queue = downloads.0.lock()?;
+        let download = queue.get_mut(&id)?;
+        if !matches!(
+            download.state,
+            DownloadState::Paused | DownloadState::Failed
+        ) {
+            return Ok(());
+        }
+        download.state = DownloadState::Queued;
+        download.error = None;
+        notify(&app_handle, download);
+        save(&app_handle, &queue);
+    }
+    pump(&app_handle);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn only_pending_downloads_are_saved_and_errors_are_not_read_back() {
+        let download = Download {
+            id: "dl-1".to_string(),
+            model: "qwen3:8b".to_string(),
+            state: DownloadState::Failed,
+            status: "pulling 3d0b790534fe".to_string(),
+            phase: Some(PullPhase::Pulling),
+            completed: Some(10),
+            total: Some(100),
+            bytes_per_second: Some(1e6),
+            eta_seconds: Some(90),
+            error: Some(AppError::ConnectionLost("reset".to_string())),
+        };
+        assert!(!download.state.is_pending());
+
+        let json = serde_json::to_value(&download).unwrap();
+        assert_eq!(json["state"], "failed");
+        assert_eq!(json["error"]["kind"], "connectionLost");
+
+        let restored: Download = serde_json::from_value(json).unwrap();
+        assert_eq!(restored.error, None);
+        assert_eq!(restored.completed, Some(10));
+        assert_eq!(restored.eta_seconds, None);
+    }
+
+    fn line(status: &str, digest: &str, completed: u64, total: u64) -> PullProgress {
+        PullProgress {
+            status: status.to_string(),
+            digest: Some(digest.to_string()),
+            completed: Some(completed),
+            total: Some(total),
+        }
+    }
+
+    #[test]
+    fn layers_add_up_and_speed_ignores_bytes_already_on_disk() {
+        let start = Instant::now();
+        let at = |ms: u64| start + Duration::from_millis(ms);
+        let mut tracker = PullTracker::default();
+        // Layer a was half done before a pause
+        tracker.update(&line("pulling a", "sha256:a", 500, 1000), at(0));
+        tracker.update(&line("pulling b", "sha256:b", 0, 3000), at(0));
+        tracker.update(&line("pulling b", "sha256:b", 500, 3000), at(400));
+        tracker.update(&line("pulling a", "sha256:a", 1000, 1000), at(1000));
+
+        let mut download = Download {
+            id: "dl-1".to_string(),
+            model: "tiny".to_string(),
+            state: DownloadState::Downloading,
+            status: String::new(),
+            phase: None,
+            completed: None,
+            total: None,
+            bytes_per_second: None,
+            eta_seconds: None,
+            error: None,
+        };
+        tracker.apply(&mut download);
+        assert_eq!(download.phase, Some(PullPhase::Pulling));
+        assert_eq!(
+            (download.completed, download.total),
+            (Some(1500), Some(4000))
+        );
+        assert_eq!(download.bytes_per_second, Some(1000.0));
+        assert_eq!(download.eta_seconds, Some(3));
+
+        // Later samples are smoothed rather than taken as is
+        tracker.update(&line("pulling b", "sha256:b", 2500, 3000), at(2000));
+        assert_eq!(tracker.bytes_per_second, Some(1300.0));
+
+        tracker.update(&line("verifying sha256 digest", "", 0, 0), at(2500));
+        tracker.apply(&mut download);
+        assert_eq!(download.phase, Some(PullPhase::Verifying));
+        assert_eq!(download.eta_seconds, None);
+    }
+}
@@ -0,0 +1,105 @@
+//! Text embeddings through the configured provider, batched and
+//! L2-normalised so cosine similarity is a plain dot product.
+
+use crate::endpoint::Endpoint;
+use crate::error::AppError;
+use crate::http::HttpClient;
+use crate::provider::LlmProvider;
+use serde::Serialize;
+use tauri::ipc::Channel;
+use tauri::State;
+
+/// Inputs sent per request. Large enough to amortise the round trip, small
+/// enough that one batch finishes well inside the request timeout.
+pub const EMBED_BATCH_SIZE: usize = 32;
+
+#[derive(Debug, Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct EmbedProgress {
+    pub completed: usize,
+    pub total: usize,
+}
+
+/// Embed `inputs` in batches, calling `progress(completed, total)` after
+/// each one. Vectors come back in input order, normalised to unit length.
+pub async fn embed_all(
+    provider: &dyn LlmProvider,
+    model: &str,
+    inputs: &[String],
+    mut progress: impl FnMut(usize, usize),
+) -> Result<Vec<Vec<f32>>, AppError> {
+    let mut vectors = Vec::with_capacity(inputs.len());
+    for batch in inputs.chunks(EMBED_BATCH_SIZE) {
+        let embedded = provider.embed(model, batch).await?;
+        if embedded.len() != batch.len() {
+            return Err(AppError::BadResponse(format!(
+                "Expected {} embeddings, got {}",
+                batch.len(),
+                embedded.len()
+            )));
+        }
+        vectors.extend(embedded.into_iter().map(normalize));
+        progress(vectors.len(), inputs.len());
+    }
+    Ok(vectors)
+}
+
+/// Scale to unit length. A zero vector is returned unchanged.
+pub fn normalize(mut vector: Vec<f32>) -> Vec<f32> {
+    let norm = vector.iter().map(|x| x * x).sum::<f32>().sqrt();
+    if norm > 0.0 {
+        vector.iter_mut().for_each(|x| *x /= norm);
+    }
+    vector
+}
+
+/// Neither API says whether a model only embeds, so go by the architecture
+/// (BERT-style encoders) and the naming convention (`nomic-embed-text`,
+/// `qwen3-embedding`, ...).
+pub fn is_embedding_model(name: &str, family: &str) -> bool {
+    family.to_lowercase().contains("bert") || name.to_lowercase().contains("embed")
+}
+
+#[tauri::command]
+pub async fn embed(
+    endpoint: State<'_, Endpoint>,
+    http: State<'_, HttpClient>,
+    model: String,
+    inputs: Vec<String>,
+    on_progress: Channel<EmbedProgress>,
+) -> Result<Vec<Vec<f32>>, AppError> {
+    if model.trim().is_empty() {
+        return Err(AppError::InvalidInput(
+            "No embedding model selected".to_string(),
+        ));
+    }
+
+    let provider = endpoint.provider(&http);
+    embed_all(provider.as_ref(), &model, &inputs, |completed, total| {
+        // A single batch finishes in one step; there's nothing to report
+        if total > EMBED_BATCH_SIZE {
+            let _ = on_progress.send(EmbedProgress { completed, total });
+        }
+    })
+    .await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn normalizes_to_unit_length() {
+        assert_eq!(normalize(vec![3.0, 4.0]), vec![0.6, 0.8]);
+        assert_eq!(normalize(vec![0.0, 0.0]), vec![0.0, 0.0]);
+    }
+
+    #[test]
+    fn detects_embedding_models() {
+        assert!(is_embedding_model("nomic-embed-text:latest", "nomic-bert"));
+        assert!(is_embedding_model("bge-m3:latest", "bert"));
+        assert!(is_embedding_model("qwen3-embedding:0.6b", "qwen3"));
+        assert!(!is_embedding_model("qwen3:8b", "qwen3"));
+        assert!(!is_embedding_model("llama3.2:latest", "llama"));
+    }
+}
@@ -0,0 +1,245 @@
+use crate::error::AppError;
+use crate::http::HttpClient;
+use crate::ollama::OllamaProvider;
+use crate::openai::OpenAiProvider;
+use crate::provider::{LlmProvider, ProviderKind};
+use crate::sidecar;
+use crate::storage;
+use serde::{Deserialize, Serialize};
+use std::net::IpAddr;
+use std::sync::{Mutex, PoisonError};
+use tauri::State;
+
+pub const DEFAULT_ENDPOINT: &str = "http://localhost:11434";
+
+/// Kept under its original name so endpoints saved before providers
+/// existed still load.
+const ENDPOINT_FILE: &str = "ollama-endpoint.json";
+
+/// Which server every chat and model command talks to, and how. Persisted in
+/// the app data dir so a remote or non-default endpoint survives restarts.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct EndpointConfig {
+    pub url: String,
+    #[serde(default)]
+    pub provider: ProviderKind,
+    /// Sent as a bearer token; only OpenAI-compatible servers use it.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub api_key: Option<String>,
+}
+
+impl Default for EndpointConfig {
+    fn default() -> Self {
+        EndpointConfig {
+            url: DEFAULT_ENDPOINT.to_string(),
+            provider: ProviderKind::Ollama,
+            api_key: None,
+        }
+    }
+}
+
+impl EndpointConfig {
+    /// Whether this is an Ollama server on this machine, i.e. the bundled sidecar.
+    pub fn uses_sidecar(&self) -> bool {
+        self.provider == ProviderKind::Ollama && is_local(&self.url)
+    }
+
+    /// The `host:port` the sidecar listens on, handed to it as `OLLAMA_HOST`
+    /// so a local endpoint on a non-default port still reaches it.
+    pub fn sidecar_host(&self) -> Option<String> {
+        if !self.uses_sidecar() {
+            return None;
+        }
+        let parsed = reqwest::Url::parse(&self.url).ok()?;
+        Some(format!(
+            "{}:{}",
+            parsed.host_str()?,
+            parsed.port_or_known_default()?
+        ))
+    }
+}
+
+pub struct Endpoint(Mutex<EndpointConfig>);
+
+impl Endpoint {
+    /// Load the persisted endpoint, falling back to the local default.
+    pub fn load(app: &tauri::AppHandle) -> Self {
+        let config = std::fs::read_to_string(storage::storage_dir(app).join(ENDPOINT_FILE))
+            .ok()
+            .and_then(|data| serde_json::from_str::<EndpointConfig>(&data).ok())
+            .and_then(|stored| {
+                let url = normalize(&stored.url).ok()?;
+                Some(EndpointConfig { url, ..stored })
+            })
+            .unwrap_or_default();
+        Endpoint(Mutex::new(config))
+    }
+
+    pub fn config(&self) -> EndpointConfig {
+        // The config is replaced whole, so a panicked writer can't leave it half-set
+        self.0.lock().unwrap_or_else(PoisonError::into_inner).clone()
+    }
+
+    pub fn base(&self) -> String {
+        self.config().url
+    }
+
+    pub fn uses_sidecar(&self) -> bool {
+        self.config().uses_sidecar()
+    }
+
+    /// The provider for the current config. Cheap: it only clones the shared
+    /// HTTP client.
+    pub fn provider(&self, http: &HttpClient) -> Box<dyn LlmProvider> {
+        let config = self.config();
+        match config.provider {
+            ProviderKind::Ollama => Box::new(OllamaProvider::new(http.clone(), config.url)),
+            ProviderKind::OpenAi => Box::new(OpenAiProvider::new(
+                http.clone(),
+                config.url,
+                config.api_key,
+            )),
+        }
+    }
+}
+
+fn normalize(url: &str) -> Result<String, AppError> {
+    let parsed = reqwest::Url::parse(url.trim())
+        .map_err(|e| AppError::InvalidInput(format!("Invalid endpoint URL: {e}")))?;
+    if !matches!(parsed.scheme(), "http" | "https") {
+        return Err(AppError::InvalidInput(
+            "Endpoint must be an http:// or https:// URL".to_string(),
+        ));
+    }
+    if parsed.host_str().is_none() {
+        return Err(AppError::InvalidInput(
+            "Endpoint URL has no host".to_string(),
+        ));
+    }
+    Ok(parsed.as_str().trim_end_matches('/').to_string())
+}
+
+fn is_local(url: &str) -> bool {
+    let Ok(parsed) = reqwest::Url::parse(url) else {
+        return false;
+    };
+    match parsed.host_str() {
+        Some("localhost") => true,
+        Some(host) => host
+            .trim_start_matches('[')
+            .trim_end_matches(']')
+            .parse::<IpAddr>()
+            .map(|ip| ip.is_loopback() || ip.is_unspecified())
+            .unwrap_or(false),
+        None => false,
+    }
+}
+
+#[tauri::command]
+pub fn get_endpoint(endpoint: State<'_, Endpoint>) -> Result<EndpointConfig, AppError> {
+    Ok(endpoint.config())
+}
+
+/// Switch to a new endpoint, persist it, and start or stop the bundled
+/// sidecar when moving between a local Ollama and any other server.
+#[tauri::command]
+pub fn set_endpoint(
+    app_handle: tauri::AppHandle,
+    endpoint: State<'_, Endpoint>,
+    config: EndpointConfig,
+) -> Result<EndpointConfig, AppError> {
+    let config = EndpointConfig {
+        url: normalize(&config.url)?,
+        api_key: config.api_key.filter(|key| !key.trim().is_empty()),
+        ..config
+    };
+
+    let data = serde_json::to_string(&config)?;
+    let path = storage::storage_dir(&app_handle).join(ENDPOINT_FILE);
+    std::fs::write(&path, data).map_err(|e| AppError::io("Failed to save endpoint", &path, e))?;
+
+    let had_host = endpoint.config().sidecar_host();
+    *endpoint.0.lock()? = config.clone();
+
+    match (had_host, config.sidecar_host()) {
+        (Some(_), None) => sidecar::stop_ollama(&app_handle),
+        (had, Some(host)) if had.as_ref() != Some(&host) => {
+            // A new port means the running sidecar listens in the wrong place
+            if had.is_some() {
+                sidecar::stop_ollama(&app_handle);
+            }
+            if let Err(e) = sidecar::start_ollama(&app_handle) {
+                log::error!("Failed to start Ollama: {e}");
+            }
+        }
+        _ => {}
+    }
+
+    Ok(config)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn normalizes_endpoint_urls() {
+        assert_eq!(
+            normalize(" http://192.168.1.20:11434/ ").unwrap(),
+            "http://192.168.1.20:11434"
+        );
+        assert_eq!(
+            normalize("https://gpu.lan/ollama/").unwrap(),
+            "https://gpu.lan/ollama"
+        );
+        assert!(normalize("ftp://gpu.lan").is_err());
+        assert!(normalize("localhost:11434").is_err());
+    }
+
+    #[test]
+    fn loads_endpoints_saved_before_providers() {
+        let stored: EndpointConfig =
+            serde_json::from_str(r#"{"url":"http://gpu.lan:11434"}"#).unwrap();
+        assert_eq!(stored.provider, ProviderKind::Ollama);
+        assert_eq!(stored.api_key, None);
+
+        let openai = EndpointConfig {
+            url: "http://localhost:8080".to_string(),
+            provider: ProviderKind::OpenAi,
+            api_key: None,
+        };
+        assert!(!openai.uses_sidecar());
+        assert!(EndpointConfig::default().uses_sidecar());
+    }
+
+    #[test]
+    fn sidecar_listens_on_the_endpoint_port() {
+        let local = |url: &str| EndpointConfig {
+            url: url.to_string(),
+            ..Default::default()
+        };
+        assert_eq!(
+            EndpointConfig::default().sidecar_host().as_deref(),
+            Some("localhost:11434")
+        );
+        assert_eq!(
+            local("http://localhost:8080").sidecar_host().as_deref(),
+            Some("localhost:8080")
+        );
+        assert_eq!(
+            local("http://[::1]:9000").sidecar_host().as_deref(),
+            Some("[::1]:9000")
+        );
+        assert_eq!(local("http://gpu.lan:11434").sidecar_host(), None);
+    }
+
+    #[test]
+    fn detects_local_endpoints() {
+        assert!(is_local("http://localhost:11434"));
+        assert!(is_local("http://127.0.0.1:8080"));
+        assert!(is_local("http://[::1]:11434"));
+        assert!(!is_local("http://192.168.1.20:11434"));
+        assert!(!is_local("http://gpu.lan:11434"));
+    }
+}
@@ -0,0 +1,335 @@
+//! The error type returned by every Tauri command. It serialises to
+//! `{ kind, message, details? }`, where `kind` is a stable code the frontend
+//! can branch on to offer a fix.
+
+use serde::ser::{Serialize, SerializeStruct, Serializer};
+use std::fmt;
+use std::path::Path;
+use std::sync::PoisonError;
+use std::time::Duration;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum AppError {
+    /// Connection refused or host unreachable.
+    OllamaUnreachable(String),
+    /// No connection could be opened within the connect timeout.
+    ConnectTimeout(Duration),
+    /// The request as a whole exceeded its timeout.
+    Timeout(Duration),
+    /// A streamed body produced nothing for longer than the idle timeout.
+    Stalled(Duration),
+    /// The connection dropped while a response was being read.
+    ConnectionLost(String),
+    /// Ollama doesn't have the requested model. Holds Ollama's message.
+    ModelNotFound(String),
+    /// Any other error reported by Ollama.
+    Ollama {
+        status: Option<u16>,
+        message: String,
+    },
+    /// Ollama answered with something we couldn't parse.
+    BadResponse(String),
+    /// The endpoint isn't the bundled Ollama, so the sidecar can't be managed.
+    RemoteEndpoint,
+    /// The configured provider has no equivalent of this operation.
+    Unsupported(String),
+    /// The bundled Ollama binary is missing or failed to start.
+    Sidecar(String),
+    /// The model kept calling tools past the round limit.
+    ToolRoundsExceeded(usize),
+    InvalidInput(String),
+    NotFound(String),
+    PermissionDenied(String),
+    DiskFull(String),
+    /// Checked before writing: `required` bytes won't fit in `available`.
+    InsufficientSpace {
+        path: String,
+        required: u64,
+        available: u64,
+    },
+    Io {
+        message: String,
+        details: String,
+    },
+    Internal(String),
+}
+
+impl AppError {
+    /// Stable identifier sent to the frontend as `kind`.
+    pub fn kind(&self) -> &'static str {
+        match self {
+            AppError::OllamaUnreachable(_) => "ollamaUnreachable",
+            AppError::ConnectTimeout(_) => "connectTimeout",
+            AppError::Timeout(_) => "timeout",
+            AppError::Stalled(_) => "stalled",
+            AppError::ConnectionLost(_) => "connectionLost",
+            AppError::ModelNotFound(_) => "modelNotFound",
+            AppError::Ollama { .. } => "ollamaError",
+            AppError::BadResponse(_) => "badResponse",
+            AppError::RemoteEndpoint => "remoteEndpoint",
+            AppError::Unsupported(_) => "unsupported",
+            AppError::Sidecar(_) => "sidecar",
+            AppError::ToolRoundsExceeded(_) => "toolRoundsExceeded",
+            AppError::InvalidInput(_) => "invalidInput",
+            AppError::NotFound(_) => "notFound",
+            AppError::PermissionDenied(_) => "permissionDenied",
+            AppError::DiskFull(_) => "diskFull",
+            AppError::InsufficientSpace { .. } => "insufficientSpace",
+            AppError::Io { .. } => "io",
+            AppError::Internal(_) => "internal",
+        }
+    }
+
+    /// Underlying cause, for logs and an expandable "details" line in the UI.
+    pub fn details(&self) -> Option<String> {
+        match self {
+            AppError::OllamaUnreachable(details)
+            | AppError::ModelNotFound(details)
+            | AppError::ConnectionLost(details)
+            | AppError::BadResponse(details)
+            | AppError::Io { details, .. } => Some(details.clone()),
+            AppError::Ollama {
+                status: Some(status),
+                ..
+            } => Some(format!("HTTP {status}")),
+            AppError::NotFound(path)
+            | AppError::PermissionDenied(path)
+            | AppError::DiskFull(path)
+            | AppError::InsufficientSpace { path, .. } => Some(path.clone()),
+            _ => None,
+        }
+    }
+
+    /// Map a filesystem error, keeping the cases the UI can act on distinct.
+    pub fn io(message: impl Into<String>, path: impl AsRef<Path>, e: std::io::Error) -> Self {
+        let path = path.as_ref().to_string_lossy().to_string();
+        match e.kind() {
+            std::io::ErrorKind::NotFound => AppError::NotFound(path),
+            std::io::ErrorKind::PermissionDenied => AppError::PermissionDenied(path),
+            _ if is_disk_full(&e) => AppError::DiskFull(path),
+            _ => AppError::Io {
+                message: message.into(),
+                details: format!("{path}: {e}"),
+            },
+        }
+    }
+
+    /// Map a failed server response. Ollama reports errors as
+    /// `{"error": "..."}`, OpenAI-compatible servers as
+    /// `{"error": {"message": "..."}}`. Only a message saying so means a
+    /// missing model here; see `from_model_response` for a 404.
+    pub fn from_response(status: Option<reqwest::StatusCode>, body: &str) -> Self {
+        Self::response_error(status, body, false)
+    }
+
+    /// Like `from_response`, for requests that name a model (chat, pull,
+    /// show, delete…), where a 404 means that model isn't there.
+    pub fn from_model_response(status: Option<reqwest::StatusCode>, body: &str) -> Self {
+        Self::response_error(status, body, true)
+    }
+
+    fn response_error(
+        status: Option<reqwest::StatusCode>,
+        body: &str,
+        model_addressed: bool,
+    ) -> Self {
+        let message = serde_json::from_str::<serde_json::Value>(body)
+            .ok()
+            .and_then(|v| {
+                let error = v.get("error")?;
+                error
+                    .as_str()
+                    .or_else(|| error.get("message")?.as_str())
+                    .map(str::to_string)
+            })
+            .unwrap_or_else(|| body.trim().to_string());
+
+        let not_found = (model_addressed && status == Some(reqwest::StatusCode::NOT_FOUND))
+            || (message.contains("model") && message.contains("not found"));
+        if not_found {
+            AppError::ModelNotFound(message)
+        } else {
+            AppError::Ollama {
+                status: status.map(|s| s.as_u16()),
+                message,
+            }
+        }
+    }
+}
+
+/// Whether an I/O error means the disk is full: ENOSPC on Unix,
+/// ERROR_HANDLE_DISK_FULL or ERROR_DISK_FULL on Windows.
+/// `ErrorKind::StorageFull` says this directly but needs Rust 1.83.
+fn is_disk_full(e: &std::io::Error) -> bool {
+    #[cfg(unix)]
+    const CODES: &[i32] = &[28];
+    #[cfg(windows)]
+    const CODES: &[i32] = &[39, 112];
+    #[cfg(not(any(unix, windows)))]
+    const CODES: &[i32] = &[];
+    e.raw_os_error().is_some_and(|code| CODES.contains(&code))
+}
+
+impl fmt::Display for AppError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AppError::OllamaUnreachable(_) => write!(f, "Cannot reach Ollama"),
+            AppError::ConnectTimeout(d) => {
+                write!(f, "Timed out connecting to Ollama after {}s", d.as_secs())
+            }
+            AppError::Timeout(d) => write!(f, "Ollama did not respond within {}s", d.as_secs()),
+            AppError::Stalled(d) => write!(
+                f,
+                "Ollama stopped responding (no data for {}s)",
+                d.as_secs()
+            ),
+            AppError::ConnectionLost(_) => write!(f, "Connection to Ollama lost"),
+            AppError::ModelNotFound(_) => write!(f, "Model not found"),
+            AppError::Ollama { message, .. } => write!(f, "Ollama error: {message}"),
+            AppError::BadResponse(_) => write!(f, "Bad response from Ollama"),
+            AppError::RemoteEndpoint => write!(
+                f,
+                "The configured server is not the bundled Ollama and cannot be managed from here"
+            ),
+            AppError::Unsupported(message) => write!(f, "{message}"),
+            AppError::Sidecar(message) => write!(f, "{message}"),
+            AppError::ToolRoundsExceeded(rounds) => {
+                write!(f, "Stopped after {rounds} rounds of tool calls")
+            }
+            AppError::InvalidInput(message) => write!(f, "{message}"),
+            AppError::NotFound(_) => write!(f, "File or folder not found"),
+            AppError::PermissionDenied(_) => write!(f, "Permission denied"),
+            AppError::DiskFull(_) => write!(f, "Disk is full"),
+            AppError::InsufficientSpace {
+                required,
+                available,
+                ..
+            } => {
+                const GB: f64 = 1024.0 * 1024.0 * 1024.0;
+                write!(
+                    f,
+                    "Not enough disk space: needs {:.1} GB, {:.1} GB free",
+                    *required as f64 / GB,
+                    *available as f64 / GB
+                )
+            }
+            AppError::Io { message, .. } => write!(f, "{message}"),
+            AppError::Internal(message) => write!(f, "Internal error: {message}"),
+        }
+    }
+}
+
+impl std::error::Error for AppError {}
+
+impl Serialize for AppError {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let details = self.details();
+        let mut state =
+            serializer.serialize_struct("AppError", if details.is_some() { 3 } else { 2 })?;
+        state.serialize_field("kind", self.kind())?;
+        state.serialize_field("message", &self.to_string())?;
+        match details {
+            Some(details) => state.serialize_field("details", &details)?,
+            None => state.skip_field("details")?,
+        }
+        state.end()
+    }
+}
+
+impl<T> From<PoisonError<T>> for AppError {
+    fn from(_: PoisonError<T>) -> Self {
+        AppError::Internal("State lock poisoned".to_string())
+    }
+}
+
+impl From<serde_json::Error> for AppError {
+    fn from(e: serde_json::Error) -> Self {
+        AppError::Internal(e.to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn serialises_kind_message_and_details() {
+        let err = AppError::io(
+            "Failed to save chats",
+            "/data/chats.json",
+            std::io::Error::other("boom"),
+        );
+        assert_eq!(
+            serde_json::to_value(&err).unwrap(),
+            json!({
+                "kind": "io",
+                "message": "Failed to save chats",
+                "details": "/data/chats.json: boom",
+            })
+        );
+        assert_eq!(
+            serde_json::to_value(AppError::RemoteEndpoint).unwrap()["kind"],
+            "remoteEndpoint"
+        );
+        assert!(serde_json::to_value(AppError::RemoteEndpoint)
+            .unwrap()
+            .get("details")
+            .is_none());
+    }
+
+    #[test]
+    fn maps_io_error_kinds() {
+        let not_found = std::io::Error::from(std::io::ErrorKind::NotFound);
+        assert_eq!(
+            AppError::io("Cannot read file", "/x", not_found),
+            AppError::NotFound("/x".to_string())
+        );
+        #[cfg(unix)]
+        let full = std::io::Error::from_raw_os_error(28);
+        #[cfg(windows)]
+        let full = std::io::Error::from_raw_os_error(112);
+        assert_eq!(
+            AppError::io("Failed to save", "/x", full).kind(),
+            "diskFull"
+        );
+    }
+
+    #[test]
+    fn recognises_missing_models_in_ollama_errors() {
+        let err = AppError::from_model_response(
+            Some(reqwest::StatusCode::NOT_FOUND),
+            r#"{"error":"model \"llama9\" not found, try pulling it first"}"#,
+        );
+        assert_eq!(
+            err,
+            AppError::ModelNotFound("model \"llama9\" not found, try pulling it first".to_string())
+        );
+
+        let err = AppError::from_response(
+            Some(reqwest::StatusCode::INTERNAL_SERVER_ERROR),
+            r#"{"error":"out of memory"}"#,
+        );
+        assert_eq!(err.kind(), "ollamaError");
+        assert_eq!(err.to_string(), "Ollama error: out of memory");
+        assert_eq!(err.details().as_deref(), Some("HTTP 500"));
+
+        let err = AppError::from_response(
+            Some(reqwest::StatusCode::BAD_REQUEST),
+            r#"{"error":{"message":"context length exceeded","type":"invalid_request_error"}}"#,
+        );
+        assert_eq!(err.to_string(), "Ollama error: context length exceeded");
+    }
+
+    #[test]
+    fn a_404_is_a_missing_model_only_where_a_model_is_named() {
+        let err =
+            AppError::from_response(Some(reqwest::StatusCode::NOT_FOUND), "404 page not found");
+        assert_eq!(err.kind(), "ollamaError");
+        let err = AppError::from_model_response(
+            Some(reqwest::StatusCode::NOT_FOUND),
+            "404 page not found",
+        );
+        assert_eq!(err.kind(), "modelNotFound");
+    }
+}
@@ -0,0 +1,305 @@
+//! Predicts whether a model will run on this machine, from its weight size,
+//! the context it will be used with and the memory `sysinfo` reports, and
+//! ranks a bundled catalog of popular models by that prediction.
+//!
+//! The estimate is deliberately rough and errs on the high side: weights,
+//! plus an f16 KV cache sized from the parameter count, plus a fixed
+//! overhead for compute buffers. GPU memory isn't considered separately.
+
+use crate::endpoint::Endpoint;
+use crate::error::AppError;
+use crate::http::HttpClient;
+use crate::model_info::Capabilities;
+use serde::{Deserialize, Serialize};
+use std::sync::OnceLock;
+use tauri::State;
+
+/// Ollama's default context window when a model doesn't set `num_ctx`.
+pub const DEFAULT_CONTEXT: u64 = 4096;
+
+/// KV cache per token per billion parameters. Matches an f16 cache for
+/// Llama 3 8B (128 KiB per token) and overestimates bigger GQA models.
+const KV_BYTES_PER_TOKEN_PER_B: f64 = 16.0 * 1024.0;
+
+/// Compute buffers and runtime on top of weights and cache.
+const OVERHEAD_BYTES: u64 = 512 * 1024 * 1024;
+
+/// Share of total RAM a model can take before the OS and other apps are
+/// squeezed so hard that loading fails or the machine thrashes.
+const USABLE_FRACTION: f64 = 0.85;
+
+#[derive(Debug, Serialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub enum FitVerdict {
+    /// Fits in memory that is free right now.
+    Fits,
+    /// Fits in RAM but not in what's free, so other apps get swapped out.
+    Slow,
+    /// Larger than this machine can hold.
+    WontFit,
+}
+
+#[derive(Debug, Serialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct SystemMemory {
+    pub total_bytes: u64,
+    pub available_bytes: u64,
+}
+
+impl SystemMemory {
+    pub fn current() -> Self {
+        let mut sys = sysinfo::System::new();
+        sys.refresh_memory();
+        SystemMemory {
+            total_bytes: sys.total_memory(),
+            available_bytes: sys.available_memory(),
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct FitEstimate {
+    pub verdict: FitVerdict,
+    pub required_bytes: u64,
+    /// The context the estimate assumes, in tokens.
+    pub context_length: u64,
+    #[serde(flatten)]
+    pub memory: SystemMemory,
+}
+
+/// Approximate bits per weight for Ollama's quantization levels, used when
+/// only the parameter count is known.
+fn bits_per_weight(quantization: &str) -> f64 {
+    match quantization.to_ascii_uppercase().as_str() {
+        q if q.starts_with("Q2") => 3.4,
+        q if q.starts_with("Q3") => 3.9,
+        q if q.starts_with("Q4") => 4.9,
+        q if q.starts_with("Q5") => 5.7,
+        q if q.starts_with("Q6") => 6.6,
+        q if q.starts_with("Q8") => 8.5,
+        "F32" => 32.0,
+        _ => 16.0,
+    }
+}
+
+/// Parse Ollama's `parameter_size`, like "8.0B" or "137M", into billions.
+pub fn parse_parameter_size(size: &str) -> Option<f64> {
+    let size = size.trim();
+    let (number, per_billion) = match size.chars().last()?.to_ascii_uppercase() {
+        'B' => (&size[..size.len() - 1], 1.0),
+        'M' => (&size[..size.len() - 1], 1e3),
+        'K' => (&size[..size.len() - 1], 1e6),
+        _ => (size, 1e9),
+    };
+    number.parse::<f64>().ok().map(|n| n / per_billion)
+}
+
+/// What the estimator needs to know about a model.
+#[derive(Debug, Clone, Copy)]
+pub struct ModelSpec<'a> {
+    /// Size of the weights on disk, or 0 if unknown.
+    pub size_bytes: u64,
+    pub parameters_billion: f64,
+    pub quantization_level: &'a str,
+    /// Longest context the model supports, if known.
+    pub max_context: Option<u64>,
+}
+
+pub fn estimate(spec: ModelSpec, context: Option<u64>, memory: SystemMemory) -> FitEstimate {
+    let weights = if spec.size_bytes > 0 {
+        spec.size_bytes
+    } else {
+        (spec.parameters_billion * 1e9 * bits_per_weight(spec.quantization_level) / 8.0) as u64
+    };
+    let context_length = context
+        .unwrap_or(DEFAULT_CONTEXT)
+        .min(spec.max_context.unwrap_or(u64::MAX));
+    let kv_cache =
+        (spec.parameters_billion * KV_BYTES_PER_TOKEN_PER_B * context_length as f64) as u64;
+    let required_bytes = weights + kv_cache + OVERHEAD_BYTES;
+
+    let verdict = if required_bytes as f64 > memory.total_bytes as f64 * USABLE_FRACTION {
+        FitVerdict::WontFit
+    } else if required_bytes > memory.available_bytes {
+        FitVerdict::Slow
+    } else {
+        FitVerdict::Fits
+    };
+
+    FitEstimate {
+        verdict,
+        required_bytes,
+        context_length,
+        memory,
+    }
+}
+
+// ── Catalog ─────────────────────────────────────────────────────────
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct CatalogModel {
+    pub name: String,
+    pub description: String,
+    pub parameter_size: String,
+    pub parameters_billion: f64,
+    pub quantization_level: String,
+    /// Download size of the default tag.
+    pub size_bytes: u64,
+    pub context_length: u64,
+    pub capabilities: Capabilities,
+}
+
+fn catalog() -> &'static [CatalogModel] {
+    static CATALOG: OnceLock<Vec<CatalogModel>> = OnceLock::new();
+    CATALOG.get_or_init(|| {
+        serde_json::from_str(include_str!("model_catalog.json"))
+            .expect("bundled model catalog is valid")
+    })
+}
+
+#[derive(Debug, Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct Recommendation {
+    #[serde(flatten)]
+    pub model: CatalogModel,
+    pub fit: FitEstimate,
+}
+
+/// Catalog models that fit now first, then ones that would run slowly, then
+/// ones too large; largest first within each group.
+fn rank(context: Option<u64>, memory: SystemMemory) -> Vec<Recommendation> {
+    let mut ranked: Vec<Recommendation> = catalog()
+        .iter()
+        .map(|model| Recommendation {
+            fit: estimate(
+                ModelSpec {
+                    size_bytes: model.size_bytes,
+                    parameters_billion: model.parameters_billion,
+                    quantization_level: &model.quantization_level,
+                    max_context: Some(model.context_length),
+                },
+                context,
+                memory,
+            ),
+            model: model.clone(),
+        })
+        .collect();
+    ranked.sort_by(|a, b| {
+        let order = |v: FitVerdict| v as u8;
+        order(a.fit.verdict).cmp(&order(b.fit.verdict)).then(
+            b.model
+                .parameters_billion
+                .total_cmp(&a.model.parameters_billion),
+        )
+    });
+    ranked
+}
+
+// ── Commands ────────────────────────────────────────────────────────
+
+#[tauri::command]
+pub fn get_system_memory() -> Result<SystemMemory, AppError> {
+    Ok(SystemMemory::current())
+}
+
+/// Predict how an installed model will run with `context_length` tokens of
+/// context (its own `num_ctx`, or Ollama's default, if not given).
+#[tauri::command]
+pub async fn estimate_model_fit(
+    endpoint: State<'_, Endpoint>,
+    http: State<'_, HttpClient>,
+    name: String,
+    context_length: Option<u64>,
+) -> Result<FitEstimate, AppError> {
+    let model = endpoint.provider(&http).show_model(&name).await?;
+    let details = model.details.unwrap_or_default();
+    let num_ctx = details
+        .parameters
+        .iter()
+        .find(|p| p.name == "num_ctx")
+        .and_then(|p| p.value.parse().ok());
+
+    let spec = ModelSpec {
+        size_bytes: model.size,
+        parameters_billion: parse_parameter_size(&model.parameter_size).unwrap_or(0.0),
+        quantization_level: &model.quantization_level,
+        max_context: details.context_length,
+    };
+    Ok(estimate(
+        spec,
+        context_length.or(num_ctx),
+        SystemMemory::current(),
+    ))
+}
+
+#[tauri::command]
+pub fn recommend_models(context_length: Option<u64>) -> Result<Vec<Recommendation>, AppError> {
+    Ok(rank(context_length, SystemMemory::current()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const GB: u64 = 1024 * 1024 * 1024;
+
+    fn memory(total_gb: u64, available_gb: u64) -> SystemMemory {
+        SystemMemory {
+            total_bytes: total_gb * GB,
+            available_bytes: available_gb * GB,
+        }
+    }
+
+    #[test]
+    fn parses_parameter_sizes() {
+        assert_eq!(parse_parameter_size("8.0B"), Some(8.0));
+        assert_eq!(parse_parameter_size("137M"), Some(0.137));
+        assert_eq!(parse_parameter_size(""), None);
+    }
+
+    #[test]
+    fn eight_b_model_on_small_machines() {
+        let spec = ModelSpec {
+            size_bytes: 4_900_000_000,
+            parameters_billion: 8.0,
+            quantization_level: "Q4_K_M",
+            max_context: Some(131_072),
+        };
+        // Weights plus 512 MiB of cache plus overhead, about 5.9 GB
+        let fit = estimate(spec, None, memory(16, 10));
+        assert_eq!(fit.verdict, FitVerdict::Fits);
+        assert_eq!(fit.context_length, DEFAULT_CONTEXT);
+        assert_eq!(estimate(spec, None, memory(8, 3)).verdict, FitVerdict::Slow);
+        // A long context pushes the cache past what 8 GB can hold
+        assert_eq!(
+            estimate(spec, Some(32_768), memory(8, 7)).verdict,
+            FitVerdict::WontFit
+        );
+    }
+
+    #[test]
+    fn context_is_capped_and_size_derived_from_quantization() {
+        let spec = ModelSpec {
+            size_bytes: 0,
+            parameters_billion: 3.8,
+            quantization_level: "Q4_0",
+            max_context: Some(2048),
+        };
+        let fit = estimate(spec, Some(8192), memory(16, 16));
+        assert_eq!(fit.context_length, 2048);
+        assert!(fit.required_bytes > 2_300_000_000 && fit.required_bytes < 3 * GB);
+    }
+
+    #[test]
+    fn catalog_is_ranked_by_fit_then_size() {
+        let ranked = rank(None, memory(16, 8));
+        assert_eq!(ranked.len(), catalog().len());
+        let verdicts: Vec<_> = ranked.iter().map(|r| r.fit.verdict as u8).collect();
+        assert!(verdicts.windows(2).all(|w| w[0] <= w[1]));
+        let too_large = ranked.iter().find(|r| r.fit.verdict == FitVerdict::WontFit);
+        assert_eq!(too_large.unwrap().model.name, "llama3.1:70b");
+        assert_eq!(ranked[0].fit.verdict, FitVerdict::Fits);
+    }
+}
@@ -0,0 +1,243 @@
+//! The HTTP clients shared by every Ollama command, with timeouts chosen per
+//! kind of request.
+
+use crate::error::AppError;
+use futures_util::{Stream, StreamExt};
+use std::time::Duration;
+
+/// Kinds of Ollama request, each with its own timeout budget.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum EndpointClass {
+    /// Health checks: answer quickly or count as down.
+    Probe,
+    /// Short JSON calls such as `/api/tags` and `/api/delete`.
+    Api,
+    /// Token streams from `/api/chat`. Loading a large model can stall the
+    /// first token for minutes, so only inactivity is bounded.
+    Generation,
+    /// Download progress from `/api/pull`.
+    Transfer,
+    /// A batch sent to `/api/embed`; the reply arrives in one piece once the
+    /// model is loaded and the whole batch is done.
+    Embedding,
+}
+
+impl EndpointClass {
+    /// Every class, in declaration order, so `class as usize` indexes it.
+    const ALL: [EndpointClass; 5] = [
+        EndpointClass::Probe,
+        EndpointClass::Api,
+        EndpointClass::Generation,
+        EndpointClass::Transfer,
+        EndpointClass::Embedding,
+    ];
+
+    /// How long to wait for a TCP/TLS connection. Streams get longer, since
+    /// they often go to a remote server or one that is still starting.
+    pub fn connect_timeout(self) -> Duration {
+        match self {
+            EndpointClass::Probe => Duration::from_secs(2),
+            EndpointClass::Api | EndpointClass::Embedding => Duration::from_secs(5),
+            EndpointClass::Generation | EndpointClass::Transfer => Duration::from_secs(15),
+        }
+    }
+
+    /// Limit on the whole request, including reading the body.
+    pub fn total_timeout(self) -> Option<Duration> {
+        match self {
+            EndpointClass::Probe => Some(Duration::from_secs(3)),
+            EndpointClass::Api => Some(Duration::from_secs(30)),
+            EndpointClass::Embedding => Some(Duration::from_secs(300)),
+            EndpointClass::Generation | EndpointClass::Transfer => None,
+        }
+    }
+
+    /// Longest gap allowed between two chunks of a streamed body.
+    pub fn idle_timeout(self) -> Duration {
+        match self {
+            EndpointClass::Probe => Duration::from_secs(3),
+            EndpointClass::Api => Duration::from_secs(30),
+            EndpointClass::Generation | EndpointClass::Embedding => Duration::from_secs(300),
+            EndpointClass::Transfer => Duration::from_secs(120),
+        }
+    }
+}
+
+/// Map a reqwest failure to the matching `AppError`, naming the timeout
+/// that applied to `class`.
+pub fn classify(e: reqwest::Error, class: EndpointClass) -> AppError {
+    if e.is_timeout() {
+        if e.is_connect() {
+            AppError::ConnectTimeout(class.connect_timeout())
+        } else {
+            AppError::Timeout(class.total_timeout().unwrap_or(class.idle_timeout()))
+        }
+    } else if e.is_connect() {
+        AppError::OllamaUnreachable(e.to_string())
+    } else if e.is_decode() {
+        AppError::BadResponse(e.to_string())
+    } else if e.is_body() {
+        AppError::ConnectionLost(e.to_string())
+    } else {
+        AppError::Ollama {
+            status: e.status().map(|s| s.as_u16()),
+            message: e.to_string(),
+        }
+    }
+}
+
+/// The next chunk of a streamed body, or `None` at its end. Fails with
+/// `Stalled` when nothing arrives within `idle`.
+pub async fn next_chunk<S, B, E>(stream: &mut S, idle: Duration) -> Result<Option<B>, AppError>
+where
+    S: Stream<Item = Result<B, E>> + Unpin,
+    E: std::fmt::Display,
+{
+    match tokio::time::timeout(idle, stream.next()).await {
+        Ok(Some(chunk)) => chunk
+            .map(Some)
+            .map_err(|e| AppError::ConnectionLost(e.to_string())),
+        Ok(None) => Ok(None),
+        Err(_) => Err(AppError::Stalled(idle)),
+    }
+}
+
+/// Connection-pooling clients shared by every command. reqwest only sets
+/// the connect timeout per client, so each class has its own.
+#[derive(Clone)]
+pub struct HttpClient([reqwest::Client; 5]);
+
+impl HttpClient {
+    pub fn new() -> Self {
+        HttpClient(EndpointClass::ALL.map(|class| {
+            reqwest::Client::builder()
+                .connect_timeout(class.connect_timeout())
+                .build()
+                .unwrap_or_default()
+        }))
+    }
+
+    fn client(&self, class: EndpointClass) -> &reqwest::Client {
+        &self.0[class as usize]
+    }
+
+    pub fn get(&self, url: &str, class: EndpointClass) -> Request {
+        Request::new(self.client(class).get(url), class)
+    }
+
+    pub fn post(&self, url: &str, class: EndpointClass) -> Request {
+        Request::new(self.client(class).post(url), class)
+    }
+
+    pub fn head(&self, url: &str, class: EndpointClass) -> Request {
+        Request::new(self.client(class).head(url), class)
+    }
+
+    pub fn delete(&self, url: &str, class: EndpointClass) -> Request {
+        Request::new(self.client(class).delete(url), class)
+    }
+}
+
+/// A request builder that remembers its class so errors map to the right timeout.
+pub struct Request {
+    builder: reqwest::RequestBuilder,
+    class: EndpointClass,
+}
+
+impl Request {
+    fn new(builder: reqwest::RequestBuilder, class: EndpointClass) -> Self {
+        let builder = match class.total_timeout() {
+            Some(timeout) => builder.timeout(timeout),
+            None => builder,
+        };
+        Request { builder, class }
+    }
+
+    pub fn bearer_auth(mut self, token: Option<&str>) -> Self {
+        if let Some(token) = token {
+            self.builder = self.builder.bearer_auth(token);
+        }
+        self
+    }
+
+    pub fn header(mut self, key: reqwest::header::HeaderName, value: &str) -> Self {
+        self.builder = self.builder.header(key, value);
+        self
+    }
+
+    pub fn body(mut self, body: reqwest::Body) -> Self {
+        self.builder = self.builder.body(body);
+        self
+    }
+
+    pub fn json<T: serde::Serialize + ?Sized>(mut self, body: &T) -> Self {
+        self.builder = self.builder.json(body);
+        self
+    }
+
+    pub async fn send(self) -> Result<reqwest::Response, AppError> {
+        let class = self.class;
+        self.builder.send().await.map_err(|e| classify(e, class))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn refused_connection_is_unreachable() {
+        let port = std::net::TcpListener::bind("127.0.0.1:0")
+            .unwrap()
+            .local_addr()
+            .unwrap()
+            .port();
+        let err = tauri::async_runtime::block_on(
+            HttpClient::new()
+                .get(&format!("http://127.0.0.1:{port}"), EndpointClass::Probe)
+                .send(),
+        )
+        .unwrap_err();
+        assert!(matches!(err, AppError::OllamaUnreachable(_)), "{err:?}");
+    }
+
+    #[test]
+    fn classes_index_their_own_client() {
+        for (i, class) in EndpointClass::ALL.iter().enumerate() {
+            assert_eq!(*class as usize, i);
+        }
+    }
+
+    #[test]
+    fn silent_server_hits_the_total_timeout() {
+        // Accepts the connection but never answers
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let err = tauri::async_runtime::block_on(
+            HttpClient::new()
+                .get(&format!("http://127.0.0.1:{port}"), EndpointClass::Probe)
+                .send(),
+        )
+        .unwrap_err();
+        let expected = EndpointClass::Probe.total_timeout().unwrap();
+        assert!(
+            matches!(err, AppError::Timeout(d) if d == expected),
+            "{err:?}"
+        );
+        drop(listener);
+    }
+
+    #[test]
+    fn quiet_stream_stalls_after_the_idle_timeout() {
+        let idle = Duration::from_millis(50);
+        let mut stream = futures_util::stream::pending::<Result<Vec<u8>, String>>();
+        let err = tauri::async_runtime::block_on(next_chunk(&mut stream, idle)).unwrap_err();
+        assert!(matches!(err, AppError::Stalled(d) if d == idle), "{err:?}");
+
+        let mut stream = futures_util::stream::iter([Ok(vec![1]), Err("reset".to_string())]);
+        let chunk = tauri::async_runtime::block_on(next_chunk(&mut stream, idle));
+        assert_eq!(chunk.unwrap(), Some(vec![1]));
+        let err = tauri::async_runtime::block_on(next_chunk(&mut stream, idle)).unwrap_err();
+        assert!(matches!(err, AppError::ConnectionLost(_)), "{err:?}");
+    }
+}
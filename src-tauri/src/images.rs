@@ -0,0 +1,153 @@
+//! Image attachments for vision models: loading, validation and downscaling
+//! before they are base64-encoded into an Ollama chat message.
+
+use base64::Engine;
+use image::imageops::FilterType;
+use image::{DynamicImage, ImageFormat, ImageReader};
+use serde::{Deserialize, Serialize};
+use std::io::Cursor;
+
+/// Largest image accepted before any processing.
+const MAX_IMAGE_BYTES: u64 = 20 * 1024 * 1024;
+
+/// Images wider or taller than this are downscaled before being sent.
+const MAX_IMAGE_DIMENSION: u32 = 2048;
+
+pub const IMAGE_EXTENSIONS: &[&str] = &["png", "jpg", "jpeg", "gif", "webp"];
+
+/// An image supplied by the frontend, either as a path on disk or as raw bytes.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(untagged)]
+pub enum ImageAttachment {
+    Path { path: String },
+    Bytes { data: Vec<u8> },
+}
+
+impl ImageAttachment {
+    fn load(&self) -> Result<Vec<u8>, String> {
+        match self {
+            ImageAttachment::Path { path } => {
+                let metadata =
+                    std::fs::metadata(path).map_err(|e| format!("Cannot read image: {e}"))?;
+                check_size(metadata.len())?;
+                std::fs::read(path).map_err(|e| format!("Cannot read image: {e}"))
+            }
+            ImageAttachment::Bytes { data } => {
+                check_size(data.len() as u64)?;
+                Ok(data.clone())
+            }
+        }
+    }
+}
+
+/// Load, validate and (if needed) downscale an image, returning it
+/// base64-encoded as Ollama expects in `message.images`.
+pub fn encode_for_model(image: &ImageAttachment) -> Result<String, String> {
+    let bytes = prepare(image.load()?)?;
+    Ok(base64::engine::general_purpose::STANDARD.encode(bytes))
+}
+
+fn check_size(len: u64) -> Result<(), String> {
+    if len > MAX_IMAGE_BYTES {
+        return Err("Image too large (max 20 MB)".to_string());
+    }
+    Ok(())
+}
+
+/// PNG and JPEG within the size limit are passed through untouched. Anything
+/// larger, or in a format the model runner may not decode, is re-encoded.
+fn prepare(bytes: Vec<u8>) -> Result<Vec<u8>, String> {
+    let reader = ImageReader::new(Cursor::new(&bytes))
+        .with_guessed_format()
+        .map_err(|e| format!("Cannot read image: {e}"))?;
+
+    let format = match reader.format() {
+        Some(f @ (ImageFormat::Png | ImageFormat::Jpeg | ImageFormat::Gif | ImageFormat::WebP)) => {
+            f
+        }
+        _ => return Err("Unsupported image type (expected PNG, JPEG, GIF or WebP)".to_string()),
+    };
+
+    let (width, height) = reader
+        .into_dimensions()
+        .map_err(|e| format!("Invalid image: {e}"))?;
+    let oversized = width > MAX_IMAGE_DIMENSION || height > MAX_IMAGE_DIMENSION;
+
+    if !oversized && matches!(format, ImageFormat::Png | ImageFormat::Jpeg) {
+        return Ok(bytes);
+    }
+
+    let mut img = image::load_from_memory_with_format(&bytes, format)
+        .map_err(|e| format!("Invalid image: {e}"))?;
+    if oversized {
+        img = img.resize(
+            MAX_IMAGE_DIMENSION,
+            MAX_IMAGE_DIMENSION,
+            FilterType::Triangle,
+        );
+    }
+
+    let mut out = Cursor::new(Vec::new());
+    if img.color().has_alpha() {
+        img.write_to(&mut out, ImageFormat::Png)
+    } else {
+        DynamicImage::ImageRgb8(img.to_rgb8()).write_to(&mut out, ImageFormat::Jpeg)
+    }
+    .map_err(|e| format!("Failed to encode image: {e}"))?;
+
+    Ok(out.into_inner())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::{GenericImageView, RgbImage, RgbaImage};
+
+    fn encode(img: DynamicImage, format: ImageFormat) -> Vec<u8> {
+        let mut out = Cursor::new(Vec::new());
+        img.write_to(&mut out, format).unwrap();
+        out.into_inner()
+    }
+
+    #[test]
+    fn small_png_passes_through_unchanged() {
+        let png = encode(
+            DynamicImage::ImageRgb8(RgbImage::new(16, 16)),
+            ImageFormat::Png,
+        );
+        assert_eq!(prepare(png.clone()).unwrap(), png);
+    }
+
+    #[test]
+    fn oversized_image_is_downscaled() {
+        let png = encode(
+            DynamicImage::ImageRgb8(RgbImage::new(4096, 1024)),
+            ImageFormat::Png,
+        );
+        let out = prepare(png).unwrap();
+        let img = image::load_from_memory(&out).unwrap();
+        assert_eq!(img.dimensions(), (MAX_IMAGE_DIMENSION, 512));
+        assert_eq!(image::guess_format(&out).unwrap(), ImageFormat::Jpeg);
+    }
+
+    #[test]
+    fn gif_is_converted_keeping_alpha() {
+        let gif = encode(
+            DynamicImage::ImageRgba8(RgbaImage::new(8, 8)),
+            ImageFormat::Gif,
+        );
+        let out = prepare(gif).unwrap();
+        assert_eq!(image::guess_format(&out).unwrap(), ImageFormat::Png);
+    }
+
+    #[test]
+    fn rejects_non_images() {
+        assert!(prepare(b"definitely not an image".to_vec()).is_err());
+    }
+
+    #[test]
+    fn rejects_oversized_input() {
+        let data = vec![0u8; MAX_IMAGE_BYTES as usize + 1];
+        assert!(ImageAttachment::Bytes { data }.load().is_err());
+    }
+}
@@ -1,6 +1,24 @@
+mod bundle;
+mod downloads;
+mod embeddings;
+mod endpoint;
+mod error;
+mod fit;
+mod http;
+mod images;
+mod model_create;
+mod model_info;
+mod model_store;
+mod ndjson;
 mod ollama;
+mod openai;
+mod provider;
+mod rag;
 mod sidecar;
+mod sse;
 mod storage;
+mod structured;
+mod tools;
 
 use std::collections::HashMap;
 use std::sync::Mutex;
@@ -9,8 +27,14 @@ use tauri::{Manager, RunEvent};
 #[cfg_attr(mobile, tauri::mobile_entry_point)]
 pub fn run() {
     tauri::Builder::default()
-        .manage(ollama::ChatAbortHandle(Mutex::new(None)))
+        .manage(ollama::ChatStreams(Mutex::new(HashMap::new())))
         .manage(ollama::FolderWatchers(Mutex::new(HashMap::new())))
+        .manage(rag::IndexLocks(Mutex::new(HashMap::new())))
+        .manage(http::HttpClient::new())
+        .manage(model_store::StoreWriters(Mutex::new(model_store::StoreUse::default())))
+        .manage(sidecar::OllamaProcess {
+            child: Mutex::new(None),
+        })
         .setup(|app| {
             if cfg!(debug_assertions) {
                 app.handle().plugin(
@@ -22,11 +46,22 @@ pub fn run() {
 
             app.handle().plugin(tauri_plugin_dialog::init())?;
 
-            match sidecar::start_ollama(app) {
-                Ok(()) => log::info!("Ollama sidecar started"),
-                Err(e) => log::error!("Failed to start Ollama: {e}"),
+            // Managed first: the sidecar reads its port from the endpoint
+            let endpoint = endpoint::Endpoint::load(app.handle());
+            let (uses_sidecar, base) = (endpoint.uses_sidecar(), endpoint.base());
+            app.manage(endpoint);
+            if uses_sidecar {
+                match sidecar::start_ollama(app.handle()) {
+                    Ok(()) => log::info!("Ollama sidecar started"),
+                    Err(e) => log::error!("Failed to start Ollama: {e}"),
+                }
+            } else {
+                log::info!("Using model server at {base}");
             }
 
+            app.manage(downloads::Downloads::load(app.handle()));
+            downloads::resume_pending(app.handle());
+
             // Center and show window (starts hidden to avoid flicker)
             if let Some(window) = app.get_webview_window("main") {
                 let _ = window.center();
@@ -41,15 +76,38 @@ pub fn run() {
             ollama::get_system_ram,
             ollama::chat,
             ollama::cancel_chat,
-            ollama::pull_model,
             ollama::delete_model,
+            model_create::create_model,
+            model_create::copy_model,
+            model_create::import_gguf,
+            bundle::export_model,
+            bundle::import_bundle,
+            downloads::queue_pull,
+            downloads::list_downloads,
+            downloads::cancel_pull,
+            downloads::pause_pull,
+            downloads::resume_pull,
+            ollama::show_model,
+            fit::get_system_memory,
+            fit::estimate_model_fit,
+            fit::recommend_models,
+            ollama::list_running_models,
+            ollama::load_model,
+            ollama::unload_model,
             ollama::wait_for_ollama,
-            ollama::get_storage_info,
+            model_store::get_storage_info,
+            model_store::clean_orphaned_blobs,
             ollama::read_file_content,
             ollama::read_folder_files,
             ollama::watch_folder,
             ollama::unwatch_folder,
+            embeddings::embed,
+            rag::index_folder,
+            rag::remove_folder_index,
+            rag::search_folders,
             sidecar::restart_ollama,
+            endpoint::get_endpoint,
+            endpoint::set_endpoint,
             storage::save_store,
             storage::load_store,
         ])
@@ -0,0 +1,349 @@
+//! Building models on the server: personas derived from an installed model
+//! or imported from a local GGUF file through `/api/create`, and copies
+//! through `/api/copy`. Local files (weights, LoRA adapters) are uploaded as
+//! blobs first and referenced by digest, so nothing here needs the internet.
+
+use crate::endpoint::Endpoint;
+use crate::error::AppError;
+use crate::http::HttpClient;
+use crate::model_store::StoreWriters;
+use futures_util::future::{self, Either};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use sha2::{Digest, Sha256};
+use std::future::Future;
+use std::io::Read;
+use std::path::Path;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+use tauri::ipc::Channel;
+use tauri::State;
+
+/// Read size when hashing or uploading a file.
+const CHUNK_SIZE: usize = 1024 * 1024;
+
+/// How often byte progress is reported while hashing or uploading.
+const PROGRESS_INTERVAL: Duration = Duration::from_millis(250);
+
+/// Every GGUF file starts with these bytes.
+const GGUF_MAGIC: &[u8; 4] = b"GGUF";
+
+/// A model to build from an installed one or from a GGUF file. Anything left
+/// out is inherited.
+#[derive(Debug, Deserialize, Clone, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct CreateModel {
+    pub name: String,
+    /// Installed model to start from.
+    #[serde(default)]
+    pub from: String,
+    /// Path to GGUF weights on this machine, used instead of `from`.
+    pub gguf: Option<String>,
+    pub system: Option<String>,
+    /// Default options such as `temperature`, `num_ctx` or `stop`.
+    #[serde(default)]
+    pub parameters: serde_json::Map<String, Value>,
+    /// Prompt template, in Go template syntax.
+    pub template: Option<String>,
+    /// Path to a LoRA adapter on this machine, as a GGUF or safetensors file.
+    pub adapter: Option<String>,
+}
+
+impl CreateModel {
+    pub fn validate(&self) -> Result<(), AppError> {
+        if self.name.trim().is_empty() {
+            return Err(AppError::InvalidInput("The model needs a name".to_string()));
+        }
+        match &self.gguf {
+            Some(path) => check_gguf(Path::new(path))?,
+            None if self.from.trim().is_empty() => {
+                return Err(AppError::InvalidInput(
+                    "Choose a base model or a GGUF file".to_string(),
+                ));
+            }
+            None if self.name.trim() == self.from.trim() => {
+                return Err(AppError::InvalidInput(
+                    "The new model needs a name of its own".to_string(),
+                ));
+            }
+            None => {}
+        }
+        if let Some(adapter) = &self.adapter {
+            if !Path::new(adapter).is_file() {
+                return Err(AppError::NotFound(adapter.clone()));
+            }
+        }
+        Ok(())
+    }
+
+    /// The `/api/create` body. `weights` and `adapter` are the uploaded
+    /// files' names and digests.
+    pub fn body(
+        &self,
+        weights: Option<&(String, String)>,
+        adapter: Option<&(String, String)>,
+    ) -> Value {
+        let mut body = serde_json::json!({
+            "model": self.name.trim(),
+            "stream": true,
+        });
+        match weights {
+            Some((file, digest)) => body["files"] = serde_json::json!({ file: digest }),
+            None => body["from"] = Value::from(self.from.trim()),
+        }
+        let text = |value: &Option<String>| {
+            value
+                .as_deref()
+                .map(str::trim)
+                .filter(|v| !v.is_empty())
+                .map(Value::from)
+        };
+        if let Some(system) = text(&self.system) {
+            body["system"] = system;
+        }
+        if let Some(template) = text(&self.template) {
+            body["template"] = template;
+        }
+        if !self.parameters.is_empty() {
+            body["parameters"] = Value::Object(self.parameters.clone());
+        }
+        if let Some((file, digest)) = adapter {
+            body["adapters"] = serde_json::json!({ file: digest });
+        }
+        body
+    }
+}
+
+#[derive(Debug, Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct CreateProgress {
+    /// Our own "hashing …"/"uploading …" steps, then Ollama's status lines,
+    /// like "using existing layer sha256:…".
+    pub status: String,
+    /// Bytes done, while hashing or uploading a file.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub completed: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub total: Option<u64>,
+}
+
+impl CreateProgress {
+    pub fn status(status: String) -> Self {
+        CreateProgress {
+            status,
+            completed: None,
+            total: None,
+        }
+    }
+}
+
+fn check_gguf(path: &Path) -> Result<(), AppError> {
+    let mut magic = [0; 4];
+    std::fs::File::open(path)
+        .and_then(|mut file| file.read_exact(&mut magic))
+        .map_err(|e| AppError::io("Cannot read file", path, e))?;
+    if &magic != GGUF_MAGIC {
+        return Err(AppError::InvalidInput(format!(
+            "{} is not a GGUF file",
+            path.display()
+        )));
+    }
+    Ok(())
+}
+
+/// A file's digest as Ollama names blobs, `sha256:<hex>`. Blocks while the
+/// whole file is read, counting bytes in `read_bytes`.
+pub fn hash_file(path: &Path, read_bytes: &AtomicU64) -> Result<String, AppError> {
+    let mut file =
+        std::fs::File::open(path).map_err(|e| AppError::io("Cannot read file", path, e))?;
+    let mut hasher = Sha256::new();
+    let mut buf = vec![0; CHUNK_SIZE];
+    loop {
+        let read = file
+            .read(&mut buf)
+            .map_err(|e| AppError::io("Cannot read file", path, e))?;
+        if read == 0 {
+            break;
+        }
+        hasher.update(&buf[..read]);
+        read_bytes.fetch_add(read as u64, Ordering::Relaxed);
+    }
+    let hex: String = hasher
+        .finalize()
+        .iter()
+        .map(|b| format!("{b:02x}"))
+        .collect();
+    Ok(format!("sha256:{hex}"))
+}
+
+/// Stream a file as a request body without holding it in memory, counting
+/// bytes handed to the connection in `sent`.
+pub fn file_body(file: std::fs::File, sent: Arc<AtomicU64>) -> reqwest::Body {
+    let chunks = futures_util::stream::try_unfold(file, move |mut file| {
+        let sent = sent.clone();
+        async move {
+            let (file, chunk) = tokio::task::spawn_blocking(move || {
+                let mut buf = vec![0; CHUNK_SIZE];
+                let read = file.read(&mut buf)?;
+                buf.truncate(read);
+                Ok::<_, std::io::Error>((file, buf))
+            })
+            .await
+            .map_err(std::io::Error::other)??;
+            sent.fetch_add(chunk.len() as u64, Ordering::Relaxed);
+            Ok::<_, std::io::Error>((!chunk.is_empty()).then_some((chunk, file)))
+        }
+    });
+    reqwest::Body::wrap_stream(chunks)
+}
+
+/// Drive `task` to completion, calling `report` with `counter` every
+/// `PROGRESS_INTERVAL` meanwhile.
+pub async fn report_while<F: Future>(
+    task: F,
+    counter: &AtomicU64,
+    report: impl Fn(u64),
+) -> F::Output {
+    let mut task = std::pin::pin!(task);
+    loop {
+        let tick = std::pin::pin!(tokio::time::sleep(PROGRESS_INTERVAL));
+        match future::select(task.as_mut(), tick).await {
+            Either::Left((output, _)) => return output,
+            Either::Right(_) => report(counter.load(Ordering::Relaxed)),
+        }
+    }
+}
+
+// ── Commands ────────────────────────────────────────────────────────
+
+/// Build a new model from an installed one, streaming Ollama's status lines.
+#[tauri::command]
+pub async fn create_model(
+    endpoint: State<'_, Endpoint>,
+    http: State<'_, HttpClient>,
+    writers: State<'_, StoreWriters>,
+    request: CreateModel,
+    on_progress: Channel<CreateProgress>,
+) -> Result<(), AppError> {
+    request.validate()?;
+    // Uploaded blobs have no manifest until `/api/create` finishes
+    let _writing = writers.begin()?;
+    let report = |progress: CreateProgress| {
+        let _ = on_progress.send(progress);
+    };
+    endpoint
+        .provider(&http)
+        .create_model(&request, &report)
+        .await
+}
+
+/// Make a model from GGUF weights on this machine, streaming progress. The
+/// file is uploaded to the server, so the original can be deleted after.
+#[tauri::command]
+pub async fn import_gguf(
+    endpoint: State<'_, Endpoint>,
+    http: State<'_, HttpClient>,
+    writers: State<'_, StoreWriters>,
+    path: String,
+    name: String,
+    on_progress: Channel<CreateProgress>,
+) -> Result<(), AppError> {
+    let request = CreateModel {
+        name,
+        gguf: Some(path),
+        ..Default::default()
+    };
+    create_model(endpoint, http, writers, request, on_progress).await
+}
+
+/// Copy a model under a new name. Blobs are shared, so this takes no space.
+#[tauri::command]
+pub async fn copy_model(
+    endpoint: State<'_, Endpoint>,
+    http: State<'_, HttpClient>,
+    source: String,
+    destination: String,
+) -> Result<(), AppError> {
+    let destination = destination.trim();
+    if destination.is_empty() || destination == source {
+        return Err(AppError::InvalidInput(
+            "The copy needs a name of its own".to_string(),
+        ));
+    }
+    endpoint
+        .provider(&http)
+        .copy_model(&source, destination)
+        .await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn body_includes_only_what_was_set() {
+        let mut request = CreateModel {
+            name: "reviewer".to_string(),
+            from: "qwen3:8b".to_string(),
+            system: Some("  You review pull requests.  ".to_string()),
+            template: Some(String::new()),
+            ..Default::default()
+        };
+        request
+            .parameters
+            .insert("temperature".to_string(), json!(0.2));
+        assert_eq!(
+            request.body(
+                None,
+                Some(&("lora.gguf".to_string(), "sha256:ab".to_string()))
+            ),
+            json!({
+                "model": "reviewer",
+                "from": "qwen3:8b",
+                "stream": true,
+                "system": "You review pull requests.",
+                "parameters": { "temperature": 0.2 },
+                "adapters": { "lora.gguf": "sha256:ab" },
+            })
+        );
+        request.name = "qwen3:8b".to_string();
+        assert!(request.validate().is_err());
+    }
+
+    #[test]
+    fn imports_reference_the_uploaded_weights_and_must_be_gguf() {
+        let path = std::env::temp_dir().join(format!("innertalk-gguf-{}", std::process::id()));
+        std::fs::write(&path, b"GGML....").unwrap();
+        let request = CreateModel {
+            name: "local-model".to_string(),
+            gguf: Some(path.to_string_lossy().to_string()),
+            ..Default::default()
+        };
+        assert!(matches!(request.validate(), Err(AppError::InvalidInput(_))));
+
+        std::fs::write(&path, b"GGUF....").unwrap();
+        assert!(request.validate().is_ok());
+        let body = request.body(
+            Some(&("model.gguf".to_string(), "sha256:cd".to_string())),
+            None,
+        );
+        assert_eq!(body["files"], json!({ "model.gguf": "sha256:cd" }));
+        assert!(body.get("from").is_none());
+        let _ = std::fs::remove_file(&path);
+    }
+
+    #[test]
+    fn hashes_files_like_ollama_names_blobs() {
+        let path = std::env::temp_dir().join(format!("innertalk-blob-{}", std::process::id()));
+        std::fs::write(&path, "abc").unwrap();
+        let read = AtomicU64::new(0);
+        assert_eq!(
+            hash_file(&path, &read).unwrap(),
+            "sha256:ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad"
+        );
+        assert_eq!(read.load(Ordering::Relaxed), 3);
+        let _ = std::fs::remove_file(&path);
+    }
+}
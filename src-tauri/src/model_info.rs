@@ -0,0 +1,202 @@
+//! Model metadata from Ollama's `/api/tags` and `/api/show`, reduced to what
+//! the model detail view needs: what the model is, how much context it takes
+//! and what it can do.
+
+use crate::embeddings;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::HashMap;
+
+/// The `details` object both `/api/tags` and `/api/show` return.
+#[derive(Debug, Deserialize, Default)]
+pub struct FamilyDetails {
+    #[serde(default)]
+    pub family: String,
+    #[serde(default)]
+    pub families: Option<Vec<String>>,
+    #[serde(default)]
+    pub parameter_size: String,
+    #[serde(default)]
+    pub quantization_level: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct Capabilities {
+    /// Accepts images.
+    pub vision: bool,
+    pub tools: bool,
+    /// Can reason before answering (`think`).
+    pub thinking: bool,
+    pub embedding: bool,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct ModelParameter {
+    pub name: String,
+    pub value: String,
+}
+
+/// What only `/api/show` knows about a model.
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct ModelDetails {
+    /// Longest context the model was trained for, in tokens.
+    pub context_length: Option<u64>,
+    pub license: Option<String>,
+    /// Prompt template, in Go template syntax.
+    pub template: Option<String>,
+    /// Defaults baked into the model, like `stop` or `temperature`.
+    pub parameters: Vec<ModelParameter>,
+    pub capabilities: Capabilities,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ShowResponse {
+    #[serde(default)]
+    license: String,
+    #[serde(default)]
+    template: String,
+    #[serde(default)]
+    parameters: String,
+    #[serde(default)]
+    pub details: FamilyDetails,
+    #[serde(default)]
+    model_info: HashMap<String, Value>,
+    #[serde(default)]
+    projector_info: Option<Value>,
+    /// Only reported by newer Ollama versions.
+    #[serde(default)]
+    capabilities: Option<Vec<String>>,
+    #[serde(default)]
+    pub modified_at: Option<String>,
+}
+
+impl ShowResponse {
+    pub fn model_details(&self, name: &str) -> ModelDetails {
+        ModelDetails {
+            context_length: self.context_length(),
+            license: Some(self.license.trim().to_string()).filter(|l| !l.is_empty()),
+            template: Some(self.template.clone()).filter(|t| !t.is_empty()),
+            parameters: parse_parameters(&self.parameters),
+            capabilities: self.capabilities(name),
+        }
+    }
+
+    fn architecture(&self) -> Option<&str> {
+        self.model_info.get("general.architecture")?.as_str()
+    }
+
+    fn context_length(&self) -> Option<u64> {
+        let key = format!("{}.context_length", self.architecture()?);
+        self.model_info.get(&key)?.as_u64()
+    }
+
+    fn capabilities(&self, name: &str) -> Capabilities {
+        if let Some(list) = &self.capabilities {
+            let has = |c: &str| list.iter().any(|l| l == c);
+            return Capabilities {
+                vision: has("vision"),
+                tools: has("tools"),
+                thinking: has("thinking"),
+                embedding: has("embedding"),
+            };
+        }
+
+        // Older servers: infer from the weights and the template, the same
+        // way Ollama itself does
+        let families = self.details.families.as_deref().unwrap_or_default();
+        let vision_weights = self.architecture().is_some_and(|arch| {
+            let prefix = format!("{arch}.vision.");
+            self.model_info.keys().any(|k| k.starts_with(&prefix))
+        });
+        Capabilities {
+            vision: self.projector_info.is_some()
+                || vision_weights
+                || families.iter().any(|f| f == "clip" || f == "mllama"),
+            tools: self.template.contains(".Tools"),
+            thinking: self.template.contains(".Think"),
+            embedding: embeddings::is_embedding_model(name, &self.details.family),
+        }
+    }
+}
+
+/// Parse the `parameters` text, one `name value` pair per line. Values may be
+/// quoted, and names like `stop` can repeat.
+fn parse_parameters(text: &str) -> Vec<ModelParameter> {
+    text.lines()
+        .filter_map(|line| {
+            let (name, value) = line.trim().split_once(char::is_whitespace)?;
+            let value = value.trim();
+            let value = value
+                .strip_prefix('"')
+                .and_then(|v| v.strip_suffix('"'))
+                .unwrap_or(value);
+            Some(ModelParameter {
+                name: name.to_string(),
+                value: value.to_string(),
+            })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn parses_parameters_with_quotes_and_repeats() {
+        let params = parse_parameters(
+            "stop                           \"<|start_header_id|>\"\nstop \"<|eot_id|>\"\nnum_ctx 8192\n",
+        );
+        let pairs: Vec<_> = params
+            .iter()
+            .map(|p| (p.name.as_str(), p.value.as_str()))
+            .collect();
+        assert_eq!(
+            pairs,
+            vec![
+                ("stop", "<|start_header_id|>"),
+                ("stop", "<|eot_id|>"),
+                ("num_ctx", "8192")
+            ]
+        );
+    }
+
+    #[test]
+    fn reads_context_length_and_reported_capabilities() {
+        let show: ShowResponse = serde_json::from_value(json!({
+            "license": "  MIT  ",
+            "template": "{{ .Prompt }}",
+            "details": { "family": "qwen3", "parameter_size": "8.2B" },
+            "model_info": { "general.architecture": "qwen3", "qwen3.context_length": 40960 },
+            "capabilities": ["completion", "tools", "thinking"],
+        }))
+        .unwrap();
+        let details = show.model_details("qwen3:8b");
+        assert_eq!(details.context_length, Some(40960));
+        assert_eq!(details.license.as_deref(), Some("MIT"));
+        assert_eq!(
+            details.capabilities,
+            Capabilities {
+                tools: true,
+                thinking: true,
+                ..Default::default()
+            }
+        );
+    }
+
+    #[test]
+    fn infers_capabilities_on_older_servers() {
+        let show: ShowResponse = serde_json::from_value(json!({
+            "template": "{{ if .Tools }}{{ .Tools }}{{ end }}{{ .Prompt }}",
+            "details": { "family": "mllama", "families": ["mllama"] },
+            "model_info": { "general.architecture": "mllama" },
+        }))
+        .unwrap();
+        let caps = show.model_details("llama3.2-vision").capabilities;
+        assert!(caps.vision && caps.tools);
+        assert!(!caps.thinking && !caps.embedding);
+    }
+}
@@ -0,0 +1,675 @@
+//! Ollama's model store on disk. Each model is a manifest under
+//! `manifests/<host>/<namespace>/<model>/<tag>` listing content-addressed
+//! blobs in `blobs/`, and models built from the same base share blobs.
+//! Reading the store directly gives per-model sizes, finds blobs no manifest
+//! uses any more, and lets a pull check for disk space before it starts.
+
+use crate::downloads::Downloads;
+use crate::endpoint::Endpoint;
+use crate::error::AppError;
+use crate::http::{self, EndpointClass, HttpClient};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, MutexGuard, PoisonError};
+use tauri::State;
+
+const DEFAULT_HOST: &str = "registry.ollama.ai";
+const DEFAULT_NAMESPACE: &str = "library";
+const DEFAULT_TAG: &str = "latest";
+
+const MANIFEST_MEDIA_TYPE: &str = "application/vnd.docker.distribution.manifest.v2+json";
+
+/// Left free on top of a download so it doesn't fill the disk to the last
+/// byte.
+const SPACE_HEADROOM: u64 = 512 * 1024 * 1024;
+
+/// Where Ollama keeps models: `OLLAMA_MODELS` if set (the sidecar inherits
+/// our environment), otherwise `~/.ollama/models`.
+pub fn models_dir() -> Result<PathBuf, AppError> {
+    if let Some(dir) = std::env::var_os("OLLAMA_MODELS").filter(|d| !d.is_empty()) {
+        return Ok(PathBuf::from(dir));
+    }
+    Ok(dirs::home_dir()
+        .ok_or_else(|| AppError::Internal("Cannot find home directory".to_string()))?
+        .join(".ollama")
+        .join("models"))
+}
+
+/// A model reference split the way Ollama lays out manifests.
+#[derive(Debug, PartialEq)]
+pub struct ModelName {
+    host: String,
+    namespace: String,
+    model: String,
+    tag: String,
+}
+
+impl ModelName {
+    /// Parse `[host/][namespace/]model[:tag]`, filling in Ollama's defaults.
+    pub fn parse(name: &str) -> Self {
+        let name = name.split('@').next().unwrap_or(name);
+        // A colon before the last slash is a port, not a tag
+        let (path, tag) = match name.rsplit_once(':') {
+            Some((path, tag)) if !tag.contains('/') => (path, tag),
+            _ => (name, DEFAULT_TAG),
+        };
+        let mut parts = path.rsplit('/');
+        let model = parts.next().unwrap_or_default();
+        let namespace = parts.next().unwrap_or(DEFAULT_NAMESPACE);
+        let host = parts.next().unwrap_or(DEFAULT_HOST);
+        ModelName {
+            host: host.to_string(),
+            namespace: namespace.to_string(),
+            model: model.to_string(),
+            tag: tag.to_string(),
+        }
+    }
+
+    /// The manifest's path relative to the models directory, with `/`
+    /// separators.
+    pub fn manifest_entry(&self) -> String {
+        format!(
+            "manifests/{}/{}/{}/{}",
+            self.host, self.namespace, self.model, self.tag
+        )
+    }
+
+    /// Inverse of `manifest_entry`.
+    pub fn from_manifest_entry(entry: &str) -> Option<Self> {
+        let parts: Vec<&str> = entry.split('/').collect();
+        let ["manifests", host, namespace, model, tag] = parts[..] else {
+            return None;
+        };
+        Some(ModelName {
+            host: host.to_string(),
+            namespace: namespace.to_string(),
+            model: model.to_string(),
+            tag: tag.to_string(),
+        })
+    }
+
+    /// The name `ollama list` shows, with default parts left out.
+    pub fn display(&self) -> String {
+        let ModelName {
+            host,
+            namespace,
+            model,
+            tag,
+        } = self;
+        if host != DEFAULT_HOST {
+            format!("{host}/{namespace}/{model}:{tag}")
+        } else if namespace != DEFAULT_NAMESPACE {
+            format!("{namespace}/{model}:{tag}")
+        } else {
+            format!("{model}:{tag}")
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct Layer {
+    pub digest: String,
+    pub size: u64,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct Manifest {
+    config: Option<Layer>,
+    #[serde(default)]
+    layers: Vec<Layer>,
+}
+
+impl Manifest {
+    pub fn blobs(&self) -> impl Iterator<Item = &Layer> {
+        self.config.iter().chain(&self.layers)
+    }
+}
+
+/// Blob path relative to the models directory: `sha256:ab…` is stored as
+/// `blobs/sha256-ab…`.
+pub fn blob_entry(digest: &str) -> String {
+    format!("blobs/{}", digest.replacen(':', "-", 1))
+}
+
+/// What's on disk, read in one pass.
+struct Store {
+    /// Manifests by display name.
+    manifests: Vec<(String, Manifest)>,
+    /// Blob files by digest (`sha256-ab…` on disk is `sha256:ab…`), with
+    /// their size.
+    blobs: HashMap<String, (PathBuf, u64)>,
+    /// Files left by downloads that never finished, like
+    /// `sha256-ab…-partial-3`.
+    partial: Vec<(PathBuf, u64)>,
+}
+
+impl Store {
+    fn scan(dir: &Path) -> Self {
+        let mut manifests = Vec::new();
+        let manifest_dir = dir.join("manifests");
+        for host in subdirs(&manifest_dir) {
+            for namespace in subdirs(&host) {
+                for model in subdirs(&namespace) {
+                    let Ok(tags) = std::fs::read_dir(&model) else {
+                        continue;
+                    };
+                    for tag in tags.flatten() {
+                        let path = tag.path();
+                        let parsed = std::fs::read_to_string(&path)
+                            .ok()
+                            .and_then(|data| serde_json::from_str::<Manifest>(&data).ok());
+                        let Some(manifest) = parsed else {
+                            log::warn!("Skipping unreadable manifest {}", path.display());
+                            continue;
+                        };
+                        let name = ModelName {
+                            host: file_name(&host),
+                            namespace: file_name(&namespace),
+                            model: file_name(&model),
+                            tag: file_name(&path),
+                        };
+                        manifests.push((name.display(), manifest));
+                    }
+                }
+            }
+        }
+
+        let mut blobs = HashMap::new();
+        let mut partial = Vec::new();
+        if let Ok(entries) = std::fs::read_dir(dir.join("blobs")) {
+            for entry in entries.flatten() {
+                let Ok(meta) = entry.metadata() else { continue };
+                if !meta.is_file() {
+                    continue;
+                }
+                let name = entry.file_name().to_string_lossy().to_string();
+                if name.contains("-partial") {
+                    partial.push((entry.path(), meta.len()));
+                } else if let Some(hex) = name.strip_prefix("sha256-") {
+                    blobs.insert(format!("sha256:{hex}"), (entry.path(), meta.len()));
+                }
+            }
+        }
+
+        Store {
+            manifests,
+            blobs,
+            partial,
+        }
+    }
+
+    /// How many manifests use each digest.
+    fn references(&self) -> HashMap<&str, usize> {
+        let mut counts = HashMap::new();
+        for (_, manifest) in &self.manifests {
+            // A manifest listing the same blob twice still holds it once
+            let digests: HashSet<&str> = manifest.blobs().map(|l| l.digest.as_str()).collect();
+            for digest in digests {
+                *counts.entry(digest).or_insert(0) += 1;
+            }
+        }
+        counts
+    }
+
+    fn usage(&self) -> Vec<ModelUsage> {
+        let references = self.references();
+        let mut models: Vec<ModelUsage> = self
+            .manifests
+            .iter()
+            .map(|(name, manifest)| {
+                let digests: HashSet<&str> = manifest.blobs().map(|l| l.digest.as_str()).collect();
+                let size = |digest: &&str| self.blobs.get(*digest).map_or(0, |(_, size)| *size);
+                ModelUsage {
+                    name: name.clone(),
+                    size_bytes: digests.iter().map(size).sum(),
+                    unique_bytes: digests
+                        .iter()
+                        .filter(|d| references.get(**d) == Some(&1))
+                        .map(size)
+                        .sum(),
+                }
+            })
+            .collect();
+        models.sort_by_key(|m| std::cmp::Reverse(m.size_bytes));
+        models
+    }
+
+    /// Blob files no manifest refers to, partial downloads included.
+    fn orphans(&self) -> Vec<(&Path, u64)> {
+        let references = self.references();
+        self.blobs
+            .iter()
+            .filter(|(digest, _)| !references.contains_key(digest.as_str()))
+            .map(|(_, (path, size))| (path.as_path(), *size))
+            .chain(
+                self.partial
+                    .iter()
+                    .map(|(path, size)| (path.as_path(), *size)),
+            )
+            .collect()
+    }
+}
+
+fn subdirs(dir: &Path) -> Vec<PathBuf> {
+    std::fs::read_dir(dir)
+        .map(|entries| {
+            entries
+                .flatten()
+                .map(|e| e.path())
+                .filter(|p| p.is_dir())
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+fn file_name(path: &Path) -> String {
+    path.file_name()
+        .map(|n| n.to_string_lossy().to_string())
+        .unwrap_or_default()
+}
+
+fn dir_size(path: &Path) -> u64 {
+    let mut total = 0;
+    if let Ok(entries) = std::fs::read_dir(path) {
+        for entry in entries.flatten() {
+            let meta = entry.metadata();
+            if let Ok(m) = meta {
+                if m.is_dir() {
+                    total += dir_size(&entry.path());
+                } else {
+                    total += m.len();
+                }
+            }
+        }
+    }
+    total
+}
+
+/// Free space on the disk holding `path`, which need not exist yet.
+fn available_space(path: &Path) -> Option<u64> {
+    let existing = path.ancestors().find(|p| p.exists())?;
+    let path = existing.canonicalize().ok()?;
+    let disks = sysinfo::Disks::new_with_refreshed_list();
+    disks
+        .list()
+        .iter()
+        .filter(|disk| path.starts_with(disk.mount_point()))
+        .max_by_key(|disk| disk.mount_point().as_os_str().len())
+        .map(|disk| disk.available_space())
+}
+
+/// Disk space deleting `name` would free: the blobs no other model uses.
+pub fn unique_bytes(name: &str) -> Option<u64> {
+    let name = ModelName::parse(name).display();
+    let store = Store::scan(&models_dir().ok()?);
+    store
+        .usage()
+        .into_iter()
+        .find(|m| m.name == name)
+        .map(|m| m.unique_bytes)
+}
+
+// ── Pull preflight ──────────────────────────────────────────────────
+
+async fn registry_manifest(http: &HttpClient, name: &ModelName) -> Result<Manifest, AppError> {
+    let url = format!(
+        "https://{}/v2/{}/{}/manifests/{}",
+        name.host, name.namespace, name.model, name.tag
+    );
+    let resp = http
+        .get(&url, EndpointClass::Api)
+        .header(reqwest::header::ACCEPT, MANIFEST_MEDIA_TYPE)
+        .send()
+        .await?;
+    if !resp.status().is_success() {
+        let status = resp.status();
+        let text = resp.text().await.unwrap_or_default();
+        return Err(AppError::from_model_response(Some(status), &text));
+    }
+    resp.json()
+        .await
+        .map_err(|e| http::classify(e, EndpointClass::Api))
+}
+
+/// Bytes a pull still has to fetch: every layer not already on disk.
+fn bytes_to_fetch(manifest: &Manifest, store: &Store) -> u64 {
+    let digests: HashMap<&str, u64> = manifest
+        .blobs()
+        .map(|l| (l.digest.as_str(), l.size))
+        .collect();
+    digests
+        .into_iter()
+        .filter(|(digest, _)| !store.blobs.contains_key(*digest))
+        .map(|(_, size)| size)
+        .sum()
+}
+
+/// Refuse to pull `name` into the local store when the disk can't hold it.
+/// If the registry or the disk can't be asked, the pull goes ahead and
+/// Ollama reports any shortage itself.
+pub async fn check_space_for_pull(http: &HttpClient, name: &str) -> Result<(), AppError> {
+    let dir = models_dir()?;
+    let manifest = match registry_manifest(http, &ModelName::parse(name)).await {
+        Ok(manifest) => manifest,
+        Err(e) => {
+            log::info!("Skipping disk space check for {name}: {e}");
+            return Ok(());
+        }
+    };
+    ensure_space(&dir, bytes_to_fetch(&manifest, &Store::scan(&dir)))
+}
+
+/// Fail unless `required` bytes, plus some headroom, fit on the disk holding
+/// `dir`. Passes if free space can't be determined.
+pub fn ensure_space(dir: &Path, required: u64) -> Result<(), AppError> {
+    let Some(available) = available_space(dir) else {
+        return Ok(());
+    };
+    if required + SPACE_HEADROOM > available {
+        return Err(AppError::InsufficientSpace {
+            path: dir.to_string_lossy().to_string(),
+            required,
+            available,
+        });
+    }
+    Ok(())
+}
+
+// ── Commands ────────────────────────────────────────────────────────
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ModelUsage {
+    pub name: String,
+    /// Every blob the model uses.
+    pub size_bytes: u64,
+    /// Blobs no other model shares, i.e. what deleting it frees.
+    pub unique_bytes: u64,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct StorageInfo {
+    pub path: String,
+    /// Everything under the model directory.
+    pub size_bytes: u64,
+    /// Largest first. Shared blobs count toward each model using them.
+    pub models: Vec<ModelUsage>,
+    /// Blobs no model uses, including abandoned partial downloads.
+    pub orphaned_bytes: u64,
+    pub orphaned_blobs: usize,
+    /// Free space on the disk holding the models, if known.
+    pub available_bytes: Option<u64>,
+}
+
+#[tauri::command]
+pub fn get_storage_info() -> Result<StorageInfo, AppError> {
+    let dir = models_dir()?;
+    let store = Store::scan(&dir);
+    let orphans = store.orphans();
+    Ok(StorageInfo {
+        path: dir.to_string_lossy().to_string(),
+        size_bytes: dir_size(&dir),
+        models: store.usage(),
+        orphaned_bytes: orphans.iter().map(|(_, size)| size).sum(),
+        orphaned_blobs: orphans.len(),
+        available_bytes: available_space(&dir),
+    })
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CleanupResult {
+    pub removed_blobs: usize,
+    pub freed_bytes: u64,
+}
+
+/// Writes in progress that put blobs in the store before the manifest that
+/// uses them: blob uploads for `/api/create` and bundle imports. Until the manifest
+/// lands those blobs look orphaned, so cleanup and these writes shut each other out.
+pub struct StoreWriters(pub Mutex<StoreUse>);
+
+#[derive(Debug, Default)]
+pub struct StoreUse {
+    writers: usize,
+    cleaning: bool,
+}
+
+fn cleanup_running() -> AppError {
+    AppError::InvalidInput("Wait for the model cleanup to finish".to_string())
+}
+
+impl StoreWriters {
+    /// Count a write as running until the returned guard is dropped. Fails
+    /// while a cleanup runs.
+    pub fn begin(&self) -> Result<StoreWrite<'_>, AppError> {
+        let mut store = self.0.lock()?;
+        if store.cleaning {
+            return Err(cleanup_running());
+        }
+        store.writers += 1;
+        Ok(StoreWrite(self))
+    }
+
+    /// Keep a cleanup from starting while the returned lock is held, for
+    /// work like queueing a pull that `clean`'s `busy` check looks at.
+    /// Fails while a cleanup runs.
+    pub fn hold_off_cleanup(&self) -> Result<MutexGuard<'_, StoreUse>, AppError> {
+        let store = self.0.lock()?;
+        if store.cleaning {
+            return Err(cleanup_running());
+        }
+        Ok(store)
+    }
+
+    /// Mark a cleanup as running until the returned guard is dropped. Fails
+    /// while a write runs or `busy`, checked under the same lock, says the
+    /// store has other unfinished work.
+    pub fn clean(
+        &self,
+        busy: impl FnOnce() -> Result<bool, AppError>,
+    ) -> Result<StoreCleanup<'_>, AppError> {
+        let mut store = self.0.lock()?;
+        if store.cleaning {
+            return Err(cleanup_running());
+        }
+        if store.writers > 0 || busy()? {
+            return Err(AppError::InvalidInput(
+                "Finish or cancel downloads and imports before cleaning up".to_string(),
+            ));
+        }
+        store.cleaning = true;
+        Ok(StoreCleanup(self))
+    }
+
+    fn lock_poisoned(&self) -> MutexGuard<'_, StoreUse> {
+        // A poisoned count is still the right count
+        self.0.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+pub struct StoreWrite<'a>(&'a StoreWriters);
+
+impl Drop for StoreWrite<'_> {
+    fn drop(&mut self) {
+        self.0.lock_poisoned().writers -= 1;
+    }
+}
+
+pub struct StoreCleanup<'a>(&'a StoreWriters);
+
+impl Drop for StoreCleanup<'_> {
+    fn drop(&mut self) {
+        self.0.lock_poisoned().cleaning = false;
+    }
+}
+
+/// The store is read from this machine's disk, which is only Ollama's own
+/// when the endpoint is local.
+pub fn require_local(endpoint: &Endpoint) -> Result<(), AppError> {
+    if !endpoint.uses_sidecar() {
+        return Err(AppError::RemoteEndpoint);
+    }
+    Ok(())
+}
+
+fn remove_orphans(store: &Store) -> CleanupResult {
+    let mut result = CleanupResult {
+        removed_blobs: 0,
+        freed_bytes: 0,
+    };
+    for (path, size) in store.orphans() {
+        match std::fs::remove_file(path) {
+            Ok(()) => {
+                result.removed_blobs += 1;
+                result.freed_bytes += size;
+            }
+            Err(e) => log::warn!("Could not remove {}: {e}", path.display()),
+        }
+    }
+    result
+}
+
+/// Delete blobs no model uses. Pulls, uploads and bundle imports write
+/// blobs before their manifest, so this refuses to run while any is
+/// unfinished, and they refuse to start until it is done.
+#[tauri::command]
+pub async fn clean_orphaned_blobs(
+    endpoint: State<'_, Endpoint>,
+    downloads: State<'_, Downloads>,
+    writers: State<'_, StoreWriters>,
+) -> Result<CleanupResult, AppError> {
+    require_local(&endpoint)?;
+    let _cleaning = writers.clean(|| downloads.has_pending())?;
+    let dir = models_dir()?;
+    // Scanning and deleting many GB of blobs blocks
+    tokio::task::spawn_blocking(move || remove_orphans(&Store::scan(&dir)))
+        .await
+        .map_err(|e| AppError::Internal(e.to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn store_writes_count_until_dropped() {
+        let writers = StoreWriters(Mutex::new(StoreUse::default()));
+        let first = writers.begin().unwrap();
+        let second = writers.begin().unwrap();
+        drop(first);
+        assert_eq!(writers.0.lock().unwrap().writers, 1);
+        drop(second);
+        assert_eq!(writers.0.lock().unwrap().writers, 0);
+    }
+
+    #[test]
+    fn cleanup_and_store_writes_shut_each_other_out() {
+        let writers = StoreWriters(Mutex::new(StoreUse::default()));
+        let writing = writers.begin().unwrap();
+        assert!(writers.clean(|| Ok(false)).is_err());
+        drop(writing);
+        assert!(writers.clean(|| Ok(true)).is_err());
+
+        let cleaning = writers.clean(|| Ok(false)).unwrap();
+        assert!(writers.begin().is_err());
+        assert!(writers.hold_off_cleanup().is_err());
+        assert!(writers.clean(|| Ok(false)).is_err());
+        drop(cleaning);
+
+        let holding = writers.hold_off_cleanup().unwrap();
+        drop(holding);
+        drop(writers.begin().unwrap());
+        assert_eq!(writers.0.lock().unwrap().writers, 0);
+    }
+
+    #[test]
+    fn parses_model_names_like_ollama() {
+        let name = ModelName::parse("llama3.1");
+        assert_eq!(
+            (
+                name.host.as_str(),
+                name.namespace.as_str(),
+                name.tag.as_str()
+            ),
+            (DEFAULT_HOST, DEFAULT_NAMESPACE, "latest")
+        );
+        assert_eq!(name.display(), "llama3.1:latest");
+        assert_eq!(ModelName::parse("jane/tiny:q4").display(), "jane/tiny:q4");
+        let name = ModelName::parse("localhost:5000/team/coder");
+        assert_eq!(name.host, "localhost:5000");
+        assert_eq!(name.display(), "localhost:5000/team/coder:latest");
+    }
+
+    fn manifest(dir: &Path, name: &str, tag: &str, layers: &[(&str, u64)]) {
+        let model_dir = dir
+            .join("manifests")
+            .join(DEFAULT_HOST)
+            .join(DEFAULT_NAMESPACE)
+            .join(name);
+        std::fs::create_dir_all(&model_dir).unwrap();
+        let layers: Vec<_> = layers
+            .iter()
+            .map(|(digest, size)| serde_json::json!({ "digest": digest, "size": size }))
+            .collect();
+        let body = serde_json::json!({ "config": layers[0], "layers": &layers[1..] });
+        std::fs::write(model_dir.join(tag), body.to_string()).unwrap();
+    }
+
+    fn blob(dir: &Path, file: &str, size: usize) {
+        std::fs::write(dir.join("blobs").join(file), vec![0u8; size]).unwrap();
+    }
+
+    fn temp_store() -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("innertalk-models-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(dir.join("blobs")).unwrap();
+        // Two tags share the weights; each has its own config
+        manifest(
+            &dir,
+            "tiny",
+            "latest",
+            &[("sha256:c1", 10), ("sha256:w", 1000)],
+        );
+        manifest(
+            &dir,
+            "tiny",
+            "chat",
+            &[("sha256:c2", 20), ("sha256:w", 1000)],
+        );
+        blob(&dir, "sha256-c1", 10);
+        blob(&dir, "sha256-c2", 20);
+        blob(&dir, "sha256-w", 1000);
+        blob(&dir, "sha256-old", 300);
+        blob(&dir, "sha256-next-partial-0", 50);
+        dir
+    }
+
+    #[test]
+    fn sizes_models_and_removes_only_orphans() {
+        let dir = temp_store();
+        let store = Store::scan(&dir);
+
+        let usage = store.usage();
+        let chat = usage.iter().find(|m| m.name == "tiny:chat").unwrap();
+        assert_eq!((chat.size_bytes, chat.unique_bytes), (1020, 20));
+
+        let mut orphans: Vec<u64> = store.orphans().iter().map(|(_, size)| *size).collect();
+        orphans.sort();
+        assert_eq!(orphans, vec![50, 300]);
+
+        let needed: Manifest = serde_json::from_value(serde_json::json!({
+            "config": { "digest": "sha256:c3", "size": 5 },
+            "layers": [{ "digest": "sha256:w", "size": 1000 }],
+        }))
+        .unwrap();
+        assert_eq!(bytes_to_fetch(&needed, &store), 5);
+
+        let result = remove_orphans(&store);
+        assert_eq!((result.removed_blobs, result.freed_bytes), (2, 350));
+        assert!(dir.join("blobs/sha256-w").exists());
+        assert!(!dir.join("blobs/sha256-old").exists());
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
@@ -0,0 +1,271 @@
+//! Incremental decoder for Ollama's newline-delimited JSON streams.
+//!
+//! Network chunks are buffered as raw bytes and only split on `\n`, so a
+//! multi-byte UTF-8 character cut in half by a chunk boundary is reassembled
+//! before the line is decoded.
+
+use serde::de::DeserializeOwned;
+use serde::Deserialize;
+use std::fmt;
+
+/// One line of an Ollama stream: either a payload or an `{"error": "..."}`
+/// object reporting a failure mid-stream (out of memory, model load, ...).
+#[derive(Debug, Deserialize, PartialEq)]
+#[serde(untagged)]
+pub enum StreamLine<T> {
+    Error { error: String },
+    Item(T),
+}
+
+/// A line that could not be decoded, kept so callers can log it.
+#[derive(Debug, PartialEq)]
+pub struct MalformedLine {
+    pub line: String,
+    pub error: String,
+}
+
+impl fmt::Display for MalformedLine {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} (line: {:?})", self.error, self.line)
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct NdjsonDecoder {
+    buffer: Vec<u8>,
+    /// Start of the first line not yet handed out.
+    start: usize,
+    /// Offset up to which `buffer` is known to contain no newline.
+    scanned: usize,
+}
+
+impl NdjsonDecoder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Append a network chunk. Consumed lines are dropped here, once per
+    /// chunk, rather than after every line.
+    pub fn push(&mut self, bytes: &[u8]) {
+        if self.start > 0 {
+            self.buffer.drain(..self.start);
+            self.scanned -= self.start;
+            self.start = 0;
+        }
+        self.buffer.extend_from_slice(bytes);
+    }
+
+    /// Decode the next complete line, skipping blank ones. Returns `None`
+    /// once the buffer holds no further newline.
+    pub fn next_line<T: DeserializeOwned>(
+        &mut self,
+    ) -> Option<Result<StreamLine<T>, MalformedLine>> {
+        loop {
+            let offset = self.buffer[self.scanned..].iter().position(|&b| b == b'\n');
+            let Some(offset) = offset else {
+                self.scanned = self.buffer.len();
+                return None;
+            };
+
+            let end = self.scanned + offset;
+            let line = trim(&self.buffer[self.start..end]);
+            let parsed = (!line.is_empty()).then(|| decode(line));
+
+            self.start = end + 1;
+            self.scanned = self.start;
+
+            if let Some(parsed) = parsed {
+                return Some(parsed);
+            }
+        }
+    }
+
+    /// Decode whatever is left once the stream has ended without a final
+    /// newline.
+    pub fn finish<T: DeserializeOwned>(&mut self) -> Option<Result<StreamLine<T>, MalformedLine>> {
+        let line = trim(&self.buffer[self.start..]);
+        let parsed = (!line.is_empty()).then(|| decode(line));
+        self.buffer.clear();
+        self.start = 0;
+        self.scanned = 0;
+        parsed
+    }
+}
+
+fn decode<T: DeserializeOwned>(line: &[u8]) -> Result<StreamLine<T>, MalformedLine> {
+    serde_json::from_slice(line).map_err(|e| MalformedLine {
+        line: String::from_utf8_lossy(line).into_owned(),
+        error: e.to_string(),
+    })
+}
+
+fn trim(bytes: &[u8]) -> &[u8] {
+    let start = bytes
+        .iter()
+        .position(|b| !b.is_ascii_whitespace())
+        .unwrap_or(bytes.len());
+    let end = bytes
+        .iter()
+        .rposition(|b| !b.is_ascii_whitespace())
+        .map_or(start, |i| i + 1);
+    &bytes[start..end]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Debug, Deserialize, PartialEq)]
+    struct Chunk {
+        content: String,
+    }
+
+    fn item(content: &str) -> StreamLine<Chunk> {
+        StreamLine::Item(Chunk {
+            content: content.to_string(),
+        })
+    }
+
+    /// Feed `input` in pieces cut at `splits` and collect every decoded line.
+    fn decode_split(
+        input: &[u8],
+        splits: &[usize],
+    ) -> Vec<Result<StreamLine<Chunk>, MalformedLine>> {
+        let mut decoder = NdjsonDecoder::new();
+        let mut out = Vec::new();
+        let mut last = 0;
+        for &split in splits.iter().chain(std::iter::once(&input.len())) {
+            decoder.push(&input[last..split]);
+            while let Some(line) = decoder.next_line() {
+                out.push(line);
+            }
+            last = split;
+        }
+        out.extend(decoder.finish());
+        out
+    }
+
+    const MULTIBYTE: &str =
+        "{\"content\":\"héllo 🦀\"}\n{\"content\":\"日本語\"}\n{\"content\":\"👩‍💻 ok\"}\n";
+
+    #[test]
+    fn decodes_whole_input() {
+        let lines = decode_split(MULTIBYTE.as_bytes(), &[]);
+        assert_eq!(
+            lines,
+            vec![Ok(item("héllo 🦀")), Ok(item("日本語")), Ok(item("👩‍💻 ok"))]
+        );
+    }
+
+    #[test]
+    fn every_single_split_point_preserves_multibyte_text() {
+        let input = MULTIBYTE.as_bytes();
+        let expected = decode_split(input, &[]);
+        for split in 1..input.len() {
+            assert_eq!(
+                decode_split(input, &[split]),
+                expected,
+                "split at byte {split}"
+            );
+        }
+    }
+
+    #[test]
+    fn every_pair_of_split_points_preserves_multibyte_text() {
+        let input = MULTIBYTE.as_bytes();
+        let expected = decode_split(input, &[]);
+        for a in 1..input.len() {
+            for b in a..input.len() {
+                assert_eq!(
+                    decode_split(input, &[a, b]),
+                    expected,
+                    "splits at {a} and {b}"
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn byte_at_a_time() {
+        let input = MULTIBYTE.as_bytes();
+        let splits: Vec<usize> = (1..input.len()).collect();
+        assert_eq!(decode_split(input, &splits), decode_split(input, &[]));
+    }
+
+    #[test]
+    fn skips_blank_lines_and_handles_crlf() {
+        let input = b"\n\r\n  {\"content\":\"a\"}\r\n\n\t\n{\"content\":\"b\"}\r\n";
+        assert_eq!(
+            decode_split(input, &[3, 9]),
+            vec![Ok(item("a")), Ok(item("b"))]
+        );
+    }
+
+    #[test]
+    fn recognises_error_objects() {
+        let input = b"{\"content\":\"a\"}\n{\"error\":\"model requires more system memory\"}\n";
+        assert_eq!(
+            decode_split(input, &[20]),
+            vec![
+                Ok(item("a")),
+                Ok(StreamLine::Error {
+                    error: "model requires more system memory".to_string()
+                }),
+            ]
+        );
+    }
+
+    #[test]
+    fn reports_malformed_lines_and_keeps_going() {
+        let input = b"{\"content\":\"a\"}\n{\"content\":\n{\"other\":1}\n{\"content\":\"b\"}\n";
+        let lines = decode_split(input, &[17, 18]);
+        assert_eq!(lines.len(), 4);
+        assert_eq!(lines[0], Ok(item("a")));
+        assert_eq!(lines[1].as_ref().unwrap_err().line, "{\"content\":");
+        assert_eq!(lines[2].as_ref().unwrap_err().line, "{\"other\":1}");
+        assert_eq!(lines[3], Ok(item("b")));
+    }
+
+    #[test]
+    fn reports_invalid_utf8_as_malformed() {
+        let input = b"{\"content\":\"\xff\xfe\"}\n{\"content\":\"b\"}\n";
+        let lines = decode_split(input, &[]);
+        assert!(lines[0].is_err());
+        assert_eq!(lines[1], Ok(item("b")));
+    }
+
+    #[test]
+    fn finish_decodes_trailing_line_without_newline() {
+        let input = "{\"content\":\"a\"}\n{\"content\":\"ü\"}".as_bytes();
+        let splits: Vec<usize> = (1..input.len()).collect();
+        assert_eq!(
+            decode_split(input, &splits),
+            vec![Ok(item("a")), Ok(item("ü"))]
+        );
+    }
+
+    #[test]
+    fn incomplete_line_is_held_until_its_newline_arrives() {
+        let mut decoder = NdjsonDecoder::new();
+        decoder.push(b"{\"content\":");
+        assert_eq!(decoder.next_line::<Chunk>(), None);
+        decoder.push(b"\"x\"}");
+        assert_eq!(decoder.next_line::<Chunk>(), None);
+        decoder.push(b"\n");
+        assert_eq!(decoder.next_line::<Chunk>(), Some(Ok(item("x"))));
+        assert_eq!(decoder.finish::<Chunk>(), None);
+    }
+
+    #[test]
+    fn consumed_lines_are_dropped_from_the_buffer() {
+        let mut decoder = NdjsonDecoder::new();
+        for _ in 0..1000 {
+            decoder.push(b"{\"content\":\"a\"}\n{\"con");
+            while decoder.next_line::<Chunk>().is_some() {}
+            decoder.push(b"tent\":\"b\"}\n");
+            while decoder.next_line::<Chunk>().is_some() {}
+        }
+        decoder.push(b"");
+        assert!(decoder.buffer.is_empty());
+    }
+}
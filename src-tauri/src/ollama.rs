@@ -1,28 +1,165 @@
-use futures_util::StreamExt;
+use crate::endpoint::Endpoint;
+use crate::embeddings;
+use crate::error::AppError;
+use crate::fit;
+use crate::http::{self, EndpointClass, HttpClient};
+use crate::images::{self, ImageAttachment};
+use crate::model_create::{self, CreateModel, CreateProgress};
+use crate::model_info::{FamilyDetails, ModelDetails, ShowResponse};
+use crate::model_store;
+use crate::provider::{ChatRequest, ChatTurn, KeepAlive, LlmProvider, Message, ToolCall, ToolCallFunction};
+use crate::rag::{self, Citation, RetrievalSettings};
+use crate::sidecar;
+use crate::ndjson::{MalformedLine, NdjsonDecoder, StreamLine};
+use crate::structured::{OutputError, OutputFormat};
+use crate::tools::{ToolRegistry, ToolSettings};
+use futures_util::future::BoxFuture;
+use serde::de::DeserializeOwned;
 use serde::{Deserialize, Serialize};
-use std::sync::Mutex;
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::sync::atomic::AtomicU64;
+use std::sync::{Arc, Mutex};
 use tauri::ipc::Channel;
-use tauri::{Emitter, Manager};
-use tokio::task::AbortHandle;
+use tauri::{Emitter, Manager, State};
+use tokio::task::{AbortHandle, JoinHandle};
 
-const OLLAMA_BASE: &str = "http://localhost:11434";
+/// Upper bound on call → execute → respond rounds in a single chat request.
+const MAX_TOOL_ROUNDS: usize = 8;
 
 // ── Chat cancellation ───────────────────────────────────────────────
 
-pub struct ChatAbortHandle(pub Mutex<Option<AbortHandle>>);
+/// Running chat streams keyed by the request id supplied by the frontend.
+pub struct ChatStreams(pub Mutex<HashMap<String, AbortHandle>>);
+
+impl ChatStreams {
+    /// Spawn a chat and register it so `cancel` can reach it.
+    fn start<T: Send + 'static>(
+        &self,
+        request_id: &str,
+        spawn: impl FnOnce() -> JoinHandle<T>,
+    ) -> Result<JoinHandle<T>, AppError> {
+        let mut running = self.0.lock()?;
+        if running.contains_key(request_id) {
+            return Err(AppError::InvalidInput(format!(
+                "Chat request {request_id} is already running"
+            )));
+        }
+        let task = spawn();
+        running.insert(request_id.to_string(), task.abort_handle());
+        Ok(task)
+    }
+
+    /// Unregister a finished chat. A cancelled chat's id may already belong
+    /// to a newer one, which has to stay cancellable.
+    fn finish(&self, request_id: &str, task: tokio::task::Id) {
+        if let Ok(mut running) = self.0.lock() {
+            if running.get(request_id).is_some_and(|handle| handle.id() == task) {
+                running.remove(request_id);
+            }
+        }
+    }
+
+    fn cancel(&self, request_id: &str) -> Result<(), AppError> {
+        let handle = self.0.lock()?.remove(request_id);
+        if let Some(handle) = handle {
+            handle.abort();
+        }
+        Ok(())
+    }
+}
 
 // ── Public types (sent to frontend) ──────────────────────────────────
 
-#[derive(Debug, Serialize, Deserialize, Clone)]
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+#[serde(rename_all = "camelCase")]
 pub struct OllamaModel {
     pub name: String,
     pub size: u64,
+    /// Embedding-only model, not usable for chat.
+    #[serde(default)]
+    pub embedding: bool,
+    #[serde(default)]
+    pub family: String,
+    /// Like "8.0B".
+    #[serde(default)]
+    pub parameter_size: String,
+    /// Like "Q4_K_M".
+    #[serde(default)]
+    pub quantization_level: String,
+    #[serde(default)]
+    pub digest: String,
+    #[serde(default)]
+    pub modified_at: Option<String>,
+    /// Only filled in by `show_model`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub details: Option<ModelDetails>,
+}
+
+/// A model currently loaded in memory, from `/api/ps`.
+#[derive(Debug, Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct RunningModel {
+    pub name: String,
+    /// Total memory the loaded model takes, in bytes.
+    pub size: u64,
+    /// The part of `size` held in GPU memory.
+    pub size_vram: u64,
+    /// RFC 3339 time at which the model will be unloaded if left idle.
+    pub expires_at: Option<String>,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct ChatMessage {
     pub role: String,
     pub content: String,
+    /// Attachments for vision models, sent to Ollama base64-encoded.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub images: Vec<ImageAttachment>,
+}
+
+/// Per-request generation parameters, forwarded as Ollama's `options` object.
+/// Unset fields are omitted so the model's own defaults apply.
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+#[serde(rename_all(deserialize = "camelCase", serialize = "snake_case"))]
+pub struct ChatOptions {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub temperature: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub num_ctx: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub seed: Option<i64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub top_p: Option<f32>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub stop: Vec<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub num_predict: Option<i32>,
+}
+
+impl ChatOptions {
+    fn validate(&self) -> Result<(), AppError> {
+        if let Some(t) = self.temperature {
+            if !(0.0..=2.0).contains(&t) {
+                return Err(AppError::InvalidInput(format!(
+                    "Invalid temperature {t} (expected 0.0 to 2.0)"
+                )));
+            }
+        }
+        if let Some(p) = self.top_p {
+            if !(0.0..=1.0).contains(&p) {
+                return Err(AppError::InvalidInput(format!(
+                    "Invalid top_p {p} (expected 0.0 to 1.0)"
+                )));
+            }
+        }
+        if self.num_ctx == Some(0) {
+            return Err(AppError::InvalidInput(
+                "Invalid num_ctx 0 (must be positive)".to_string(),
+            ));
+        }
+        Ok(())
+    }
 }
 
 #[derive(Debug, Serialize, Clone)]
@@ -30,28 +167,103 @@ pub struct ChatMessage {
 pub enum StreamEvent {
     #[serde(rename = "token")]
     Token { content: String },
+    /// Reasoning trace from thinking models, kept apart from the answer.
+    #[serde(rename = "thinking")]
+    Thinking { content: String },
+    #[serde(rename = "toolCall")]
+    ToolCall {
+        name: String,
+        arguments: serde_json::Value,
+    },
+    #[serde(rename = "toolResult")]
+    ToolResult {
+        name: String,
+        result: String,
+        #[serde(rename = "isError")]
+        is_error: bool,
+    },
+    /// Chunks retrieved from attached folders and given to the model as
+    /// context. Sent before the first token.
+    #[serde(rename = "citations")]
+    Citations { citations: Vec<Citation> },
+    /// The finished reply does not match the requested `format`.
+    #[serde(rename = "invalidOutput")]
+    InvalidOutput { error: OutputError },
+    /// Something worth telling the user that doesn't stop the reply.
+    #[serde(rename = "warning")]
+    Warning { message: String },
     #[serde(rename = "done")]
     Done {
-        #[serde(rename = "totalDuration")]
-        total_duration: u64,
+        /// True when the user cancelled before Ollama finished the reply.
+        partial: bool,
+        /// Only reported by Ollama on a completed reply.
+        metrics: Option<GenerationMetrics>,
     },
     #[serde(rename = "error")]
-    Error { message: String },
+    Error(AppError),
 }
 
+/// Timing and token counts from Ollama's final chunk. Durations are in
+/// nanoseconds, as reported by Ollama.
 #[derive(Debug, Serialize, Clone)]
-#[serde(tag = "event", content = "data")]
-pub enum PullEvent {
-    #[serde(rename = "progress")]
-    Progress {
-        status: String,
-        completed: Option<u64>,
-        total: Option<u64>,
-    },
-    #[serde(rename = "done")]
-    Done {},
-    #[serde(rename = "error")]
-    Error { message: String },
+#[serde(rename_all = "camelCase")]
+pub struct GenerationMetrics {
+    pub total_duration: u64,
+    pub load_duration: u64,
+    pub prompt_eval_count: u64,
+    pub prompt_eval_duration: u64,
+    pub eval_count: u64,
+    pub eval_duration: u64,
+    pub tokens_per_second: Option<f64>,
+    pub prompt_tokens_per_second: Option<f64>,
+}
+
+impl GenerationMetrics {
+    /// Durations in nanoseconds; the per-second rates are derived from them.
+    pub fn new(
+        total_duration: u64,
+        load_duration: u64,
+        prompt_eval_count: u64,
+        prompt_eval_duration: u64,
+        eval_count: u64,
+        eval_duration: u64,
+    ) -> Self {
+        GenerationMetrics {
+            total_duration,
+            load_duration,
+            prompt_eval_count,
+            prompt_eval_duration,
+            eval_count,
+            eval_duration,
+            tokens_per_second: per_second(eval_count, eval_duration),
+            prompt_tokens_per_second: per_second(prompt_eval_count, prompt_eval_duration),
+        }
+    }
+
+    fn from_chunk(chunk: &ChatChunk) -> Self {
+        GenerationMetrics::new(
+            chunk.total_duration.unwrap_or(0),
+            chunk.load_duration.unwrap_or(0),
+            chunk.prompt_eval_count.unwrap_or(0),
+            chunk.prompt_eval_duration.unwrap_or(0),
+            chunk.eval_count.unwrap_or(0),
+            chunk.eval_duration.unwrap_or(0),
+        )
+    }
+}
+
+fn per_second(count: u64, duration_ns: u64) -> Option<f64> {
+    (duration_ns > 0).then(|| count as f64 / (duration_ns as f64 / 1_000_000_000.0))
+}
+
+/// One status line from a model pull. Byte counts are for the layer named
+/// by `digest`, not the whole model.
+#[derive(Debug, Clone)]
+pub struct PullProgress {
+    pub status: String,
+    pub digest: Option<String>,
+    pub completed: Option<u64>,
+    pub total: Option<u64>,
 }
 
 // ── Internal deserialization types ───────────────────────────────────
@@ -65,6 +277,49 @@ struct TagsResponse {
 struct ModelDetail {
     name: String,
     size: u64,
+    #[serde(default)]
+    digest: String,
+    #[serde(default)]
+    modified_at: Option<String>,
+    #[serde(default)]
+    details: FamilyDetails,
+}
+
+impl From<ModelDetail> for OllamaModel {
+    fn from(m: ModelDetail) -> Self {
+        OllamaModel {
+            embedding: embeddings::is_embedding_model(&m.name, &m.details.family),
+            name: m.name,
+            size: m.size,
+            family: m.details.family,
+            parameter_size: m.details.parameter_size,
+            quantization_level: m.details.quantization_level,
+            digest: m.digest,
+            modified_at: m.modified_at,
+            details: None,
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct PsResponse {
+    #[serde(default)]
+    models: Vec<PsModel>,
+}
+
+#[derive(Debug, Deserialize)]
+struct PsModel {
+    name: String,
+    #[serde(default)]
+    size: u64,
+    #[serde(default)]
+    size_vram: u64,
+    expires_at: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct EmbedResponse {
+    embeddings: Vec<Vec<f32>>,
 }
 
 #[derive(Debug, Deserialize)]
@@ -72,412 +327,1371 @@ struct ChatChunk {
     message: Option<ChatChunkMessage>,
     done: bool,
     total_duration: Option<u64>,
+    load_duration: Option<u64>,
+    prompt_eval_count: Option<u64>,
+    prompt_eval_duration: Option<u64>,
+    eval_count: Option<u64>,
+    eval_duration: Option<u64>,
 }
 
 #[derive(Debug, Deserialize)]
 struct ChatChunkMessage {
+    #[serde(default)]
     content: String,
+    #[serde(default)]
+    thinking: Option<String>,
+    #[serde(default)]
+    tool_calls: Vec<ToolCall>,
 }
 
+/// A status line from `/api/pull` or `/api/create`; only pulls carry bytes.
 #[derive(Debug, Deserialize)]
 struct PullChunk {
     status: Option<String>,
+    digest: Option<String>,
     completed: Option<u64>,
     total: Option<u64>,
 }
 
 // ── Helper: read newline-delimited JSON stream ───────────────────────
 
+/// Drive an NDJSON response through the shared decoder. Error objects,
+/// transport failures and gaps longer than the class's idle timeout end the
+/// stream with `Err`; malformed lines are logged and skipped.
 async fn read_ndjson_stream<T, F>(
     resp: reqwest::Response,
+    class: EndpointClass,
     mut handler: F,
-) -> Result<(), String>
+) -> Result<(), AppError>
 where
-    T: for<'de> Deserialize<'de>,
+    T: DeserializeOwned,
     F: FnMut(T),
 {
     let mut stream = resp.bytes_stream();
-    let mut buffer = String::new();
+    let mut decoder = NdjsonDecoder::new();
 
-    while let Some(chunk) = stream.next().await {
-        let bytes = chunk.map_err(|e| e.to_string())?;
-        buffer.push_str(&String::from_utf8_lossy(&bytes));
+    let mut handle = |line: Result<StreamLine<T>, MalformedLine>| match line {
+        Ok(StreamLine::Item(parsed)) => {
+            handler(parsed);
+            Ok(())
+        }
+        Ok(StreamLine::Error { error }) => Err(AppError::from_response(None, &error)),
+        Err(malformed) => {
+            log::warn!("Skipping malformed stream line: {malformed}");
+            Ok(())
+        }
+    };
 
-        while let Some(pos) = buffer.find('\n') {
-            let line = buffer[..pos].trim().to_string();
-            buffer = buffer[pos + 1..].to_string();
+    let idle = class.idle_timeout();
+    loop {
+        let Some(bytes) = http::next_chunk(&mut stream, idle).await? else {
+            break;
+        };
+        decoder.push(&bytes);
 
-            if line.is_empty() {
-                continue;
-            }
-
-            if let Ok(parsed) = serde_json::from_str::<T>(&line) {
-                handler(parsed);
-            }
+        while let Some(line) = decoder.next_line() {
+            handle(line)?;
         }
     }
 
+    if let Some(line) = decoder.finish() {
+        handle(line)?;
+    }
+
     Ok(())
 }
 
 // ── Commands ─────────────────────────────────────────────────────────
 
 #[tauri::command]
-pub async fn check_ollama() -> Result<bool, String> {
-    match reqwest::get(OLLAMA_BASE).await {
-        Ok(r) => Ok(r.status().is_success()),
-        Err(_) => Ok(false),
-    }
+pub async fn check_ollama(
+    endpoint: State<'_, Endpoint>,
+    http: State<'_, HttpClient>,
+) -> Result<bool, AppError> {
+    Ok(endpoint.provider(&http).check().await)
 }
 
 #[tauri::command]
-pub async fn list_models() -> Result<Vec<OllamaModel>, String> {
-    let url = format!("{}/api/tags", OLLAMA_BASE);
-    let resp = reqwest::get(&url)
-        .await
-        .map_err(|e| format!("Cannot reach Ollama: {e}"))?;
-    let tags: TagsResponse = resp
-        .json()
-        .await
-        .map_err(|e| format!("Bad response: {e}"))?;
-
-    Ok(tags
-        .models
-        .unwrap_or_default()
-        .into_iter()
-        .map(|m| OllamaModel {
-            name: m.name,
-            size: m.size,
-        })
-        .collect())
+pub async fn list_models(
+    endpoint: State<'_, Endpoint>,
+    http: State<'_, HttpClient>,
+) -> Result<Vec<OllamaModel>, AppError> {
+    endpoint.provider(&http).list_models().await
 }
 
+/// Total RAM rounded to GB; `fit::get_system_memory` has the exact figures.
 #[tauri::command]
-pub async fn get_system_ram() -> Result<u64, String> {
-    let mut sys = sysinfo::System::new();
-    sys.refresh_memory();
-    let gb = ((sys.total_memory() as f64) / (1024.0 * 1024.0 * 1024.0)).round() as u64;
-    Ok(gb)
+pub async fn get_system_ram() -> Result<u64, AppError> {
+    let total = fit::SystemMemory::current().total_bytes;
+    Ok(((total as f64) / (1024.0 * 1024.0 * 1024.0)).round() as u64)
 }
 
 #[tauri::command]
+#[allow(clippy::too_many_arguments)]
 pub async fn chat(
     app_handle: tauri::AppHandle,
+    endpoint: State<'_, Endpoint>,
+    http: State<'_, HttpClient>,
+    request_id: String,
     model: String,
     messages: Vec<ChatMessage>,
+    options: Option<ChatOptions>,
+    think: Option<bool>,
+    tools: Option<ToolSettings>,
+    format: Option<serde_json::Value>,
+    retrieval: Option<RetrievalSettings>,
+    keep_alive: Option<KeepAlive>,
     on_event: Channel<StreamEvent>,
-) -> Result<(), String> {
-    let client = reqwest::Client::new();
-    let url = format!("{}/api/chat", OLLAMA_BASE);
-
-    let body = serde_json::json!({
-        "model": model,
-        "messages": messages,
-        "stream": true,
-    });
-
-    let resp = client
-        .post(&url)
-        .json(&body)
-        .send()
+) -> Result<(), AppError> {
+    // Reading and downscaling images is blocking work
+    let messages = tokio::task::spawn_blocking(move || encode_messages(&messages))
         .await
-        .map_err(|e| format!("Cannot reach Ollama: {e}"))?;
+        .map_err(|e| AppError::Internal(format!("Image processing failed: {e}")))?
+        .map_err(AppError::InvalidInput)?;
+
+    if let Some(options) = &options {
+        options.validate()?;
+    }
 
-    if !resp.status().is_success() {
-        let status = resp.status();
-        let text = resp.text().await.unwrap_or_default();
-        return Err(format!("Ollama error ({status}): {text}"));
+    let format = format
+        .map(OutputFormat::parse)
+        .transpose()
+        .map_err(AppError::InvalidInput)?;
+
+    let registry = tools.map(|settings| Arc::new(ToolRegistry::builtin(&settings)));
+    let request = ChatRequest {
+        model,
+        messages,
+        options,
+        think,
+        tools: registry.as_deref().map(ToolRegistry::definitions).unwrap_or_default(),
+        format,
+        keep_alive,
+    };
+
+    let ignored = endpoint.provider(&http).ignored_settings(&request);
+    if !ignored.is_empty() {
+        let _ = on_event.send(StreamEvent::Warning {
+            message: format!("Not supported by this server, ignored: {}", ignored.join(", ")),
+        });
     }
 
-    // Spawn the streaming in a task so we can abort it
+    let streams = app_handle.state::<ChatStreams>();
+    // Spawn the whole request in a task so it can be aborted at any point,
+    // including while searching attached folders or waiting for the server
     let on_event_clone = on_event.clone();
-    let task = tokio::spawn(async move {
-        let mut stream = resp.bytes_stream();
-        let mut buffer = String::new();
-
-        while let Some(chunk) = stream.next().await {
-            let bytes = match chunk {
-                Ok(b) => b,
-                Err(_) => break,
-            };
-            buffer.push_str(&String::from_utf8_lossy(&bytes));
+    let provider = endpoint.provider(&http);
+    let task_handle = app_handle.clone();
+    let task = streams.start(&request_id, || {
+        tokio::spawn(async move {
+            let mut request = request;
+            if let Some(retrieval) = retrieval.filter(|r| !r.folders.is_empty()) {
+                let (messages, on_event) = (&mut request.messages, &on_event_clone);
+                add_sources(&task_handle, provider.as_ref(), &retrieval, messages, on_event).await;
+            }
 
-            while let Some(pos) = buffer.find('\n') {
-                let line = buffer[..pos].trim().to_string();
-                buffer = buffer[pos + 1..].to_string();
+            for _ in 0..MAX_TOOL_ROUNDS {
+                let Some(turn) = provider.chat_turn(&request, &on_event_clone).await? else {
+                    return Ok(());
+                };
 
-                if line.is_empty() {
-                    continue;
-                }
-
-                if let Ok(parsed) = serde_json::from_str::<ChatChunk>(&line) {
-                    if parsed.done {
+                let tool_registry = match &registry {
+                    Some(tool_registry) if !turn.tool_calls.is_empty() => tool_registry,
+                    _ => {
+                        if let Some(Err(error)) = request.format.as_ref().map(|f| f.check(&turn.content)) {
+                            let _ = on_event_clone.send(StreamEvent::InvalidOutput { error });
+                        }
                         let _ = on_event_clone.send(StreamEvent::Done {
-                            total_duration: parsed.total_duration.unwrap_or(0),
+                            partial: false,
+                            metrics: turn.metrics,
                         });
-                    } else if let Some(msg) = parsed.message {
-                        if !msg.content.is_empty() {
-                            let _ = on_event_clone.send(StreamEvent::Token {
-                                content: msg.content,
-                            });
-                        }
+                        return Ok(());
                     }
+                };
+
+                // Run the requested tools and feed the results back to the model
+                request.messages.push(Message {
+                    role: "assistant".to_string(),
+                    content: turn.content,
+                    tool_calls: turn.tool_calls.clone(),
+                    ..Default::default()
+                });
+
+                for call in turn.tool_calls {
+                    let ToolCallFunction { name, arguments } = &call.function;
+                    let _ = on_event_clone.send(StreamEvent::ToolCall {
+                        name: name.clone(),
+                        arguments: arguments.clone(),
+                    });
+
+                    // Tools read files and directories, which blocks
+                    let (tool, tool_name, tool_args) = (tool_registry.clone(), name.clone(), arguments.clone());
+                    let outcome = tokio::task::spawn_blocking(move || tool.call(&tool_name, &tool_args))
+                        .await
+                        .map_err(|e| AppError::Internal(format!("Tool {name} failed: {e}")))?;
+                    let (result, is_error) = match outcome {
+                        Ok(output) => (output, false),
+                        Err(e) => (e, true),
+                    };
+                    let _ = on_event_clone.send(StreamEvent::ToolResult {
+                        name: name.clone(),
+                        result: result.clone(),
+                        is_error,
+                    });
+
+                    request.messages.push(Message::tool_result(call, result));
                 }
             }
-        }
-    });
 
-    // Store the abort handle so cancel_chat can kill this task
-    if let Some(state) = app_handle.try_state::<ChatAbortHandle>() {
-        *state.0.lock().unwrap() = Some(task.abort_handle());
-    }
+            let _ = on_event_clone.send(StreamEvent::Error(AppError::ToolRoundsExceeded(
+                MAX_TOOL_ROUNDS,
+            )));
+            Ok(())
+        })
+    })?;
+    let task_id = task.id();
 
     // Wait for task to finish (or get aborted)
-    match task.await {
-        Ok(()) => {}
+    let result = match task.await {
+        Ok(result) => result,
         Err(e) if e.is_cancelled() => {
             // Cancelled by user — send done event
-            let _ = on_event.send(StreamEvent::Done { total_duration: 0 });
+            let _ = on_event.send(StreamEvent::Done {
+                partial: true,
+                metrics: None,
+            });
+            Ok(())
         }
-        Err(e) => return Err(format!("Chat task failed: {e}")),
-    }
+        Err(e) => Err(AppError::Internal(format!("Chat task failed: {e}"))),
+    };
+
+    // Unregister this stream
+    streams.finish(&request_id, task_id);
+
+    result
+}
 
-    // Clear the abort handle
-    if let Some(state) = app_handle.try_state::<ChatAbortHandle>() {
-        *state.0.lock().unwrap() = None;
+/// Put passages from the attached folders that match the latest question
+/// in front of it. Runs inside the chat task so the embedding, which may
+/// wait for a model to load, can be cancelled with the rest of the chat.
+async fn add_sources(
+    app_handle: &tauri::AppHandle,
+    provider: &dyn LlmProvider,
+    retrieval: &RetrievalSettings,
+    messages: &mut Vec<Message>,
+    on_event: &Channel<StreamEvent>,
+) {
+    let Some(position) = messages.iter().rposition(|m| m.role == "user") else {
+        return;
+    };
+    let retrieved = rag::retrieve(
+        app_handle,
+        provider,
+        &retrieval.folders,
+        &messages[position].content,
+        retrieval.top_k.unwrap_or(rag::DEFAULT_TOP_K),
+    )
+    .await;
+
+    // Sources are a bonus; a missing or mismatched embedding model
+    // shouldn't cost the user their answer
+    match retrieved {
+        Ok(citations) if !citations.is_empty() => {
+            messages.insert(position, Message {
+                role: "system".to_string(),
+                content: rag::context_prompt(&citations),
+                ..Default::default()
+            });
+            let _ = on_event.send(StreamEvent::Citations { citations });
+        }
+        Ok(_) => {}
+        Err(e) => {
+            log::warn!("Retrieval failed, answering without sources: {e}");
+            let _ = on_event.send(StreamEvent::Warning {
+                message: format!("Could not search attached folders ({e}), answering without sources"),
+            });
+        }
     }
+}
 
-    Ok(())
+/// Convert frontend messages to provider messages, encoding image attachments.
+fn encode_messages(messages: &[ChatMessage]) -> Result<Vec<Message>, String> {
+    messages
+        .iter()
+        .map(|m| {
+            Ok(Message {
+                role: m.role.clone(),
+                content: m.content.clone(),
+                images: m
+                    .images
+                    .iter()
+                    .map(images::encode_for_model)
+                    .collect::<Result<Vec<_>, _>>()?,
+                ..Default::default()
+            })
+        })
+        .collect()
 }
 
 #[tauri::command]
-pub async fn cancel_chat(app_handle: tauri::AppHandle) -> Result<(), String> {
-    if let Some(state) = app_handle.try_state::<ChatAbortHandle>() {
-        if let Some(handle) = state.0.lock().unwrap().take() {
-            handle.abort();
-        }
+pub async fn cancel_chat(app_handle: tauri::AppHandle, request_id: String) -> Result<(), AppError> {
+    app_handle.state::<ChatStreams>().cancel(&request_id)
+}
+
+#[tauri::command]
+pub async fn wait_for_ollama(
+    endpoint: State<'_, Endpoint>,
+    http: State<'_, HttpClient>,
+) -> Result<bool, AppError> {
+    Ok(sidecar::wait_for_ready(endpoint.provider(&http).as_ref(), 30, 500).await)
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DeletedModel {
+    /// Disk space given back, known only for the bundled Ollama's store.
+    /// Blobs other models share stay.
+    pub freed_bytes: Option<u64>,
+}
+
+/// Delete a model, unloading it from memory first if it is loaded.
+#[tauri::command]
+pub async fn delete_model(
+    endpoint: State<'_, Endpoint>,
+    http: State<'_, HttpClient>,
+    name: String,
+) -> Result<DeletedModel, AppError> {
+    let provider = endpoint.provider(&http);
+    // Asking to unload a model that isn't loaded would load it first
+    let loaded = provider
+        .running_models()
+        .await
+        .is_ok_and(|running| running.iter().any(|m| m.name == name));
+    if loaded {
+        provider.unload_model(&name).await?;
     }
-    Ok(())
+
+    let freed_bytes = if endpoint.uses_sidecar() {
+        model_store::unique_bytes(&name)
+    } else {
+        None
+    };
+    provider.delete_model(&name).await?;
+    Ok(DeletedModel { freed_bytes })
 }
 
+/// Everything known about one installed model, including what it can do.
 #[tauri::command]
-pub async fn pull_model(
+pub async fn show_model(
+    endpoint: State<'_, Endpoint>,
+    http: State<'_, HttpClient>,
     name: String,
-    on_event: Channel<PullEvent>,
-) -> Result<(), String> {
-    let client = reqwest::Client::new();
-    let url = format!("{}/api/pull", OLLAMA_BASE);
-
-    let body = serde_json::json!({
-        "name": name,
-        "stream": true,
-    });
+) -> Result<OllamaModel, AppError> {
+    endpoint.provider(&http).show_model(&name).await
+}
 
-    let resp = client
-        .post(&url)
-        .json(&body)
-        .send()
-        .await
-        .map_err(|e| format!("Cannot reach Ollama: {e}"))?;
+#[tauri::command]
+pub async fn list_running_models(
+    endpoint: State<'_, Endpoint>,
+    http: State<'_, HttpClient>,
+) -> Result<Vec<RunningModel>, AppError> {
+    endpoint.provider(&http).running_models().await
+}
 
-    if !resp.status().is_success() {
-        return Err(format!("Pull failed: {}", resp.status()));
+/// Load `name` into memory ahead of the first message.
+#[tauri::command]
+pub async fn load_model(
+    endpoint: State<'_, Endpoint>,
+    http: State<'_, HttpClient>,
+    name: String,
+    keep_alive: Option<KeepAlive>,
+) -> Result<(), AppError> {
+    endpoint.provider(&http).load_model(&name, keep_alive.as_ref()).await
+}
+
+#[tauri::command]
+pub async fn unload_model(
+    endpoint: State<'_, Endpoint>,
+    http: State<'_, HttpClient>,
+    name: String,
+) -> Result<(), AppError> {
+    endpoint.provider(&http).unload_model(&name).await
+}
+
+// ── Ollama provider ─────────────────────────────────────────────────
+
+/// Ollama's native `/api/*` routes.
+pub struct OllamaProvider {
+    http: HttpClient,
+    base: String,
+}
+
+impl OllamaProvider {
+    pub fn new(http: HttpClient, base: String) -> Self {
+        OllamaProvider { http, base }
     }
 
-    read_ndjson_stream::<PullChunk, _>(resp, |chunk| {
-        let status = chunk.status.unwrap_or_default();
-        if status == "success" {
-            let _ = on_event.send(PullEvent::Done {});
-        } else {
-            let _ = on_event.send(PullEvent::Progress {
-                status,
-                completed: chunk.completed,
-                total: chunk.total,
-            });
+    fn url(&self, path: &str) -> String {
+        format!("{}{path}", self.base)
+    }
+
+    /// Make sure the server holds the file at `path` as a blob, uploading it
+    /// if needed, and return its file name and digest.
+    async fn upload_blob(
+        &self,
+        path: &std::path::Path,
+        on_progress: &(dyn Fn(CreateProgress) + Send + Sync),
+    ) -> Result<(String, String), AppError> {
+        let file_name = path
+            .file_name()
+            .map(|n| n.to_string_lossy().to_string())
+            .unwrap_or_default();
+        let total = std::fs::metadata(path)
+            .map_err(|e| AppError::io("Cannot read file", path, e))?
+            .len();
+        let report = |step: &str, done: u64| {
+            on_progress(CreateProgress {
+                status: format!("{step} {file_name}"),
+                completed: Some(done),
+                total: Some(total),
+            })
+        };
+
+        let hashed = Arc::new(AtomicU64::new(0));
+        let (owned, counter) = (path.to_path_buf(), hashed.clone());
+        let hashing = tokio::task::spawn_blocking(move || model_create::hash_file(&owned, &counter));
+        let digest = model_create::report_while(hashing, &hashed, |done| report("hashing", done))
+            .await
+            .map_err(|e| AppError::Internal(e.to_string()))??;
+
+        let url = self.url(&format!("/api/blobs/{digest}"));
+        let existing = self.http.head(&url, EndpointClass::Api).send().await?;
+        if existing.status().is_success() {
+            return Ok((file_name, digest));
         }
-    })
-    .await
-}
 
-#[tauri::command]
-pub async fn wait_for_ollama() -> Result<bool, String> {
-    let client = reqwest::Client::new();
-    for _ in 0..30 {
-        if let Ok(r) = client.get(OLLAMA_BASE).send().await {
-            if r.status().is_success() {
-                return Ok(true);
-            }
+        let file = std::fs::File::open(path).map_err(|e| AppError::io("Cannot read file", path, e))?;
+        let sent = Arc::new(AtomicU64::new(0));
+        let upload = self
+            .http
+            .post(&url, EndpointClass::Transfer)
+            .body(model_create::file_body(file, sent.clone()))
+            .send();
+        let resp = model_create::report_while(upload, &sent, |done| report("uploading", done)).await?;
+        if !resp.status().is_success() {
+            let status = resp.status();
+            let text = resp.text().await.unwrap_or_default();
+            return Err(AppError::from_response(Some(status), &text));
         }
-        tokio::time::sleep(std::time::Duration::from_millis(500)).await;
+        Ok((file_name, digest))
     }
-    Ok(false)
-}
 
-#[tauri::command]
-pub async fn delete_model(name: String) -> Result<(), String> {
-    let client = reqwest::Client::new();
-    let url = format!("{}/api/delete", OLLAMA_BASE);
-
-    let resp = client
-        .delete(&url)
-        .json(&serde_json::json!({ "name": name }))
-        .send()
-        .await
-        .map_err(|e| format!("Cannot reach Ollama: {e}"))?;
+    /// POST to `/api/generate` with no prompt, which only loads or unloads
+    /// the model according to `keep_alive`.
+    async fn generate_without_prompt(&self, body: serde_json::Value, class: EndpointClass) -> Result<(), AppError> {
+        let resp = self
+            .http
+            .post(&self.url("/api/generate"), class)
+            .json(&body)
+            .send()
+            .await?;
+
+        if resp.status().is_success() {
+            Ok(())
+        } else {
+            let status = resp.status();
+            let text = resp.text().await.unwrap_or_default();
+            Err(AppError::from_model_response(Some(status), &text))
+        }
+    }
 
-    if resp.status().is_success() {
-        Ok(())
-    } else {
-        let text = resp.text().await.unwrap_or_default();
-        Err(format!("Delete failed: {text}"))
+    fn chat_body(request: &ChatRequest) -> Result<serde_json::Value, AppError> {
+        let messages: Vec<serde_json::Value> = request.messages.iter().map(encode_message).collect();
+        let mut body = serde_json::json!({
+            "model": request.model,
+            "messages": messages,
+            "stream": true,
+        });
+
+        if let Some(options) = &request.options {
+            body["options"] = serde_json::to_value(options)?;
+        }
+        if let Some(think) = request.think {
+            body["think"] = serde_json::Value::Bool(think);
+        }
+        if let Some(format) = &request.format {
+            body["format"] = format.to_request();
+        }
+        if !request.tools.is_empty() {
+            body["tools"] = serde_json::json!(request.tools);
+        }
+        if let Some(keep_alive) = &request.keep_alive {
+            body["keep_alive"] = serde_json::to_value(keep_alive)?;
+        }
+        Ok(body)
     }
 }
 
-#[derive(Debug, Serialize)]
-#[serde(rename_all = "camelCase")]
-pub struct StorageInfo {
-    pub path: String,
-    pub size_bytes: u64,
+fn encode_message(m: &Message) -> serde_json::Value {
+    let mut msg = serde_json::json!({
+        "role": m.role,
+        "content": m.content,
+    });
+    if !m.images.is_empty() {
+        msg["images"] = serde_json::json!(m.images);
+    }
+    if !m.tool_calls.is_empty() {
+        msg["tool_calls"] = serde_json::json!(m.tool_calls);
+    }
+    if let Some(call) = &m.tool_call {
+        msg["tool_name"] = serde_json::json!(call.function.name);
+    }
+    msg
 }
 
-#[tauri::command]
-pub fn get_storage_info() -> Result<StorageInfo, String> {
-    // Models are stored in the default Ollama directory (~/.ollama/models)
-    let models_dir = dirs::home_dir()
-        .ok_or("Cannot find home directory")?
-        .join(".ollama")
-        .join("models");
+impl LlmProvider for OllamaProvider {
+    fn check(&self) -> BoxFuture<'_, bool> {
+        Box::pin(async move {
+            match self.http.get(&self.base, EndpointClass::Probe).send().await {
+                Ok(r) => r.status().is_success(),
+                Err(_) => false,
+            }
+        })
+    }
+
+    fn list_models(&self) -> BoxFuture<'_, Result<Vec<OllamaModel>, AppError>> {
+        Box::pin(async move {
+            let resp = self
+                .http
+                .get(&self.url("/api/tags"), EndpointClass::Api)
+                .send()
+                .await?;
+            let tags: TagsResponse = resp
+                .json()
+                .await
+                .map_err(|e| http::classify(e, EndpointClass::Api))?;
+
+            Ok(tags
+                .models
+                .unwrap_or_default()
+                .into_iter()
+                .map(OllamaModel::from)
+                .collect())
+        })
+    }
+
+    fn chat_turn<'a>(
+        &'a self,
+        request: &'a ChatRequest,
+        on_event: &'a Channel<StreamEvent>,
+    ) -> BoxFuture<'a, Result<Option<ChatTurn>, AppError>> {
+        Box::pin(async move {
+            let resp = self
+                .http
+                .post(&self.url("/api/chat"), EndpointClass::Generation)
+                .json(&Self::chat_body(request)?)
+                .send()
+                .await?;
+
+            if !resp.status().is_success() {
+                let status = resp.status();
+                let text = resp.text().await.unwrap_or_default();
+                return Err(AppError::from_model_response(Some(status), &text));
+            }
+
+            let mut turn = ChatTurn::default();
+            let result = read_ndjson_stream::<ChatChunk, _>(resp, EndpointClass::Generation, |chunk| {
+                if chunk.done {
+                    turn.metrics = Some(GenerationMetrics::from_chunk(&chunk));
+                }
+                if let Some(msg) = chunk.message {
+                    if let Some(thinking) = msg.thinking.filter(|t| !t.is_empty()) {
+                        let _ = on_event.send(StreamEvent::Thinking { content: thinking });
+                    }
+                    if !msg.content.is_empty() {
+                        turn.content.push_str(&msg.content);
+                        let _ = on_event.send(StreamEvent::Token {
+                            content: msg.content,
+                        });
+                    }
+                    turn.tool_calls.extend(msg.tool_calls);
+                }
+            })
+            .await;
+
+            if let Err(error) = result {
+                let _ = on_event.send(StreamEvent::Error(error));
+                return Ok(None);
+            }
+
+            Ok(Some(turn))
+        })
+    }
 
-    Ok(StorageInfo {
-        path: models_dir.to_string_lossy().to_string(),
-        size_bytes: dir_size(&models_dir),
-    })
+    fn pull_model<'a>(
+        &'a self,
+        name: &'a str,
+        on_progress: &'a (dyn Fn(PullProgress) + Send + Sync),
+    ) -> BoxFuture<'a, Result<(), AppError>> {
+        Box::pin(async move {
+            let body = serde_json::json!({
+                "name": name,
+                "stream": true,
+            });
+
+            let resp = self
+                .http
+                .post(&self.url("/api/pull"), EndpointClass::Transfer)
+                .json(&body)
+                .send()
+                .await?;
+
+            if !resp.status().is_success() {
+                let status = resp.status();
+                let text = resp.text().await.unwrap_or_default();
+                return Err(AppError::from_model_response(Some(status), &text));
+            }
+
+            let mut succeeded = false;
+            read_ndjson_stream::<PullChunk, _>(resp, EndpointClass::Transfer, |chunk| {
+                let status = chunk.status.unwrap_or_default();
+                if status == "success" {
+                    succeeded = true;
+                } else {
+                    on_progress(PullProgress {
+                        status,
+                        digest: chunk.digest,
+                        completed: chunk.completed,
+                        total: chunk.total,
+                    });
+                }
+            })
+            .await?;
+
+            if succeeded {
+                Ok(())
+            } else {
+                Err(AppError::ConnectionLost("Download ended before it completed".to_string()))
+            }
+        })
+    }
+
+    fn delete_model<'a>(&'a self, name: &'a str) -> BoxFuture<'a, Result<(), AppError>> {
+        Box::pin(async move {
+            let resp = self
+                .http
+                .delete(&self.url("/api/delete"), EndpointClass::Api)
+                .json(&serde_json::json!({ "name": name }))
+                .send()
+                .await?;
+
+            if resp.status().is_success() {
+                Ok(())
+            } else {
+                let status = resp.status();
+                let text = resp.text().await.unwrap_or_default();
+                Err(AppError::from_model_response(Some(status), &text))
+            }
+        })
+    }
+
+    fn create_model<'a>(
+        &'a self,
+        request: &'a CreateModel,
+        on_progress: &'a (dyn Fn(CreateProgress) + Send + Sync),
+    ) -> BoxFuture<'a, Result<(), AppError>> {
+        Box::pin(async move {
+            let weights = match &request.gguf {
+                Some(path) => Some(self.upload_blob(std::path::Path::new(path), on_progress).await?),
+                None => None,
+            };
+            let adapter = match &request.adapter {
+                Some(path) => Some(self.upload_blob(std::path::Path::new(path), on_progress).await?),
+                None => None,
+            };
+            let body = request.body(weights.as_ref(), adapter.as_ref());
+
+            let resp = self
+                .http
+                .post(&self.url("/api/create"), EndpointClass::Transfer)
+                .json(&body)
+                .send()
+                .await?;
+
+            if !resp.status().is_success() {
+                let status = resp.status();
+                let text = resp.text().await.unwrap_or_default();
+                return Err(AppError::from_model_response(Some(status), &text));
+            }
+
+            let mut succeeded = false;
+            read_ndjson_stream::<PullChunk, _>(resp, EndpointClass::Transfer, |chunk| {
+                let status = chunk.status.unwrap_or_default();
+                if status == "success" {
+                    succeeded = true;
+                } else {
+                    on_progress(CreateProgress::status(status));
+                }
+            })
+            .await?;
+
+            if succeeded {
+                Ok(())
+            } else {
+                Err(AppError::ConnectionLost("Model creation ended before it completed".to_string()))
+            }
+        })
+    }
+
+    fn copy_model<'a>(
+        &'a self,
+        source: &'a str,
+        destination: &'a str,
+    ) -> BoxFuture<'a, Result<(), AppError>> {
+        Box::pin(async move {
+            let resp = self
+                .http
+                .post(&self.url("/api/copy"), EndpointClass::Api)
+                .json(&serde_json::json!({ "source": source, "destination": destination }))
+                .send()
+                .await?;
+
+            if resp.status().is_success() {
+                Ok(())
+            } else {
+                let status = resp.status();
+                let text = resp.text().await.unwrap_or_default();
+                Err(AppError::from_model_response(Some(status), &text))
+            }
+        })
+    }
+
+    fn show_model<'a>(&'a self, name: &'a str) -> BoxFuture<'a, Result<OllamaModel, AppError>> {
+        Box::pin(async move {
+            let resp = self
+                .http
+                .post(&self.url("/api/show"), EndpointClass::Api)
+                .json(&serde_json::json!({ "model": name }))
+                .send()
+                .await?;
+
+            if !resp.status().is_success() {
+                let status = resp.status();
+                let text = resp.text().await.unwrap_or_default();
+                return Err(AppError::from_model_response(Some(status), &text));
+            }
+
+            let show: ShowResponse = resp
+                .json()
+                .await
+                .map_err(|e| http::classify(e, EndpointClass::Api))?;
+
+            // Size and digest are only listed by /api/tags
+            let listed = self
+                .list_models()
+                .await?
+                .into_iter()
+                .find(|m| m.name == name || m.name == format!("{name}:latest"));
+            let details = show.model_details(name);
+            Ok(OllamaModel {
+                embedding: details.capabilities.embedding,
+                family: show.details.family,
+                parameter_size: show.details.parameter_size,
+                quantization_level: show.details.quantization_level,
+                modified_at: show.modified_at,
+                details: Some(details),
+                ..listed.unwrap_or_else(|| OllamaModel {
+                    name: name.to_string(),
+                    ..Default::default()
+                })
+            })
+        })
+    }
+
+    fn running_models(&self) -> BoxFuture<'_, Result<Vec<RunningModel>, AppError>> {
+        Box::pin(async move {
+            let resp = self
+                .http
+                .get(&self.url("/api/ps"), EndpointClass::Api)
+                .send()
+                .await?;
+            let ps: PsResponse = resp
+                .json()
+                .await
+                .map_err(|e| http::classify(e, EndpointClass::Api))?;
+
+            Ok(ps
+                .models
+                .into_iter()
+                .map(|m| RunningModel {
+                    name: m.name,
+                    size: m.size,
+                    size_vram: m.size_vram,
+                    expires_at: m.expires_at,
+                })
+                .collect())
+        })
+    }
+
+    fn load_model<'a>(
+        &'a self,
+        name: &'a str,
+        keep_alive: Option<&'a KeepAlive>,
+    ) -> BoxFuture<'a, Result<(), AppError>> {
+        Box::pin(async move {
+            // A generate request without a prompt just loads the model;
+            // reading a large model from disk can take minutes
+            let mut body = serde_json::json!({ "model": name });
+            if let Some(keep_alive) = keep_alive {
+                body["keep_alive"] = serde_json::to_value(keep_alive)?;
+            }
+            self.generate_without_prompt(body, EndpointClass::Generation).await
+        })
+    }
+
+    fn unload_model<'a>(&'a self, name: &'a str) -> BoxFuture<'a, Result<(), AppError>> {
+        Box::pin(async move {
+            let body = serde_json::json!({ "model": name, "keep_alive": 0 });
+            self.generate_without_prompt(body, EndpointClass::Api).await
+        })
+    }
+
+    fn embed<'a>(
+        &'a self,
+        model: &'a str,
+        inputs: &'a [String],
+    ) -> BoxFuture<'a, Result<Vec<Vec<f32>>, AppError>> {
+        Box::pin(async move {
+            let resp = self
+                .http
+                .post(&self.url("/api/embed"), EndpointClass::Embedding)
+                .json(&serde_json::json!({ "model": model, "input": inputs }))
+                .send()
+                .await?;
+
+            if !resp.status().is_success() {
+                let status = resp.status();
+                let text = resp.text().await.unwrap_or_default();
+                return Err(AppError::from_model_response(Some(status), &text));
+            }
+
+            let body: EmbedResponse = resp
+                .json()
+                .await
+                .map_err(|e| http::classify(e, EndpointClass::Embedding))?;
+            Ok(body.embeddings)
+        })
+    }
 }
 
 #[tauri::command]
-pub fn read_file_content(path: String) -> Result<String, String> {
+pub fn read_file_content(path: String) -> Result<String, AppError> {
     let metadata = std::fs::metadata(&path)
-        .map_err(|e| format!("Cannot read file: {e}"))?;
+        .map_err(|e| AppError::io("Cannot read file", &path, e))?;
 
     if metadata.len() > 10 * 1024 * 1024 {
-        return Err("File too large (max 10 MB)".to_string());
+        return Err(AppError::InvalidInput("File too large (max 10 MB)".to_string()));
     }
 
     std::fs::read_to_string(&path)
-        .map_err(|e| format!("Cannot read file: {e}"))
+        .map_err(|e| AppError::io("Cannot read file", &path, e))
 }
 
-#[derive(Debug, Serialize)]
+#[derive(Debug, Serialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum FolderFileKind {
+    Text,
+    /// Content is left empty; pass the path as an image attachment instead.
+    Image,
+}
+
+/// Why a text file in a folder was left out.
+#[derive(Debug, Serialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub enum SkipReason {
+    /// Larger than `MAX_TEXT_FILE_SIZE`.
+    TooLarge,
+    /// Not valid UTF-8, or could not be read.
+    Unreadable,
+}
+
+/// Text files above this size are left out of context and the index.
+const MAX_TEXT_FILE_SIZE: u64 = 1024 * 1024;
+
+/// How many folders deep below the chosen one files are read.
+const MAX_FOLDER_DEPTH: usize = 5;
+
+const TEXT_EXTENSIONS: &[&str] = &[
+    "txt", "md", "json", "csv", "xml", "yaml", "yml", "toml", "ini", "cfg", "log",
+    "js", "ts", "tsx", "jsx", "py", "rs", "go", "java", "c", "cpp", "h", "hpp",
+    "css", "html", "sql", "sh", "bash", "zsh", "swift", "kt", "rb", "php",
+    "env", "gitignore", "dockerfile", "makefile",
+];
+
+/// Hidden entries and build output are never read.
+fn is_ignored(name: &str) -> bool {
+    name.starts_with('.') || name == "node_modules" || name == "target" || name == "dist"
+}
+
+#[derive(Debug, Serialize, Clone)]
 pub struct FolderFile {
     pub name: String,
     pub path: String,
     pub content: String,
+    pub kind: FolderFileKind,
 }
 
-#[tauri::command]
-pub fn read_folder_files(path: String) -> Result<Vec<FolderFile>, String> {
-    let text_extensions = [
-        "txt", "md", "json", "csv", "xml", "yaml", "yml", "toml", "ini", "cfg", "log",
-        "js", "ts", "tsx", "jsx", "py", "rs", "go", "java", "c", "cpp", "h", "hpp",
-        "css", "html", "sql", "sh", "bash", "zsh", "swift", "kt", "rb", "php",
-        "env", "gitignore", "dockerfile", "makefile",
-    ];
+/// The text files in `path` and its subfolders, plus images if asked for.
+/// Text files too large or unreadable to use are listed separately.
+fn scan_files(path: String, include_images: Option<bool>) -> FolderScan {
+    let image_extensions = if include_images.unwrap_or(false) {
+        images::IMAGE_EXTENSIONS
+    } else {
+        &[]
+    };
 
-    let mut files = Vec::new();
-    collect_text_files(std::path::Path::new(&path), &text_extensions, &mut files, 0);
-    Ok(files)
+    let mut scan = FolderScan::default();
+    collect_text_files(std::path::Path::new(&path), TEXT_EXTENSIONS, image_extensions, &mut scan, 0);
+    scan
+}
+
+#[tauri::command]
+pub fn read_folder_files(path: String, include_images: Option<bool>) -> Result<Vec<FolderFile>, AppError> {
+    Ok(scan_files(path, include_images).files)
 }
 
-fn collect_text_files(dir: &std::path::Path, exts: &[&str], out: &mut Vec<FolderFile>, depth: usize) {
-    if depth > 5 { return; }
+fn collect_text_files(
+    dir: &std::path::Path,
+    exts: &[&str],
+    image_exts: &[&str],
+    out: &mut FolderScan,
+    depth: usize,
+) {
+    if depth > MAX_FOLDER_DEPTH { return; }
     let Ok(entries) = std::fs::read_dir(dir) else { return };
     for entry in entries.flatten() {
         let path = entry.path();
-        let name = entry.file_name().to_string_lossy().to_string();
-        if name.starts_with('.') || name == "node_modules" || name == "target" || name == "dist" {
+        if is_ignored(&entry.file_name().to_string_lossy()) {
             continue;
         }
         if path.is_dir() {
-            collect_text_files(&path, exts, out, depth + 1);
+            collect_text_files(&path, exts, image_exts, out, depth + 1);
         } else if let Ok(meta) = entry.metadata() {
-            if meta.len() > 1024 * 1024 { continue; } // skip files > 1MB
-            let ext = path.extension().and_then(|e| e.to_str()).unwrap_or("");
-            let fname = name.to_lowercase();
-            if exts.iter().any(|e| *e == ext.to_lowercase()) || fname == "makefile" || fname == "dockerfile" {
-                if let Ok(content) = std::fs::read_to_string(&path) {
-                    out.push(FolderFile {
-                        name: path.strip_prefix(dir.parent().unwrap_or(dir))
-                            .unwrap_or(&path)
-                            .to_string_lossy()
-                            .to_string(),
-                        path: path.to_string_lossy().to_string(),
-                        content,
-                    });
-                }
-            }
+            collect_file(&path, dir, meta.len(), exts, image_exts, out);
         }
     }
 }
 
-fn dir_size(path: &std::path::Path) -> u64 {
-    let mut total = 0;
-    if let Ok(entries) = std::fs::read_dir(path) {
-        for entry in entries.flatten() {
-            let meta = entry.metadata();
-            if let Ok(m) = meta {
-                if m.is_dir() {
-                    total += dir_size(&entry.path());
-                } else {
-                    total += m.len();
-                }
-            }
+/// Add the file at `path`, found in `dir`, if it is text or a wanted image.
+fn collect_file(
+    path: &std::path::Path,
+    dir: &std::path::Path,
+    len: u64,
+    exts: &[&str],
+    image_exts: &[&str],
+    out: &mut FolderScan,
+) {
+    let ext = path.extension().and_then(|e| e.to_str()).unwrap_or("");
+    let display_name = || {
+        path.strip_prefix(dir.parent().unwrap_or(dir))
+            .unwrap_or(path)
+            .to_string_lossy()
+            .to_string()
+    };
+    if image_exts.iter().any(|e| *e == ext.to_lowercase()) {
+        // Images are validated and downscaled when sent, not here
+        out.files.push(FolderFile {
+            name: display_name(),
+            path: path.to_string_lossy().to_string(),
+            content: String::new(),
+            kind: FolderFileKind::Image,
+        });
+        return;
+    }
+    let fname = path.file_name().unwrap_or_default().to_string_lossy().to_lowercase();
+    if exts.iter().any(|e| *e == ext.to_lowercase()) || fname == "makefile" || fname == "dockerfile" {
+        let file_path = path.to_string_lossy().to_string();
+        if len > MAX_TEXT_FILE_SIZE {
+            out.skipped.push((file_path, SkipReason::TooLarge));
+            return;
+        }
+        match std::fs::read_to_string(path) {
+            Ok(content) => out.files.push(FolderFile {
+                name: display_name(),
+                path: file_path,
+                content,
+                kind: FolderFileKind::Text,
+            }),
+            Err(_) => out.skipped.push((file_path, SkipReason::Unreadable)),
         }
     }
-    total
 }
 
 // ── File watching ───────────────────────────────────────────────────
 
 use notify::RecursiveMode;
 use notify_debouncer_mini::{new_debouncer, DebouncedEventKind};
-use std::collections::HashMap;
+use std::path::{Path, PathBuf};
 use std::time::Duration;
 
 pub struct FolderWatchers(pub Mutex<HashMap<String, notify_debouncer_mini::Debouncer<notify::RecommendedWatcher>>>);
 
+/// What a scan of a folder found.
+#[derive(Debug, Default)]
+pub struct FolderScan {
+    pub files: Vec<FolderFile>,
+    /// Text files left out, by path.
+    pub skipped: Vec<(String, SkipReason)>,
+}
+
+/// Each text file last seen in a watched folder, keyed by path: its content
+/// hash, or why it was skipped.
+type FolderSnapshot = HashMap<String, Result<[u8; 32], SkipReason>>;
+
+#[derive(Debug, Serialize, Clone)]
+#[serde(tag = "change", rename_all = "lowercase")]
+pub enum FileChange {
+    Added(FolderFile),
+    Modified(FolderFile),
+    Deleted { path: String },
+    /// Still there, but now too large or unreadable to use.
+    Skipped { path: String, reason: SkipReason },
+}
+
+impl FileChange {
+    pub fn path(&self) -> &str {
+        match self {
+            FileChange::Added(file) | FileChange::Modified(file) => &file.path,
+            FileChange::Deleted { path } | FileChange::Skipped { path, .. } => path,
+        }
+    }
+}
+
+/// Payload of the `folder-index-updated` event.
+#[derive(Debug, Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct FolderUpdate {
+    pub folder: String,
+    pub changes: Vec<FileChange>,
+    /// Whether the folder has a retrieval index that now reflects the changes.
+    pub indexed: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<AppError>,
+}
+
+fn content_hash(content: &str) -> [u8; 32] {
+    Sha256::digest(content.as_bytes()).into()
+}
+
+/// Compare a fresh scan against the previous snapshot, updating it in place.
+fn diff_files(snapshot: &mut FolderSnapshot, scan: FolderScan) -> Vec<FileChange> {
+    diff_within(snapshot, scan, |_| true)
+}
+
+/// Like `diff_files` for a scan that only covered some paths; files
+/// outside `scanned` are left alone rather than taken as deleted.
+fn diff_within(
+    snapshot: &mut FolderSnapshot,
+    scan: FolderScan,
+    scanned: impl Fn(&str) -> bool,
+) -> Vec<FileChange> {
+    let mut changes = Vec::new();
+    let mut seen = std::collections::HashSet::new();
+    for file in scan.files {
+        let hash = Ok(content_hash(&file.content));
+        seen.insert(file.path.clone());
+        match snapshot.insert(file.path.clone(), hash) {
+            None => changes.push(FileChange::Added(file)),
+            Some(old) if old != hash => changes.push(FileChange::Modified(file)),
+            Some(_) => {}
+        }
+    }
+    for (path, reason) in scan.skipped {
+        seen.insert(path.clone());
+        if snapshot.insert(path.clone(), Err(reason)) != Some(Err(reason)) {
+            changes.push(FileChange::Skipped { path, reason });
+        }
+    }
+    let mut deleted: Vec<String> = snapshot
+        .keys()
+        .filter(|path| !seen.contains(*path) && scanned(path))
+        .cloned()
+        .collect();
+    deleted.sort();
+    for path in deleted {
+        snapshot.remove(&path);
+        changes.push(FileChange::Deleted { path });
+    }
+    changes
+}
+
+fn scan_folder(path: &str) -> FolderScan {
+    scan_files(path.to_string(), None)
+}
+
+/// Re-read only the files and folders a watch event named, updating the
+/// snapshot of the folder at `root`. Paths reported outside `root` as it is
+/// spelled, as through a symlink, fall back to a full scan.
+fn rescan_paths(snapshot: &mut FolderSnapshot, root: &str, mut paths: Vec<PathBuf>) -> Vec<FileChange> {
+    // An event on a folder covers everything in it
+    paths.sort();
+    paths.dedup_by(|later, earlier| later.starts_with(&*earlier));
+
+    let mut scan = FolderScan::default();
+    for path in &paths {
+        let Ok(relative) = path.strip_prefix(root) else {
+            return diff_files(snapshot, scan_folder(root));
+        };
+        let depth = relative.components().count();
+        if relative.components().any(|c| is_ignored(&c.as_os_str().to_string_lossy())) {
+            continue;
+        }
+        if path.is_dir() {
+            collect_text_files(path, TEXT_EXTENSIONS, &[], &mut scan, depth);
+        } else if let (Some(dir), Ok(meta)) = (path.parent(), path.metadata()) {
+            if depth <= MAX_FOLDER_DEPTH + 1 {
+                collect_file(path, dir, meta.len(), TEXT_EXTENSIONS, &[], &mut scan);
+            }
+        }
+    }
+    diff_within(snapshot, scan, |file| paths.iter().any(|path| Path::new(file).starts_with(path)))
+}
+
 #[tauri::command]
-pub fn watch_folder(app_handle: tauri::AppHandle, path: String) -> Result<(), String> {
+pub fn watch_folder(app_handle: tauri::AppHandle, path: String) -> Result<(), AppError> {
     let watchers_state = app_handle.state::<FolderWatchers>();
-    let mut watchers = watchers_state.0.lock().map_err(|e| e.to_string())?;
+    let mut watchers = watchers_state.0.lock()?;
 
     // Already watching
     if watchers.contains_key(&path) {
         return Ok(());
     }
 
+    // Take the baseline off the main thread; the first debounced event can't
+    // arrive before it finishes, as it waits on the same lock
+    let snapshot = Arc::new(Mutex::new(FolderSnapshot::new()));
+    {
+        let snapshot = snapshot.clone();
+        let path = path.clone();
+        let (ready_tx, ready_rx) = std::sync::mpsc::channel();
+        std::thread::spawn(move || {
+            let Ok(mut snapshot) = snapshot.lock() else { return };
+            let _ = ready_tx.send(());
+            diff_files(&mut snapshot, scan_folder(&path));
+        });
+        let _ = ready_rx.recv();
+    }
+
     let app = app_handle.clone();
     let watch_path = path.clone();
 
     let mut debouncer = new_debouncer(Duration::from_secs(2), move |events: Result<Vec<notify_debouncer_mini::DebouncedEvent>, notify::Error>| {
-        if let Ok(events) = events {
-            let has_changes = events.iter().any(|e| matches!(e.kind, DebouncedEventKind::Any));
-            if has_changes {
-                let _ = app.emit("folder-changed", &watch_path);
-            }
+        let Ok(events) = events else { return };
+        let paths: Vec<PathBuf> = events
+            .into_iter()
+            .filter(|e| matches!(e.kind, DebouncedEventKind::Any))
+            .map(|e| e.path)
+            .collect();
+        if paths.is_empty() {
+            return;
+        }
+        let changes = {
+            let Ok(mut snapshot) = snapshot.lock() else { return };
+            rescan_paths(&mut snapshot, &watch_path, paths)
+        };
+        if changes.is_empty() {
+            return;
         }
-    }).map_err(|e| format!("Failed to create watcher: {e}"))?;
+
+        let app = app.clone();
+        let folder = watch_path.clone();
+        tauri::async_runtime::spawn(async move {
+            let provider = app.state::<Endpoint>().provider(&app.state::<HttpClient>());
+            let (indexed, error) = match rag::update_index(&app, provider.as_ref(), &folder, &changes).await {
+                Ok(indexed) => (indexed, None),
+                Err(e) => {
+                    log::warn!("Failed to update index for {folder}: {e}");
+                    (false, Some(e))
+                }
+            };
+            let _ = app.emit("folder-index-updated", FolderUpdate { folder, changes, indexed, error });
+        });
+    }).map_err(|e| watch_error(&path, e))?;
 
     debouncer.watcher().watch(std::path::Path::new(&path), RecursiveMode::Recursive)
-        .map_err(|e| format!("Failed to watch folder: {e}"))?;
+        .map_err(|e| watch_error(&path, e))?;
 
     watchers.insert(path, debouncer);
     Ok(())
 }
 
 #[tauri::command]
-pub fn unwatch_folder(app_handle: tauri::AppHandle, path: String) -> Result<(), String> {
+pub fn unwatch_folder(app_handle: tauri::AppHandle, path: String) -> Result<(), AppError> {
     let watchers_state = app_handle.state::<FolderWatchers>();
-    let mut watchers = watchers_state.0.lock().map_err(|e| e.to_string())?;
+    let mut watchers = watchers_state.0.lock()?;
     watchers.remove(&path);
     Ok(())
 }
+
+fn watch_error(path: &str, e: notify::Error) -> AppError {
+    match e.kind {
+        notify::ErrorKind::Io(io) => AppError::io("Failed to watch folder", path, io),
+        notify::ErrorKind::PathNotFound => AppError::NotFound(path.to_string()),
+        _ => AppError::Io {
+            message: "Failed to watch folder".to_string(),
+            details: e.to_string(),
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn text_file(path: &str, content: &str) -> FolderFile {
+        FolderFile {
+            name: path.trim_start_matches('/').to_string(),
+            path: path.to_string(),
+            content: content.to_string(),
+            kind: FolderFileKind::Text,
+        }
+    }
+
+    fn scan(files: Vec<FolderFile>) -> FolderScan {
+        FolderScan {
+            files,
+            skipped: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn diff_files_reports_added_modified_and_deleted() {
+        let mut snapshot = FolderSnapshot::new();
+        let changes = diff_files(&mut snapshot, scan(vec![text_file("/a.md", "one"), text_file("/b.md", "two")]));
+        assert_eq!(changes.len(), 2);
+        assert!(changes.iter().all(|c| matches!(c, FileChange::Added(_))));
+
+        let changes = diff_files(&mut snapshot, scan(vec![text_file("/a.md", "one"), text_file("/c.md", "three")]));
+        let summary: Vec<_> = changes
+            .iter()
+            .map(|c| (c.path().to_string(), serde_json::to_value(c).unwrap()["change"].clone()))
+            .collect();
+        assert_eq!(
+            summary,
+            vec![
+                ("/c.md".to_string(), serde_json::json!("added")),
+                ("/b.md".to_string(), serde_json::json!("deleted")),
+            ]
+        );
+
+        let changes = diff_files(&mut snapshot, scan(vec![text_file("/a.md", "one!"), text_file("/c.md", "three")]));
+        assert!(matches!(changes.as_slice(), [FileChange::Modified(f)] if f.path == "/a.md"));
+        assert!(diff_files(&mut snapshot, scan(vec![text_file("/a.md", "one!"), text_file("/c.md", "three")])).is_empty());
+    }
+
+    #[test]
+    fn files_that_become_unusable_are_skipped_not_deleted() {
+        let mut snapshot = FolderSnapshot::new();
+        diff_files(&mut snapshot, scan(vec![text_file("/log.txt", "short")]));
+
+        let too_large = FolderScan {
+            files: Vec::new(),
+            skipped: vec![("/log.txt".to_string(), SkipReason::TooLarge)],
+        };
+        let changes = diff_files(&mut snapshot, too_large);
+        assert!(matches!(
+            changes.as_slice(),
+            [FileChange::Skipped { path, reason: SkipReason::TooLarge }] if path == "/log.txt"
+        ));
+        assert_eq!(
+            serde_json::to_value(&changes[0]).unwrap(),
+            serde_json::json!({ "change": "skipped", "path": "/log.txt", "reason": "tooLarge" })
+        );
+
+        // Reported once, then again only when it changes
+        let still_large = FolderScan {
+            files: Vec::new(),
+            skipped: vec![("/log.txt".to_string(), SkipReason::TooLarge)],
+        };
+        assert!(diff_files(&mut snapshot, still_large).is_empty());
+        let changes = diff_files(&mut snapshot, scan(vec![text_file("/log.txt", "short")]));
+        assert!(matches!(changes.as_slice(), [FileChange::Modified(f)] if f.path == "/log.txt"));
+    }
+
+    #[test]
+    fn rescans_only_the_paths_an_event_names() {
+        let root = std::env::temp_dir().join(format!("innertalk-watch-{}", std::process::id()));
+        std::fs::create_dir_all(root.join("notes")).unwrap();
+        std::fs::write(root.join("a.md"), "one").unwrap();
+        std::fs::write(root.join("b.md"), "two").unwrap();
+        std::fs::write(root.join("notes/c.md"), "three").unwrap();
+        let folder = root.to_string_lossy().to_string();
+        let mut snapshot = FolderSnapshot::new();
+        diff_files(&mut snapshot, scan_folder(&folder));
+
+        // b.md changed too, but no event named it
+        std::fs::write(root.join("a.md"), "one!").unwrap();
+        std::fs::write(root.join("b.md"), "two!").unwrap();
+        std::fs::write(root.join("d.md"), "four").unwrap();
+        std::fs::remove_dir_all(root.join("notes")).unwrap();
+        let paths = vec![root.join("a.md"), root.join("d.md"), root.join("notes"), root.join("notes/c.md")];
+        let changes = rescan_paths(&mut snapshot, &folder, paths);
+        std::fs::remove_dir_all(&root).unwrap();
+
+        let summary: Vec<_> = changes
+            .iter()
+            .map(|c| {
+                let name = Path::new(c.path()).strip_prefix(&root).unwrap().to_string_lossy().to_string();
+                (name, serde_json::to_value(c).unwrap()["change"].clone())
+            })
+            .collect();
+        assert_eq!(
+            summary,
+            vec![
+                ("a.md".to_string(), serde_json::json!("modified")),
+                ("d.md".to_string(), serde_json::json!("added")),
+                ("notes/c.md".to_string(), serde_json::json!("deleted")),
+            ]
+        );
+    }
+
+    #[test]
+    fn chat_body_passes_keep_alive_through() {
+        let mut request = ChatRequest {
+            model: "llama3.2".to_string(),
+            messages: Vec::new(),
+            options: None,
+            think: None,
+            tools: Vec::new(),
+            format: None,
+            keep_alive: None,
+        };
+        assert!(OllamaProvider::chat_body(&request).unwrap().get("keep_alive").is_none());
+
+        request.keep_alive = serde_json::from_value(serde_json::json!("30m")).unwrap();
+        assert_eq!(OllamaProvider::chat_body(&request).unwrap()["keep_alive"], "30m");
+        request.keep_alive = serde_json::from_value(serde_json::json!(-1)).unwrap();
+        assert_eq!(OllamaProvider::chat_body(&request).unwrap()["keep_alive"], -1);
+    }
+
+    #[test]
+    fn a_restarted_chat_stays_cancellable_after_the_cancelled_one_ends() {
+        tauri::async_runtime::block_on(async {
+            let streams = ChatStreams(Mutex::new(HashMap::new()));
+            let pending = || tokio::spawn(std::future::pending::<()>());
+
+            let first = streams.start("chat", pending).unwrap();
+            let first_id = first.id();
+            streams.cancel("chat").unwrap();
+            let second = streams.start("chat", pending).unwrap();
+            assert!(streams.start("chat", pending).is_err());
+
+            // The cancelled call cleans up after the new one has registered
+            assert!(first.await.unwrap_err().is_cancelled());
+            streams.finish("chat", first_id);
+
+            streams.cancel("chat").unwrap();
+            assert!(second.await.unwrap_err().is_cancelled());
+        });
+    }
+}
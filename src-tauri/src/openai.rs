@@ -0,0 +1,639 @@
+//! Client for OpenAI-compatible servers (llama.cpp server, LM Studio, vLLM)
+//! via `/v1/models` and streaming `/v1/chat/completions`.
+
+use crate::embeddings;
+use crate::error::AppError;
+use crate::http::{self, EndpointClass, HttpClient};
+use crate::model_create::{CreateModel, CreateProgress};
+use crate::ollama::{GenerationMetrics, OllamaModel, PullProgress, RunningModel, StreamEvent};
+use crate::provider::{
+    ChatRequest, ChatTurn, KeepAlive, LlmProvider, Message, ToolCall, ToolCallFunction,
+};
+use crate::sse::SseDecoder;
+use crate::structured::OutputFormat;
+use futures_util::future::BoxFuture;
+use serde::Deserialize;
+use serde_json::{json, Value};
+use std::time::Instant;
+use tauri::ipc::Channel;
+
+pub struct OpenAiProvider {
+    http: HttpClient,
+    base: String,
+    api_key: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ModelsResponse {
+    #[serde(default)]
+    data: Vec<ModelEntry>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ModelEntry {
+    id: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct EmbeddingsResponse {
+    data: Vec<EmbeddingEntry>,
+}
+
+#[derive(Debug, Deserialize)]
+struct EmbeddingEntry {
+    #[serde(default)]
+    index: usize,
+    embedding: Vec<f32>,
+}
+
+#[derive(Debug, Deserialize)]
+struct CompletionChunk {
+    #[serde(default)]
+    choices: Vec<Choice>,
+    usage: Option<Usage>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Choice {
+    #[serde(default)]
+    delta: Delta,
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct Delta {
+    content: Option<String>,
+    /// Reasoning trace; llama.cpp and vLLM name it differently.
+    #[serde(alias = "reasoning")]
+    reasoning_content: Option<String>,
+    #[serde(default)]
+    tool_calls: Vec<ToolCallDelta>,
+}
+
+/// A fragment of a tool call. The name arrives first and the arguments as
+/// a JSON string spread over many chunks, keyed by `index`.
+#[derive(Debug, Deserialize)]
+struct ToolCallDelta {
+    #[serde(default)]
+    index: usize,
+    id: Option<String>,
+    function: Option<FunctionDelta>,
+}
+
+#[derive(Debug, Deserialize)]
+struct FunctionDelta {
+    name: Option<String>,
+    arguments: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Usage {
+    prompt_tokens: u64,
+    completion_tokens: u64,
+}
+
+#[derive(Debug, Default)]
+struct PartialToolCall {
+    id: Option<String>,
+    name: String,
+    arguments: String,
+}
+
+impl OpenAiProvider {
+    pub fn new(http: HttpClient, base: String, api_key: Option<String>) -> Self {
+        OpenAiProvider {
+            http,
+            base,
+            api_key,
+        }
+    }
+
+    /// Accepts a base URL with or without the `/v1` suffix.
+    fn url(&self, path: &str) -> String {
+        if self.base.ends_with("/v1") {
+            format!("{}{path}", self.base)
+        } else {
+            format!("{}/v1{path}", self.base)
+        }
+    }
+
+    async fn models(&self, class: EndpointClass) -> Result<ModelsResponse, AppError> {
+        let resp = self
+            .http
+            .get(&self.url("/models"), class)
+            .bearer_auth(self.api_key.as_deref())
+            .send()
+            .await?;
+        if !resp.status().is_success() {
+            let status = resp.status();
+            let text = resp.text().await.unwrap_or_default();
+            return Err(AppError::from_response(Some(status), &text));
+        }
+        resp.json().await.map_err(|e| http::classify(e, class))
+    }
+}
+
+/// `num_ctx`, `think` and `keep_alive` have no OpenAI equivalent (the
+/// context size is fixed when the server loads the model) and are left out;
+/// see `ignored_settings`.
+fn chat_body(request: &ChatRequest) -> Value {
+    let messages: Vec<Value> = request.messages.iter().map(encode_message).collect();
+    let mut body = json!({
+        "model": request.model,
+        "messages": messages,
+        "stream": true,
+        "stream_options": { "include_usage": true },
+    });
+
+    if let Some(options) = &request.options {
+        if let Some(temperature) = options.temperature {
+            body["temperature"] = json!(temperature);
+        }
+        if let Some(top_p) = options.top_p {
+            body["top_p"] = json!(top_p);
+        }
+        if let Some(seed) = options.seed {
+            body["seed"] = json!(seed);
+        }
+        if !options.stop.is_empty() {
+            body["stop"] = json!(options.stop);
+        }
+        // Ollama uses -1 for "no limit"
+        if let Some(max_tokens) = options.num_predict.filter(|n| *n > 0) {
+            body["max_tokens"] = json!(max_tokens);
+        }
+    }
+    if !request.tools.is_empty() {
+        body["tools"] = json!(request.tools);
+    }
+    match &request.format {
+        Some(OutputFormat::Json) => body["response_format"] = json!({ "type": "json_object" }),
+        Some(OutputFormat::Schema { schema, .. }) => {
+            body["response_format"] = json!({
+                "type": "json_schema",
+                "json_schema": { "name": "response", "schema": schema },
+            })
+        }
+        None => {}
+    }
+    body
+}
+
+fn encode_message(m: &Message) -> Value {
+    if let Some(call) = &m.tool_call {
+        return json!({
+            "role": "tool",
+            "tool_call_id": call.id,
+            "content": m.content,
+        });
+    }
+
+    let content =
+        if m.images.is_empty() {
+            json!(m.content)
+        } else {
+            let mut parts = vec![json!({ "type": "text", "text": m.content })];
+            parts.extend(m.images.iter().map(
+                |image| json!({ "type": "image_url", "image_url": { "url": data_url(image) } }),
+            ));
+            json!(parts)
+        };
+
+    let mut msg = json!({ "role": m.role, "content": content });
+    if !m.tool_calls.is_empty() {
+        let calls: Vec<Value> = m
+            .tool_calls
+            .iter()
+            .map(|call| {
+                json!({
+                    "id": call.id,
+                    "type": "function",
+                    "function": {
+                        "name": call.function.name,
+                        "arguments": call.function.arguments.to_string(),
+                    },
+                })
+            })
+            .collect();
+        msg["tool_calls"] = json!(calls);
+    }
+    msg
+}
+
+/// Images reach us as base64 PNG or JPEG (see `images::prepare`), which can
+/// be told apart by their encoded signature.
+fn data_url(image: &str) -> String {
+    let mime = if image.starts_with("iVBORw0KGgo") {
+        "image/png"
+    } else {
+        "image/jpeg"
+    };
+    format!("data:{mime};base64,{image}")
+}
+
+fn finish_tool_calls(partial: Vec<PartialToolCall>) -> Vec<ToolCall> {
+    partial
+        .into_iter()
+        .enumerate()
+        .filter(|(_, call)| !call.name.is_empty())
+        .map(|(index, call)| {
+            let arguments = if call.arguments.trim().is_empty() {
+                json!({})
+            } else {
+                serde_json::from_str(&call.arguments).unwrap_or(Value::String(call.arguments))
+            };
+            ToolCall {
+                id: Some(call.id.unwrap_or_else(|| format!("call_{index}"))),
+                function: ToolCallFunction {
+                    name: call.name,
+                    arguments,
+                },
+            }
+        })
+        .collect()
+}
+
+/// Drive an SSE response until `[DONE]`. Error payloads, transport failures
+/// and idle gaps end the stream with `Err`; malformed events are logged and
+/// skipped.
+async fn read_sse_stream<F>(
+    resp: reqwest::Response,
+    class: EndpointClass,
+    mut handler: F,
+) -> Result<(), AppError>
+where
+    F: FnMut(CompletionChunk),
+{
+    let mut stream = resp.bytes_stream();
+    let mut decoder = SseDecoder::new();
+
+    // Ok(false) once the server has signalled the end of the stream
+    let mut handle = |data: String| -> Result<bool, AppError> {
+        if data == "[DONE]" {
+            return Ok(false);
+        }
+        let value: Value = match serde_json::from_str(&data) {
+            Ok(value) => value,
+            Err(e) => {
+                log::warn!("Skipping malformed stream event: {e} (data: {data:?})");
+                return Ok(true);
+            }
+        };
+        if value.get("error").is_some() {
+            return Err(AppError::from_response(None, &data));
+        }
+        match serde_json::from_value(value) {
+            Ok(chunk) => handler(chunk),
+            Err(e) => log::warn!("Skipping malformed stream event: {e} (data: {data:?})"),
+        }
+        Ok(true)
+    };
+
+    let idle = class.idle_timeout();
+    loop {
+        let Some(bytes) = http::next_chunk(&mut stream, idle).await? else {
+            break;
+        };
+        decoder.push(&bytes);
+
+        while let Some(data) = decoder.next_event() {
+            if !handle(data)? {
+                return Ok(());
+            }
+        }
+    }
+
+    if let Some(data) = decoder.finish() {
+        handle(data)?;
+    }
+
+    Ok(())
+}
+
+impl LlmProvider for OpenAiProvider {
+    fn check(&self) -> BoxFuture<'_, bool> {
+        Box::pin(async move { self.models(EndpointClass::Probe).await.is_ok() })
+    }
+
+    fn list_models(&self) -> BoxFuture<'_, Result<Vec<OllamaModel>, AppError>> {
+        Box::pin(async move {
+            let models = self.models(EndpointClass::Api).await?;
+            // Sizes aren't part of the OpenAI API
+            Ok(models
+                .data
+                .into_iter()
+                .map(|m| OllamaModel {
+                    embedding: embeddings::is_embedding_model(&m.id, ""),
+                    name: m.id,
+                    ..Default::default()
+                })
+                .collect())
+        })
+    }
+
+    fn ignored_settings(&self, request: &ChatRequest) -> Vec<&'static str> {
+        let mut ignored = Vec::new();
+        if request.options.as_ref().is_some_and(|o| o.num_ctx.is_some()) {
+            ignored.push("context length");
+        }
+        if request.think.is_some() {
+            ignored.push("thinking");
+        }
+        if request.keep_alive.is_some() {
+            ignored.push("keep alive");
+        }
+        ignored
+    }
+
+    fn chat_turn<'a>(
+        &'a self,
+        request: &'a ChatRequest,
+        on_event: &'a Channel<StreamEvent>,
+    ) -> BoxFuture<'a, Result<Option<ChatTurn>, AppError>> {
+        Box::pin(async move {
+            let started = Instant::now();
+            let resp = self
+                .http
+                .post(&self.url("/chat/completions"), EndpointClass::Generation)
+                .bearer_auth(self.api_key.as_deref())
+                .json(&chat_body(request))
+                .send()
+                .await?;
+
+            if !resp.status().is_success() {
+                let status = resp.status();
+                let text = resp.text().await.unwrap_or_default();
+                return Err(AppError::from_model_response(Some(status), &text));
+            }
+
+            let mut turn = ChatTurn::default();
+            let mut tool_calls: Vec<PartialToolCall> = Vec::new();
+            let mut usage = None;
+            let mut first_token = None;
+
+            let result = read_sse_stream(resp, EndpointClass::Generation, |chunk| {
+                if chunk.usage.is_some() {
+                    usage = chunk.usage;
+                }
+                for choice in chunk.choices {
+                    let delta = choice.delta;
+                    if let Some(thinking) = delta.reasoning_content.filter(|t| !t.is_empty()) {
+                        first_token.get_or_insert_with(Instant::now);
+                        let _ = on_event.send(StreamEvent::Thinking { content: thinking });
+                    }
+                    if let Some(content) = delta.content.filter(|c| !c.is_empty()) {
+                        first_token.get_or_insert_with(Instant::now);
+                        turn.content.push_str(&content);
+                        let _ = on_event.send(StreamEvent::Token { content });
+                    }
+                    for fragment in delta.tool_calls {
+                        if tool_calls.len() <= fragment.index {
+                            tool_calls.resize_with(fragment.index + 1, Default::default);
+                        }
+                        let call = &mut tool_calls[fragment.index];
+                        if fragment.id.is_some() {
+                            call.id = fragment.id;
+                        }
+                        if let Some(function) = fragment.function {
+                            if let Some(name) = function.name {
+                                call.name.push_str(&name);
+                            }
+                            if let Some(arguments) = function.arguments {
+                                call.arguments.push_str(&arguments);
+                            }
+                        }
+                    }
+                }
+            })
+            .await;
+
+            if let Err(error) = result {
+                let _ = on_event.send(StreamEvent::Error(error));
+                return Ok(None);
+            }
+
+            turn.tool_calls = finish_tool_calls(tool_calls);
+            // Without server-side timings, measure from our side of the wire
+            turn.metrics = usage.map(|usage| {
+                let first_token = first_token.unwrap_or_else(Instant::now);
+                GenerationMetrics::new(
+                    started.elapsed().as_nanos() as u64,
+                    0,
+                    usage.prompt_tokens,
+                    first_token.duration_since(started).as_nanos() as u64,
+                    usage.completion_tokens,
+                    first_token.elapsed().as_nanos() as u64,
+                )
+            });
+
+            Ok(Some(turn))
+        })
+    }
+
+    fn pull_model<'a>(
+        &'a self,
+        _name: &'a str,
+        _on_progress: &'a (dyn Fn(PullProgress) + Send + Sync),
+    ) -> BoxFuture<'a, Result<(), AppError>> {
+        Box::pin(async {
+            Err(AppError::Unsupported(
+                "Downloading models is not supported for OpenAI-compatible servers".to_string(),
+            ))
+        })
+    }
+
+    fn delete_model<'a>(&'a self, _name: &'a str) -> BoxFuture<'a, Result<(), AppError>> {
+        Box::pin(async {
+            Err(AppError::Unsupported(
+                "Deleting models is not supported for OpenAI-compatible servers".to_string(),
+            ))
+        })
+    }
+
+    fn create_model<'a>(
+        &'a self,
+        _request: &'a CreateModel,
+        _on_progress: &'a (dyn Fn(CreateProgress) + Send + Sync),
+    ) -> BoxFuture<'a, Result<(), AppError>> {
+        Box::pin(async {
+            Err(AppError::Unsupported(
+                "Creating models is not supported for OpenAI-compatible servers".to_string(),
+            ))
+        })
+    }
+
+    fn copy_model<'a>(
+        &'a self,
+        _source: &'a str,
+        _destination: &'a str,
+    ) -> BoxFuture<'a, Result<(), AppError>> {
+        Box::pin(async {
+            Err(AppError::Unsupported(
+                "Copying models is not supported for OpenAI-compatible servers".to_string(),
+            ))
+        })
+    }
+
+    fn show_model<'a>(&'a self, _name: &'a str) -> BoxFuture<'a, Result<OllamaModel, AppError>> {
+        Box::pin(async {
+            Err(AppError::Unsupported(
+                "Model details are not available for OpenAI-compatible servers".to_string(),
+            ))
+        })
+    }
+
+    fn running_models(&self) -> BoxFuture<'_, Result<Vec<RunningModel>, AppError>> {
+        Box::pin(async {
+            Err(AppError::Unsupported(
+                "Listing loaded models is not supported for OpenAI-compatible servers".to_string(),
+            ))
+        })
+    }
+
+    fn load_model<'a>(
+        &'a self,
+        _name: &'a str,
+        _keep_alive: Option<&'a KeepAlive>,
+    ) -> BoxFuture<'a, Result<(), AppError>> {
+        Box::pin(async {
+            Err(AppError::Unsupported(
+                "Loading models is not supported for OpenAI-compatible servers".to_string(),
+            ))
+        })
+    }
+
+    fn unload_model<'a>(&'a self, _name: &'a str) -> BoxFuture<'a, Result<(), AppError>> {
+        Box::pin(async {
+            Err(AppError::Unsupported(
+                "Unloading models is not supported for OpenAI-compatible servers".to_string(),
+            ))
+        })
+    }
+
+    fn embed<'a>(
+        &'a self,
+        model: &'a str,
+        inputs: &'a [String],
+    ) -> BoxFuture<'a, Result<Vec<Vec<f32>>, AppError>> {
+        Box::pin(async move {
+            let resp = self
+                .http
+                .post(&self.url("/embeddings"), EndpointClass::Embedding)
+                .bearer_auth(self.api_key.as_deref())
+                .json(&json!({ "model": model, "input": inputs }))
+                .send()
+                .await?;
+
+            if !resp.status().is_success() {
+                let status = resp.status();
+                let text = resp.text().await.unwrap_or_default();
+                return Err(AppError::from_model_response(Some(status), &text));
+            }
+
+            let mut body: EmbeddingsResponse = resp
+                .json()
+                .await
+                .map_err(|e| http::classify(e, EndpointClass::Embedding))?;
+            // The spec doesn't promise input order, only an index per entry
+            body.data.sort_by_key(|entry| entry.index);
+            Ok(body.data.into_iter().map(|entry| entry.embedding).collect())
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ollama::ChatOptions;
+
+    fn request(messages: Vec<Message>) -> ChatRequest {
+        ChatRequest {
+            model: "qwen3".to_string(),
+            messages,
+            options: None,
+            think: None,
+            tools: Vec::new(),
+            format: None,
+            keep_alive: None,
+        }
+    }
+
+    #[test]
+    fn encodes_images_and_tool_round_trips() {
+        let call = ToolCall {
+            id: Some("call_0".to_string()),
+            function: ToolCallFunction {
+                name: "read_file".to_string(),
+                arguments: json!({ "path": "notes.md" }),
+            },
+        };
+        let body = chat_body(&request(vec![
+            Message {
+                role: "user".to_string(),
+                content: "What is this?".to_string(),
+                images: vec!["iVBORw0KGgoAAAA".to_string()],
+                ..Default::default()
+            },
+            Message {
+                role: "assistant".to_string(),
+                tool_calls: vec![call.clone()],
+                ..Default::default()
+            },
+            Message::tool_result(call, "hello".to_string()),
+        ]));
+
+        let messages = &body["messages"];
+        assert_eq!(
+            messages[0]["content"][1]["image_url"]["url"],
+            "data:image/png;base64,iVBORw0KGgoAAAA"
+        );
+        assert_eq!(
+            messages[1]["tool_calls"][0]["function"]["arguments"],
+            "{\"path\":\"notes.md\"}"
+        );
+        assert_eq!(
+            messages[2],
+            json!({ "role": "tool", "tool_call_id": "call_0", "content": "hello" })
+        );
+    }
+
+    #[test]
+    fn assembles_tool_calls_from_fragments() {
+        let calls = finish_tool_calls(vec![PartialToolCall {
+            id: None,
+            name: "read_file".to_string(),
+            arguments: "{\"path\": \"a.md\"}".to_string(),
+        }]);
+        assert_eq!(calls[0].id.as_deref(), Some("call_0"));
+        assert_eq!(calls[0].function.arguments, json!({ "path": "a.md" }));
+    }
+
+    #[test]
+    fn names_settings_it_cannot_honour() {
+        let provider =
+            OpenAiProvider::new(HttpClient::new(), "http://localhost:8080".to_string(), None);
+        let mut chat = request(Vec::new());
+        assert!(provider.ignored_settings(&chat).is_empty());
+
+        chat.think = Some(true);
+        chat.options = Some(ChatOptions {
+            num_ctx: Some(8192),
+            ..Default::default()
+        });
+        assert_eq!(provider.ignored_settings(&chat), vec!["context length", "thinking"]);
+
+        chat.keep_alive = serde_json::from_value(json!("30m")).unwrap();
+        assert_eq!(
+            provider.ignored_settings(&chat),
+            vec!["context length", "thinking", "keep alive"]
+        );
+    }
+
+    #[test]
+    fn accepts_base_urls_with_or_without_v1() {
+        let http = HttpClient::new();
+        let plain = OpenAiProvider::new(http.clone(), "http://localhost:8080".to_string(), None);
+        let v1 = OpenAiProvider::new(http, "http://localhost:1234/v1".to_string(), None);
+        assert_eq!(plain.url("/models"), "http://localhost:8080/v1/models");
+        assert_eq!(v1.url("/models"), "http://localhost:1234/v1/models");
+    }
+}
@@ -0,0 +1,164 @@
+//! The inference backend behind the chat and model commands. Ollama's native
+//! API is one implementation; any server speaking the OpenAI
+//! `/v1/chat/completions` protocol (llama.cpp server, LM Studio, vLLM) is
+//! the other.
+
+use crate::error::AppError;
+use crate::model_create::{CreateModel, CreateProgress};
+use crate::ollama::{
+    ChatOptions, GenerationMetrics, OllamaModel, PullProgress, RunningModel, StreamEvent,
+};
+use crate::structured::OutputFormat;
+use futures_util::future::BoxFuture;
+use serde::{Deserialize, Serialize};
+use tauri::ipc::Channel;
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Default)]
+#[serde(rename_all = "camelCase")]
+pub enum ProviderKind {
+    #[default]
+    Ollama,
+    /// OpenAI-compatible `/v1` API.
+    #[serde(rename = "openai")]
+    OpenAi,
+}
+
+pub trait LlmProvider: Send + Sync {
+    /// Whether the server is up and answering.
+    fn check(&self) -> BoxFuture<'_, bool>;
+
+    fn list_models(&self) -> BoxFuture<'_, Result<Vec<OllamaModel>, AppError>>;
+
+    /// Run one chat completion, streaming tokens to `on_event`. Returns
+    /// `Ok(None)` if the stream broke off midway; that error has already
+    /// been sent as `StreamEvent::Error`.
+    fn chat_turn<'a>(
+        &'a self,
+        request: &'a ChatRequest,
+        on_event: &'a Channel<StreamEvent>,
+    ) -> BoxFuture<'a, Result<Option<ChatTurn>, AppError>>;
+
+    /// Settings in `request` this server has no way to honour, named as
+    /// the user knows them. The chat still runs without them.
+    fn ignored_settings(&self, _request: &ChatRequest) -> Vec<&'static str> {
+        Vec::new()
+    }
+
+    /// Download a model, reporting each status line. Resolves once the
+    /// model is fully installed.
+    fn pull_model<'a>(
+        &'a self,
+        name: &'a str,
+        on_progress: &'a (dyn Fn(PullProgress) + Send + Sync),
+    ) -> BoxFuture<'a, Result<(), AppError>>;
+
+    fn delete_model<'a>(&'a self, name: &'a str) -> BoxFuture<'a, Result<(), AppError>>;
+
+    /// Build a model from an installed one or local weights, reporting
+    /// uploads and each status line. Resolves once the new model is listed.
+    fn create_model<'a>(
+        &'a self,
+        request: &'a CreateModel,
+        on_progress: &'a (dyn Fn(CreateProgress) + Send + Sync),
+    ) -> BoxFuture<'a, Result<(), AppError>>;
+
+    fn copy_model<'a>(
+        &'a self,
+        source: &'a str,
+        destination: &'a str,
+    ) -> BoxFuture<'a, Result<(), AppError>>;
+
+    /// One model with its `details` filled in.
+    fn show_model<'a>(&'a self, name: &'a str) -> BoxFuture<'a, Result<OllamaModel, AppError>>;
+
+    /// Models currently loaded in memory.
+    fn running_models(&self) -> BoxFuture<'_, Result<Vec<RunningModel>, AppError>>;
+
+    /// Load a model without generating anything, keeping it resident for
+    /// `keep_alive` (the server default if `None`).
+    fn load_model<'a>(
+        &'a self,
+        name: &'a str,
+        keep_alive: Option<&'a KeepAlive>,
+    ) -> BoxFuture<'a, Result<(), AppError>>;
+
+    /// Evict a model from memory now.
+    fn unload_model<'a>(&'a self, name: &'a str) -> BoxFuture<'a, Result<(), AppError>>;
+
+    /// Embed one batch of inputs, returning one vector per input in order.
+    fn embed<'a>(
+        &'a self,
+        model: &'a str,
+        inputs: &'a [String],
+    ) -> BoxFuture<'a, Result<Vec<Vec<f32>>, AppError>>;
+}
+
+/// How long a model stays loaded after a request: a duration such as `"10m"`
+/// or `"1h"`, or a number of seconds. Zero unloads the model straight away and
+/// a negative value keeps it loaded until the server exits.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(untagged)]
+pub enum KeepAlive {
+    Seconds(i64),
+    Duration(String),
+}
+
+/// A chat request in provider-neutral form. Each provider encodes it into
+/// its own wire format on every round of the tool loop.
+pub struct ChatRequest {
+    pub model: String,
+    pub messages: Vec<Message>,
+    pub options: Option<ChatOptions>,
+    pub think: Option<bool>,
+    /// Tool definitions, already in the `{"type": "function", ...}` shape
+    /// both APIs share.
+    pub tools: Vec<serde_json::Value>,
+    pub format: Option<OutputFormat>,
+    pub keep_alive: Option<KeepAlive>,
+}
+
+/// One conversation entry, with images already base64-encoded.
+#[derive(Debug, Clone, Default)]
+pub struct Message {
+    pub role: String,
+    pub content: String,
+    pub images: Vec<String>,
+    /// Calls requested by the assistant in this message.
+    pub tool_calls: Vec<ToolCall>,
+    /// For `tool` messages: the call this is the result of.
+    pub tool_call: Option<ToolCall>,
+}
+
+impl Message {
+    pub fn tool_result(call: ToolCall, content: String) -> Self {
+        Message {
+            role: "tool".to_string(),
+            content,
+            tool_call: Some(call),
+            ..Default::default()
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct ToolCall {
+    /// Only OpenAI-compatible servers assign ids to calls.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub id: Option<String>,
+    pub function: ToolCallFunction,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct ToolCallFunction {
+    pub name: String,
+    #[serde(default)]
+    pub arguments: serde_json::Value,
+}
+
+/// What the model produced in one chat round.
+#[derive(Debug, Default)]
+pub struct ChatTurn {
+    pub content: String,
+    pub tool_calls: Vec<ToolCall>,
+    pub metrics: Option<GenerationMetrics>,
+}
@@ -0,0 +1,472 @@
+//! Retrieval over attached folders. Text files are split into overlapping
+//! chunks, embedded with a local embedding model and stored on disk per
+//! folder; each question pulls back the closest chunks, which go to the model
+//! as context and to the frontend as citations.
+
+use crate::embeddings::{self, EmbedProgress};
+use crate::endpoint::Endpoint;
+use crate::error::AppError;
+use crate::http::HttpClient;
+use crate::ollama::{self, FileChange, FolderFile, FolderFileKind};
+use crate::provider::LlmProvider;
+use crate::storage;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::{HashMap, HashSet};
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use tauri::ipc::Channel;
+use tauri::{Manager, State};
+
+/// Target chunk size in bytes. Roughly 300 tokens, small enough that several
+/// chunks fit in a default context window.
+const CHUNK_SIZE: usize = 1200;
+
+/// Text repeated at the start of the next chunk so a passage cut at a chunk
+/// boundary is still found whole in one of them.
+const CHUNK_OVERLAP: usize = 200;
+
+pub const DEFAULT_TOP_K: usize = 5;
+
+const INDEX_DIR: &str = "rag";
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct Chunk {
+    pub path: String,
+    /// Path relative to the folder's parent, as shown in the files panel.
+    pub name: String,
+    /// 1-based, inclusive.
+    pub start_line: usize,
+    pub end_line: usize,
+    pub text: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct IndexedChunk {
+    #[serde(flatten)]
+    chunk: Chunk,
+    vector: Vec<f32>,
+}
+
+/// Everything stored for one folder. Vectors are unit length, so similarity
+/// is a dot product.
+#[derive(Debug, Serialize, Deserialize)]
+struct FolderIndex {
+    folder: String,
+    /// The embedding model the vectors came from; queries must use it too.
+    model: String,
+    chunks: Vec<IndexedChunk>,
+}
+
+#[derive(Debug, Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct Citation {
+    #[serde(flatten)]
+    pub chunk: Chunk,
+    pub score: f32,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct IndexSummary {
+    pub files: usize,
+    pub chunks: usize,
+}
+
+/// Which indexed folders to search for a chat request.
+#[derive(Debug, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct RetrievalSettings {
+    pub folders: Vec<String>,
+    pub top_k: Option<usize>,
+}
+
+// ── Chunking ────────────────────────────────────────────────────────
+
+/// Split `text` on line boundaries into chunks of about `CHUNK_SIZE` bytes,
+/// each overlapping the previous one by up to `CHUNK_OVERLAP`. Lines longer
+/// than a chunk are cut into pieces that keep their line number.
+pub fn chunk_text(text: &str) -> Vec<(usize, usize, String)> {
+    let mut lines: Vec<(usize, &str)> = Vec::new();
+    for (i, line) in text.lines().enumerate() {
+        let mut rest = line;
+        while rest.len() > CHUNK_SIZE {
+            let mut cut = CHUNK_SIZE;
+            while !rest.is_char_boundary(cut) {
+                cut -= 1;
+            }
+            lines.push((i + 1, &rest[..cut]));
+            rest = &rest[cut..];
+        }
+        lines.push((i + 1, rest));
+    }
+
+    let mut chunks = Vec::new();
+    let mut start = 0;
+    while start < lines.len() {
+        let mut end = start;
+        let mut size = 0;
+        while end < lines.len() && (end == start || size + lines[end].1.len() < CHUNK_SIZE) {
+            size += lines[end].1.len() + 1;
+            end += 1;
+        }
+
+        let text = lines[start..end]
+            .iter()
+            .map(|(_, line)| *line)
+            .collect::<Vec<_>>()
+            .join("\n");
+        if !text.trim().is_empty() {
+            chunks.push((lines[start].0, lines[end - 1].0, text));
+        }
+        if end == lines.len() {
+            break;
+        }
+
+        // Back up for the overlap, but always move forward
+        let mut next = end;
+        let mut overlap = 0;
+        while next > start + 1 && overlap + lines[next - 1].1.len() < CHUNK_OVERLAP {
+            next -= 1;
+            overlap += lines[next].1.len() + 1;
+        }
+        start = next;
+    }
+    chunks
+}
+
+fn chunk_file(file: &FolderFile) -> Vec<Chunk> {
+    chunk_text(&file.content)
+        .into_iter()
+        .map(|(start_line, end_line, text)| Chunk {
+            path: file.path.clone(),
+            name: file.name.clone(),
+            start_line,
+            end_line,
+            text,
+        })
+        .collect()
+}
+
+async fn embed_chunks(
+    provider: &dyn LlmProvider,
+    model: &str,
+    chunks: Vec<Chunk>,
+    progress: impl FnMut(usize, usize),
+) -> Result<Vec<IndexedChunk>, AppError> {
+    // The file name gives the embedding model context the chunk itself may lack
+    let inputs: Vec<String> = chunks
+        .iter()
+        .map(|c| format!("{}\n{}", c.name, c.text))
+        .collect();
+    let vectors = embeddings::embed_all(provider, model, &inputs, progress).await?;
+    Ok(chunks
+        .into_iter()
+        .zip(vectors)
+        .map(|(chunk, vector)| IndexedChunk { chunk, vector })
+        .collect())
+}
+
+// ── Storage ─────────────────────────────────────────────────────────
+
+/// One lock per folder, held across each load-modify-save of its index so
+/// a rebuild and a watcher update running together don't drop each other's
+/// chunks.
+pub struct IndexLocks(pub Mutex<HashMap<String, Arc<tokio::sync::Mutex<()>>>>);
+
+impl IndexLocks {
+    async fn lock(&self, folder: &str) -> Result<tokio::sync::OwnedMutexGuard<()>, AppError> {
+        let lock = self
+            .0
+            .lock()?
+            .entry(folder.to_string())
+            .or_default()
+            .clone();
+        Ok(lock.lock_owned().await)
+    }
+}
+
+fn index_path(app: &tauri::AppHandle, folder: &str) -> PathBuf {
+    let digest = Sha256::digest(folder.as_bytes());
+    let name: String = digest[..8].iter().map(|b| format!("{b:02x}")).collect();
+    storage::storage_dir(app)
+        .join(INDEX_DIR)
+        .join(format!("{name}.json"))
+}
+
+impl FolderIndex {
+    fn load(app: &tauri::AppHandle, folder: &str) -> Option<Self> {
+        let data = std::fs::read_to_string(index_path(app, folder)).ok()?;
+        serde_json::from_str(&data)
+            .map_err(|e| log::warn!("Ignoring unreadable index for {folder}: {e}"))
+            .ok()
+    }
+
+    fn save(&self, app: &tauri::AppHandle) -> Result<(), AppError> {
+        let path = index_path(app, &self.folder);
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir)
+                .map_err(|e| AppError::io("Failed to create index directory", dir, e))?;
+        }
+        // Written aside and renamed so retrieval never reads half a file
+        let temp = path.with_extension("json.tmp");
+        let data = serde_json::to_string(self)?;
+        std::fs::write(&temp, data).map_err(|e| AppError::io("Failed to save index", &temp, e))?;
+        std::fs::rename(&temp, &path).map_err(|e| AppError::io("Failed to save index", &path, e))
+    }
+}
+
+// ── Retrieval ───────────────────────────────────────────────────────
+
+/// The `top_k` chunks across `folders` most similar to `query`. Folders
+/// without an index are skipped.
+pub async fn retrieve(
+    app: &tauri::AppHandle,
+    provider: &dyn LlmProvider,
+    folders: &[String],
+    query: &str,
+    top_k: usize,
+) -> Result<Vec<Citation>, AppError> {
+    let mut query_vectors: HashMap<String, Vec<f32>> = HashMap::new();
+    let mut citations = Vec::new();
+
+    for folder in folders {
+        let Some(index) = FolderIndex::load(app, folder) else {
+            log::warn!("No index for {folder}, skipping retrieval");
+            continue;
+        };
+
+        if !query_vectors.contains_key(&index.model) {
+            let vector =
+                embeddings::embed_all(provider, &index.model, &[query.to_string()], |_, _| {})
+                    .await?
+                    .pop()
+                    .unwrap_or_default();
+            query_vectors.insert(index.model.clone(), vector);
+        }
+        let query_vector = &query_vectors[&index.model];
+
+        citations.extend(index.chunks.into_iter().map(|indexed| Citation {
+            score: dot(query_vector, &indexed.vector),
+            chunk: indexed.chunk,
+        }));
+    }
+
+    citations.sort_by(|a, b| b.score.total_cmp(&a.score));
+    citations.truncate(top_k);
+    Ok(citations)
+}
+
+fn dot(a: &[f32], b: &[f32]) -> f32 {
+    a.iter().zip(b).map(|(x, y)| x * y).sum()
+}
+
+/// System prompt carrying the retrieved chunks, numbered to match the
+/// citations sent to the frontend.
+pub fn context_prompt(citations: &[Citation]) -> String {
+    let mut prompt = String::from(
+        "Use the following excerpts from the user's files to answer when they are relevant. \
+         Refer to an excerpt by its number, like [1].",
+    );
+    for (i, citation) in citations.iter().enumerate() {
+        let chunk = &citation.chunk;
+        prompt.push_str(&format!(
+            "\n\n[{}] {} (lines {}-{})\n{}",
+            i + 1,
+            chunk.name,
+            chunk.start_line,
+            chunk.end_line,
+            chunk.text
+        ));
+    }
+    prompt
+}
+
+/// Re-embed only the files in `changes`, with the model the folder was
+/// indexed with. Returns `false` if the folder has no index.
+pub async fn update_index(
+    app: &tauri::AppHandle,
+    provider: &dyn LlmProvider,
+    folder: &str,
+    changes: &[FileChange],
+) -> Result<bool, AppError> {
+    let _guard = app.state::<IndexLocks>().lock(folder).await?;
+    let Some(mut index) = FolderIndex::load(app, folder) else {
+        return Ok(false);
+    };
+
+    let changed: HashSet<&str> = changes.iter().map(FileChange::path).collect();
+    index
+        .chunks
+        .retain(|indexed| !changed.contains(indexed.chunk.path.as_str()));
+
+    let chunks = changes
+        .iter()
+        .filter_map(|change| match change {
+            FileChange::Added(file) | FileChange::Modified(file) => Some(chunk_file(file)),
+            FileChange::Deleted { .. } | FileChange::Skipped { .. } => None,
+        })
+        .flatten()
+        .collect();
+    let chunks = embed_chunks(provider, &index.model, chunks, |_, _| {}).await?;
+    index.chunks.extend(chunks);
+
+    index.save(app)?;
+    Ok(true)
+}
+
+// ── Commands ────────────────────────────────────────────────────────
+
+/// (Re)build the index for `path` with embedding model `model`.
+#[tauri::command]
+pub async fn index_folder(
+    app_handle: tauri::AppHandle,
+    endpoint: State<'_, Endpoint>,
+    http: State<'_, HttpClient>,
+    locks: State<'_, IndexLocks>,
+    path: String,
+    model: String,
+    on_progress: Channel<EmbedProgress>,
+) -> Result<IndexSummary, AppError> {
+    let _guard = locks.lock(&path).await?;
+    let folder = path.clone();
+    let files = tokio::task::spawn_blocking(move || ollama::read_folder_files(folder, None))
+        .await
+        .map_err(|e| AppError::Internal(format!("Reading folder failed: {e}")))??;
+
+    let text_files: Vec<&FolderFile> = files
+        .iter()
+        .filter(|f| f.kind == FolderFileKind::Text)
+        .collect();
+    let chunks = text_files.iter().flat_map(|f| chunk_file(f)).collect();
+
+    let provider = endpoint.provider(&http);
+    let chunks = embed_chunks(provider.as_ref(), &model, chunks, |completed, total| {
+        let _ = on_progress.send(EmbedProgress { completed, total });
+    })
+    .await?;
+
+    let index = FolderIndex {
+        folder: path,
+        model,
+        chunks,
+    };
+    index.save(&app_handle)?;
+
+    Ok(IndexSummary {
+        files: text_files.len(),
+        chunks: index.chunks.len(),
+    })
+}
+
+#[tauri::command]
+pub async fn remove_folder_index(
+    app_handle: tauri::AppHandle,
+    locks: State<'_, IndexLocks>,
+    path: String,
+) -> Result<(), AppError> {
+    let _guard = locks.lock(&path).await?;
+    let index = index_path(&app_handle, &path);
+    match std::fs::remove_file(&index) {
+        Ok(()) => Ok(()),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(()),
+        Err(e) => Err(AppError::io("Failed to remove index", &index, e)),
+    }
+}
+
+#[tauri::command]
+pub async fn search_folders(
+    app_handle: tauri::AppHandle,
+    endpoint: State<'_, Endpoint>,
+    http: State<'_, HttpClient>,
+    folders: Vec<String>,
+    query: String,
+    top_k: Option<usize>,
+) -> Result<Vec<Citation>, AppError> {
+    let provider = endpoint.provider(&http);
+    retrieve(
+        &app_handle,
+        provider.as_ref(),
+        &folders,
+        &query,
+        top_k.unwrap_or(DEFAULT_TOP_K),
+    )
+    .await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn short_text_is_one_chunk() {
+        let chunks = chunk_text("first line\nsecond line\n");
+        assert_eq!(chunks, vec![(1, 2, "first line\nsecond line".to_string())]);
+        assert!(chunk_text("\n  \n").is_empty());
+    }
+
+    #[test]
+    fn long_text_is_split_with_overlap() {
+        let text: String = (1..=100)
+            .map(|i| format!("line {i:03} {}\n", "x".repeat(40)))
+            .collect();
+        let chunks = chunk_text(&text);
+
+        assert!(chunks.len() > 1);
+        assert_eq!(chunks[0].0, 1);
+        assert_eq!(chunks.last().unwrap().1, 100);
+        for pair in chunks.windows(2) {
+            // Each chunk starts inside the previous one, but further on
+            assert!(pair[1].0 <= pair[0].1);
+            assert!(pair[1].0 > pair[0].0);
+        }
+        assert!(chunks.iter().all(|(_, _, text)| text.len() <= CHUNK_SIZE));
+    }
+
+    #[test]
+    fn overlong_lines_are_cut_on_char_boundaries() {
+        let text = "é".repeat(CHUNK_SIZE);
+        let chunks = chunk_text(&text);
+        assert!(chunks.len() >= 2);
+        assert!(chunks
+            .iter()
+            .all(|(start, end, _)| (*start, *end) == (1, 1)));
+        assert_eq!(
+            chunks
+                .iter()
+                .map(|(_, _, t)| t.chars().count())
+                .sum::<usize>(),
+            CHUNK_SIZE
+        );
+    }
+
+    #[test]
+    fn index_locks_are_per_folder() {
+        let locks = IndexLocks(Mutex::new(HashMap::new()));
+        tauri::async_runtime::block_on(async {
+            let _docs = locks.lock("/docs").await.unwrap();
+            let _notes = locks.lock("/notes").await.unwrap();
+            let wait = std::time::Duration::from_millis(50);
+            assert!(tokio::time::timeout(wait, locks.lock("/docs"))
+                .await
+                .is_err());
+        });
+    }
+
+    #[test]
+    fn context_prompt_numbers_citations() {
+        let citation = Citation {
+            chunk: Chunk {
+                path: "/notes/todo.md".to_string(),
+                name: "notes/todo.md".to_string(),
+                start_line: 3,
+                end_line: 9,
+                text: "buy milk".to_string(),
+            },
+            score: 0.8,
+        };
+        let prompt = context_prompt(&[citation]);
+        assert!(prompt.ends_with("[1] notes/todo.md (lines 3-9)\nbuy milk"));
+    }
+}
@@ -1,3 +1,6 @@
+use crate::endpoint::Endpoint;
+use crate::error::AppError;
+use crate::provider::LlmProvider;
 use std::process::{Child, Command};
 use std::sync::Mutex;
 use tauri::Manager;
@@ -6,38 +9,46 @@ pub struct OllamaProcess {
     pub child: Mutex<Option<Child>>,
 }
 
-pub fn start_ollama(app: &tauri::App) -> Result<(), Box<dyn std::error::Error>> {
-    let child = spawn_ollama(app.handle())?;
-    app.manage(OllamaProcess {
-        child: Mutex::new(Some(child)),
-    });
+pub fn start_ollama(app_handle: &tauri::AppHandle) -> Result<(), AppError> {
+    let child = spawn_ollama(app_handle)?;
+    if let Some(state) = app_handle.try_state::<OllamaProcess>() {
+        *state.child.lock().unwrap() = Some(child);
+    }
     Ok(())
 }
 
-fn spawn_ollama(app_handle: &tauri::AppHandle) -> Result<Child, Box<dyn std::error::Error>> {
+fn spawn_ollama(app_handle: &tauri::AppHandle) -> Result<Child, AppError> {
     let resource_dir = app_handle
         .path()
         .resource_dir()
-        .map_err(|e| format!("Failed to get resource dir: {e}"))?;
+        .map_err(|e| AppError::Sidecar(format!("Failed to get resource dir: {e}")))?;
 
     let ollama_dir = resource_dir.join("resources").join("ollama");
     let ollama_bin = ollama_dir.join("ollama");
 
     if !ollama_bin.exists() {
-        return Err(format!("Ollama binary not found at {:?}", ollama_bin).into());
+        return Err(AppError::Sidecar(format!("Ollama binary not found at {:?}", ollama_bin)));
     }
 
     // Kill any stale Ollama processes from previous runs
     kill_stale_ollama(&ollama_bin);
 
-    let child = Command::new(&ollama_bin)
+    let mut command = Command::new(&ollama_bin);
+    // Listen where the endpoint points, not only on the default port
+    if let Some(host) = app_handle
+        .try_state::<Endpoint>()
+        .and_then(|endpoint| endpoint.config().sidecar_host())
+    {
+        command.env("OLLAMA_HOST", host);
+    }
+    let child = command
         .arg("serve")
         .env("DYLD_LIBRARY_PATH", &ollama_dir)
         .env("LD_LIBRARY_PATH", &ollama_dir)
         .stdout(std::process::Stdio::null())
         .stderr(std::process::Stdio::null())
         .spawn()
-        .map_err(|e| format!("Failed to spawn Ollama: {e}"))?;
+        .map_err(|e| AppError::Sidecar(format!("Failed to spawn Ollama: {e}")))?;
 
     log::info!("Ollama started (pid: {})", child.id());
     Ok(child)
@@ -75,12 +86,15 @@ pub fn stop_ollama(app: &tauri::AppHandle) {
 }
 
 #[tauri::command]
-pub async fn restart_ollama(app_handle: tauri::AppHandle) -> Result<(), String> {
+pub async fn restart_ollama(app_handle: tauri::AppHandle) -> Result<(), AppError> {
+    if !app_handle.state::<Endpoint>().uses_sidecar() {
+        return Err(AppError::RemoteEndpoint);
+    }
+
     stop_ollama(&app_handle);
     std::thread::sleep(std::time::Duration::from_millis(500));
 
-    let child = spawn_ollama(&app_handle)
-        .map_err(|e| format!("Failed to restart Ollama: {e}"))?;
+    let child = spawn_ollama(&app_handle)?;
 
     if let Some(state) = app_handle.try_state::<OllamaProcess>() {
         *state.child.lock().unwrap() = Some(child);
@@ -109,18 +123,18 @@ fn kill_stale_ollama(ollama_bin: &std::path::Path) {
     }
 }
 
-pub async fn wait_for_ready(max_attempts: u32, interval_ms: u64) -> bool {
+pub async fn wait_for_ready(
+    provider: &dyn LlmProvider,
+    max_attempts: u32,
+    interval_ms: u64,
+) -> bool {
     for attempt in 1..=max_attempts {
-        match reqwest::get("http://localhost:11434").await {
-            Ok(resp) if resp.status().is_success() => {
-                log::info!("Ollama ready after {} attempt(s)", attempt);
-                return true;
-            }
-            _ => {
-                log::debug!("Ollama not ready (attempt {}/{})", attempt, max_attempts);
-                tokio::time::sleep(std::time::Duration::from_millis(interval_ms)).await;
-            }
+        if provider.check().await {
+            log::info!("Ollama ready after {} attempt(s)", attempt);
+            return true;
         }
+        log::debug!("Ollama not ready (attempt {}/{})", attempt, max_attempts);
+        tokio::time::sleep(std::time::Duration::from_millis(interval_ms)).await;
     }
     log::warn!("Ollama did not become ready after {} attempts", max_attempts);
     false
@@ -0,0 +1,121 @@
+//! Incremental decoder for Server-Sent Events, as streamed by
+//! OpenAI-compatible `/v1/chat/completions` endpoints.
+//!
+//! Only `data:` fields are kept; `event:`, `id:`, `retry:` and comment lines
+//! are ignored. Like the NDJSON decoder, bytes are buffered until a full line
+//! arrives so split UTF-8 characters survive chunk boundaries.
+
+#[derive(Debug, Default)]
+pub struct SseDecoder {
+    buffer: Vec<u8>,
+    /// Start of the first line not yet consumed.
+    start: usize,
+    /// `data:` lines of the event being assembled.
+    data: Vec<String>,
+}
+
+impl SseDecoder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn push(&mut self, bytes: &[u8]) {
+        if self.start > 0 {
+            self.buffer.drain(..self.start);
+            self.start = 0;
+        }
+        self.buffer.extend_from_slice(bytes);
+    }
+
+    /// The data of the next complete event, with multi-line data joined by
+    /// `\n`. Returns `None` until a blank line ends an event.
+    pub fn next_event(&mut self) -> Option<String> {
+        while let Some(offset) = self.buffer[self.start..].iter().position(|&b| b == b'\n') {
+            let end = self.start + offset;
+            let line = String::from_utf8_lossy(&self.buffer[self.start..end])
+                .trim_end_matches('\r')
+                .to_string();
+            self.start = end + 1;
+
+            if let Some(event) = self.take_line(&line) {
+                return Some(event);
+            }
+        }
+        None
+    }
+
+    /// Flush an event left open when the stream ended without a blank line.
+    pub fn finish(&mut self) -> Option<String> {
+        let rest = String::from_utf8_lossy(&self.buffer[self.start..]).to_string();
+        self.buffer.clear();
+        self.start = 0;
+        self.take_line(rest.trim_end_matches('\r'));
+        self.take_line("")
+    }
+
+    fn take_line(&mut self, line: &str) -> Option<String> {
+        if line.is_empty() {
+            if self.data.is_empty() {
+                return None;
+            }
+            let event = self.data.join("\n");
+            self.data.clear();
+            return Some(event);
+        }
+        if let Some(value) = line.strip_prefix("data:") {
+            self.data
+                .push(value.strip_prefix(' ').unwrap_or(value).to_string());
+        }
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn decode_all(chunks: &[&[u8]]) -> Vec<String> {
+        let mut decoder = SseDecoder::new();
+        let mut events = Vec::new();
+        for chunk in chunks {
+            decoder.push(chunk);
+            while let Some(event) = decoder.next_event() {
+                events.push(event);
+            }
+        }
+        events.extend(decoder.finish());
+        events
+    }
+
+    #[test]
+    fn splits_events_on_blank_lines() {
+        let events = decode_all(&[b"data: {\"a\":1}\n\ndata: [DONE]\n\n"]);
+        assert_eq!(events, vec!["{\"a\":1}", "[DONE]"]);
+    }
+
+    #[test]
+    fn reassembles_events_split_across_chunks() {
+        let bytes = "data: {\"content\":\"héllo\"}\r\n\r\ndata: [DONE]\r\n\r\n".as_bytes();
+        for split in 1..bytes.len() {
+            let (a, b) = bytes.split_at(split);
+            assert_eq!(
+                decode_all(&[a, b]),
+                vec!["{\"content\":\"héllo\"}", "[DONE]"],
+                "split at {split}"
+            );
+        }
+    }
+
+    #[test]
+    fn joins_multi_line_data_and_ignores_other_fields() {
+        let events =
+            decode_all(&[b": keep-alive\nevent: message\nid: 7\ndata: first\ndata:second\n\n"]);
+        assert_eq!(events, vec!["first\nsecond"]);
+    }
+
+    #[test]
+    fn flushes_unterminated_event_at_end() {
+        assert_eq!(decode_all(&[b"data: tail"]), vec!["tail"]);
+        assert!(decode_all(&[b"\n\n: comment"]).is_empty());
+    }
+}
@@ -1,8 +1,9 @@
+use crate::error::AppError;
 use std::fs;
 use std::path::PathBuf;
 use tauri::Manager;
 
-fn storage_dir(app: &tauri::AppHandle) -> PathBuf {
+pub(crate) fn storage_dir(app: &tauri::AppHandle) -> PathBuf {
     let dir = app
         .path()
         .app_data_dir()
@@ -12,16 +13,17 @@ fn storage_dir(app: &tauri::AppHandle) -> PathBuf {
 }
 
 #[tauri::command]
-pub fn save_store(app: tauri::AppHandle, key: String, value: String) -> Result<(), String> {
+pub fn save_store(app: tauri::AppHandle, key: String, value: String) -> Result<(), AppError> {
     let path = storage_dir(&app).join(format!("{key}.json"));
-    fs::write(&path, &value).map_err(|e| format!("Failed to save {key}: {e}"))
+    fs::write(&path, &value).map_err(|e| AppError::io(format!("Failed to save {key}"), &path, e))
 }
 
 #[tauri::command]
-pub fn load_store(app: tauri::AppHandle, key: String) -> Result<Option<String>, String> {
+pub fn load_store(app: tauri::AppHandle, key: String) -> Result<Option<String>, AppError> {
     let path = storage_dir(&app).join(format!("{key}.json"));
     if path.exists() {
-        let data = fs::read_to_string(&path).map_err(|e| format!("Failed to load {key}: {e}"))?;
+        let data = fs::read_to_string(&path)
+            .map_err(|e| AppError::io(format!("Failed to load {key}"), &path, e))?;
         Ok(Some(data))
     } else {
         Ok(None)
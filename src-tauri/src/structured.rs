@@ -0,0 +1,144 @@
+//! Structured output: Ollama's `format` parameter and validation of the
+//! assembled reply against it.
+
+use serde::Serialize;
+use serde_json::Value;
+
+pub enum OutputFormat {
+    /// `"json"`: any well-formed JSON value.
+    Json,
+    /// A JSON Schema the reply must conform to.
+    Schema {
+        schema: Value,
+        validator: Box<jsonschema::Validator>,
+    },
+}
+
+#[derive(Debug, Serialize, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct SchemaViolation {
+    /// JSON Pointer to the offending value, empty for the root.
+    pub path: String,
+    pub message: String,
+}
+
+/// Why a reply did not match the requested format.
+#[derive(Debug, Serialize, Clone, PartialEq)]
+#[serde(tag = "kind", rename_all = "camelCase")]
+pub enum OutputError {
+    #[serde(rename_all = "camelCase")]
+    InvalidJson { message: String },
+    #[serde(rename_all = "camelCase")]
+    SchemaMismatch { violations: Vec<SchemaViolation> },
+}
+
+impl OutputFormat {
+    /// Accepts the string `"json"` or a JSON Schema object. The schema is
+    /// compiled up front so a broken one fails before Ollama is called.
+    pub fn parse(value: Value) -> Result<Self, String> {
+        match value {
+            Value::String(s) if s == "json" => Ok(OutputFormat::Json),
+            Value::Object(_) => {
+                let validator = jsonschema::validator_for(&value)
+                    .map_err(|e| format!("Invalid JSON Schema: {e}"))?;
+                Ok(OutputFormat::Schema {
+                    schema: value,
+                    validator: Box::new(validator),
+                })
+            }
+            _ => Err("Invalid format (expected \"json\" or a JSON Schema object)".to_string()),
+        }
+    }
+
+    /// The value sent to Ollama as `format`.
+    pub fn to_request(&self) -> Value {
+        match self {
+            OutputFormat::Json => Value::String("json".to_string()),
+            OutputFormat::Schema { schema, .. } => schema.clone(),
+        }
+    }
+
+    /// Parse the complete reply and check it against the format.
+    pub fn check(&self, output: &str) -> Result<Value, OutputError> {
+        let value: Value =
+            serde_json::from_str(output.trim()).map_err(|e| OutputError::InvalidJson {
+                message: e.to_string(),
+            })?;
+
+        if let OutputFormat::Schema { validator, .. } = self {
+            let violations: Vec<SchemaViolation> = validator
+                .iter_errors(&value)
+                .map(|e| SchemaViolation {
+                    path: e.instance_path.to_string(),
+                    message: e.to_string(),
+                })
+                .collect();
+            if !violations.is_empty() {
+                return Err(OutputError::SchemaMismatch { violations });
+            }
+        }
+
+        Ok(value)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn person_schema() -> OutputFormat {
+        OutputFormat::parse(json!({
+            "type": "object",
+            "properties": {
+                "name": { "type": "string" },
+                "age": { "type": "integer", "minimum": 0 },
+            },
+            "required": ["name", "age"],
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn accepts_json_keyword_and_schema_objects() {
+        assert!(matches!(
+            OutputFormat::parse(json!("json")),
+            Ok(OutputFormat::Json)
+        ));
+        assert_eq!(
+            person_schema().to_request()["required"],
+            json!(["name", "age"])
+        );
+        assert!(OutputFormat::parse(json!("yaml")).is_err());
+        assert!(OutputFormat::parse(json!({ "type": 12 })).is_err());
+    }
+
+    #[test]
+    fn conforming_output_is_returned_parsed() {
+        let value = person_schema()
+            .check("  {\"name\": \"Ada\", \"age\": 36}\n")
+            .unwrap();
+        assert_eq!(value, json!({ "name": "Ada", "age": 36 }));
+    }
+
+    #[test]
+    fn reports_each_schema_violation_with_its_path() {
+        let err = person_schema()
+            .check("{\"name\": 7, \"age\": -1}")
+            .unwrap_err();
+        let OutputError::SchemaMismatch { violations } = err else {
+            panic!("expected a schema mismatch, got {err:?}");
+        };
+        let mut paths: Vec<_> = violations.iter().map(|v| v.path.as_str()).collect();
+        paths.sort();
+        assert_eq!(paths, vec!["/age", "/name"]);
+    }
+
+    #[test]
+    fn reports_invalid_json() {
+        assert!(matches!(
+            OutputFormat::Json.check("{\"name\": "),
+            Err(OutputError::InvalidJson { .. })
+        ));
+    }
+}
@@ -0,0 +1,309 @@
+//! Tools the model can call during a chat, advertised to Ollama via `tools`.
+
+use serde::Deserialize;
+use serde_json::{json, Value};
+use std::path::{Path, PathBuf};
+
+/// Largest file `read_file` will return to the model.
+const MAX_TOOL_FILE_BYTES: u64 = 1024 * 1024;
+
+/// Tool output is cut to this many characters so one call can't fill the context.
+const MAX_TOOL_OUTPUT_CHARS: usize = 32_000;
+
+pub trait Tool: Send + Sync {
+    fn name(&self) -> &'static str;
+    fn description(&self) -> &'static str;
+    /// JSON Schema for the arguments object.
+    fn parameters(&self) -> Value;
+    fn call(&self, args: &Value) -> Result<String, String>;
+}
+
+/// Which tools the frontend enables for a chat request.
+#[derive(Debug, Deserialize, Clone, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct ToolSettings {
+    /// Attached folders the file tools may read from.
+    #[serde(default)]
+    pub folders: Vec<String>,
+}
+
+pub struct ToolRegistry {
+    tools: Vec<Box<dyn Tool>>,
+}
+
+impl ToolRegistry {
+    pub fn new() -> Self {
+        ToolRegistry { tools: Vec::new() }
+    }
+
+    pub fn register(&mut self, tool: impl Tool + 'static) {
+        self.tools.push(Box::new(tool));
+    }
+
+    /// The built-in tools, with file access limited to `settings.folders`.
+    pub fn builtin(settings: &ToolSettings) -> Self {
+        let mut registry = ToolRegistry::new();
+        registry.register(CurrentDateTime);
+        if !settings.folders.is_empty() {
+            let roots = FolderRoots::new(&settings.folders);
+            registry.register(ReadFile(roots.clone()));
+            registry.register(ListDirectory(roots));
+        }
+        registry
+    }
+
+    /// Tool definitions in the shape Ollama expects for `tools`.
+    pub fn definitions(&self) -> Vec<Value> {
+        self.tools
+            .iter()
+            .map(|t| {
+                json!({
+                    "type": "function",
+                    "function": {
+                        "name": t.name(),
+                        "description": t.description(),
+                        "parameters": t.parameters(),
+                    },
+                })
+            })
+            .collect()
+    }
+
+    pub fn call(&self, name: &str, args: &Value) -> Result<String, String> {
+        let tool = self
+            .tools
+            .iter()
+            .find(|t| t.name() == name)
+            .ok_or_else(|| format!("Unknown tool: {name}"))?;
+        tool.call(args).map(truncate)
+    }
+}
+
+fn truncate(mut output: String) -> String {
+    if let Some((idx, _)) = output.char_indices().nth(MAX_TOOL_OUTPUT_CHARS) {
+        output.truncate(idx);
+        output.push_str("\n[output truncated]");
+    }
+    output
+}
+
+fn string_arg<'a>(args: &'a Value, key: &str) -> Result<&'a str, String> {
+    args.get(key)
+        .and_then(Value::as_str)
+        .ok_or_else(|| format!("Missing argument: {key}"))
+}
+
+// ── Folder access ───────────────────────────────────────────────────
+
+#[derive(Clone)]
+struct FolderRoots(Vec<PathBuf>);
+
+impl FolderRoots {
+    fn new(folders: &[String]) -> Self {
+        FolderRoots(
+            folders
+                .iter()
+                .filter_map(|f| std::fs::canonicalize(f).ok())
+                .collect(),
+        )
+    }
+
+    /// Resolve `path` (absolute, or relative to an attached folder) and
+    /// refuse anything that escapes the attached folders.
+    fn resolve(&self, path: &str) -> Result<PathBuf, String> {
+        let requested = Path::new(path);
+        let candidates: Vec<PathBuf> = if requested.is_absolute() {
+            vec![requested.to_path_buf()]
+        } else {
+            self.0.iter().map(|root| root.join(requested)).collect()
+        };
+
+        for candidate in candidates {
+            if let Ok(resolved) = std::fs::canonicalize(&candidate) {
+                if self.0.iter().any(|root| resolved.starts_with(root)) {
+                    return Ok(resolved);
+                }
+            }
+        }
+        Err(format!("Path is not inside an attached folder: {path}"))
+    }
+}
+
+struct ReadFile(FolderRoots);
+
+impl Tool for ReadFile {
+    fn name(&self) -> &'static str {
+        "read_file"
+    }
+
+    fn description(&self) -> &'static str {
+        "Read a text file from one of the user's attached folders."
+    }
+
+    fn parameters(&self) -> Value {
+        json!({
+            "type": "object",
+            "properties": {
+                "path": {
+                    "type": "string",
+                    "description": "File path, absolute or relative to an attached folder",
+                },
+            },
+            "required": ["path"],
+        })
+    }
+
+    fn call(&self, args: &Value) -> Result<String, String> {
+        let path = self.0.resolve(string_arg(args, "path")?)?;
+        let metadata = std::fs::metadata(&path).map_err(|e| format!("Cannot read file: {e}"))?;
+        if !metadata.is_file() {
+            return Err("Not a file".to_string());
+        }
+        if metadata.len() > MAX_TOOL_FILE_BYTES {
+            return Err("File too large (max 1 MB)".to_string());
+        }
+        std::fs::read_to_string(&path).map_err(|e| format!("Cannot read file: {e}"))
+    }
+}
+
+struct ListDirectory(FolderRoots);
+
+impl Tool for ListDirectory {
+    fn name(&self) -> &'static str {
+        "list_directory"
+    }
+
+    fn description(&self) -> &'static str {
+        "List the files and subdirectories of a directory in the user's attached folders. \
+         Directories are shown with a trailing slash."
+    }
+
+    fn parameters(&self) -> Value {
+        json!({
+            "type": "object",
+            "properties": {
+                "path": {
+                    "type": "string",
+                    "description": "Directory path, absolute or relative to an attached folder. \
+                                    Use \".\" for the folder itself.",
+                },
+            },
+            "required": ["path"],
+        })
+    }
+
+    fn call(&self, args: &Value) -> Result<String, String> {
+        let path = self.0.resolve(string_arg(args, "path")?)?;
+        let entries =
+            std::fs::read_dir(&path).map_err(|e| format!("Cannot list directory: {e}"))?;
+
+        let mut names: Vec<String> = entries
+            .flatten()
+            .filter_map(|entry| {
+                let name = entry.file_name().to_string_lossy().to_string();
+                if name.starts_with('.') {
+                    return None;
+                }
+                let is_dir = entry.file_type().map(|t| t.is_dir()).unwrap_or(false);
+                Some(if is_dir { format!("{name}/") } else { name })
+            })
+            .collect();
+        names.sort();
+
+        if names.is_empty() {
+            Ok("(empty directory)".to_string())
+        } else {
+            Ok(names.join("\n"))
+        }
+    }
+}
+
+// ── Date and time ───────────────────────────────────────────────────
+
+struct CurrentDateTime;
+
+impl Tool for CurrentDateTime {
+    fn name(&self) -> &'static str {
+        "current_datetime"
+    }
+
+    fn description(&self) -> &'static str {
+        "Get the current local date, time and timezone offset."
+    }
+
+    fn parameters(&self) -> Value {
+        json!({ "type": "object", "properties": {} })
+    }
+
+    fn call(&self, _args: &Value) -> Result<String, String> {
+        Ok(chrono::Local::now()
+            .format("%A, %Y-%m-%d %H:%M:%S %:z")
+            .to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_folder(name: &str) -> PathBuf {
+        let dir =
+            std::env::temp_dir().join(format!("innertalk-tools-{name}-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(dir.join("sub")).unwrap();
+        std::fs::write(dir.join("notes.md"), "hello").unwrap();
+        dir
+    }
+
+    fn registry_for(dir: &Path) -> ToolRegistry {
+        ToolRegistry::builtin(&ToolSettings {
+            folders: vec![dir.to_string_lossy().to_string()],
+        })
+    }
+
+    #[test]
+    fn reads_and_lists_inside_attached_folder() {
+        let dir = temp_folder("inside");
+        let registry = registry_for(&dir);
+
+        let content = registry
+            .call("read_file", &json!({ "path": "notes.md" }))
+            .unwrap();
+        assert_eq!(content, "hello");
+
+        let listing = registry
+            .call("list_directory", &json!({ "path": "." }))
+            .unwrap();
+        assert_eq!(listing, "notes.md\nsub/");
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn refuses_paths_outside_attached_folder() {
+        let dir = temp_folder("outside");
+        let registry = registry_for(&dir.join("sub"));
+
+        assert!(registry
+            .call("read_file", &json!({ "path": "../notes.md" }))
+            .is_err());
+        let absolute = dir.join("notes.md").to_string_lossy().to_string();
+        assert!(registry
+            .call("read_file", &json!({ "path": absolute }))
+            .is_err());
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn file_tools_need_an_attached_folder() {
+        let registry = ToolRegistry::builtin(&ToolSettings::default());
+        let names: Vec<_> = registry
+            .definitions()
+            .iter()
+            .map(|d| d["function"]["name"].as_str().unwrap().to_string())
+            .collect();
+        assert_eq!(names, vec!["current_datetime"]);
+        assert!(registry.call("read_file", &json!({ "path": "x" })).is_err());
+    }
+}
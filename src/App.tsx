@@ -6,7 +6,7 @@ import { ChatPanel } from './components/chat/ChatPanel'
 import { SettingsModal } from './components/settings/SettingsModal'
 import { useAppStore } from './stores/appStore'
 import { useChatStore } from './stores/chatStore'
-import { listModels, getSystemRam, waitForOllama } from './lib/ollama'
+import { listModels, getSystemRam, waitForOllama, listDownloads, onDownloadUpdated } from './lib/ollama'
 
 function App() {
   const [showSettings, setShowSettings] = useState(false)
@@ -35,9 +35,15 @@ function App() {
             const models = await listModels()
             const names = models.map(m => m.name)
             setAvailableModels(names)
+            const chatModels = models.filter(m => !m.embedding).map(m => m.name)
+            const embeddingModels = models.filter(m => m.embedding).map(m => m.name)
+            const { embeddingModel, setEmbeddingModel } = useAppStore.getState()
+            if (!embeddingModels.includes(embeddingModel)) {
+              setEmbeddingModel(embeddingModels[0] ?? '')
+            }
             const current = useAppStore.getState().selectedModel
-            if (!current || !names.includes(current)) {
-              setSelectedModel(names.length > 0 ? names[0] : '')
+            if (!current || !chatModels.includes(current)) {
+              setSelectedModel(chatModels.length > 0 ? chatModels[0] : '')
             }
           } catch (e) {
             console.error('Failed to fetch models:', e)
@@ -53,6 +59,26 @@ function App() {
     init()
   }, [])
 
+  // Mirror the download queue; it runs in the backend and outlives the settings modal
+  useEffect(() => {
+    const { setDownloads, upsertDownload, setPullError } = useAppStore.getState()
+    listDownloads().then(setDownloads).catch(() => {})
+    const unlisten = onDownloadUpdated(async (download) => {
+      upsertDownload(download)
+      if (download.state === 'failed' && download.error) {
+        setPullError(`${download.model}: ${download.error.message}`)
+      } else if (download.state === 'done') {
+        try {
+          const models = await listModels()
+          setAvailableModels(models.map(m => m.name))
+        } catch { /* ignore */ }
+      }
+    })
+    return () => {
+      unlisten.then(fn => fn())
+    }
+  }, [])
+
   // Auto-fix selectedModel when models change (e.g. after deletion)
   useEffect(() => {
     if (selectedModel && availableModels.length > 0 && !availableModels.includes(selectedModel)) {
@@ -4,8 +4,9 @@ import Markdown from 'react-markdown'
 import { open } from '@tauri-apps/plugin-dialog'
 import { useChatStore } from '../../stores/chatStore'
 import { useAppStore } from '../../stores/appStore'
-import { chat as ollamaChat, cancelChat, readFileContent } from '../../lib/ollama'
-import type { Message, Attachment } from '../../stores/chatStore'
+import { chat as ollamaChat, cancelChat, readFileContent, errorMessage, errorHint } from '../../lib/ollama'
+import type { ChatMessage as OllamaMessage } from '../../lib/ollama'
+import type { Message, Attachment, ToolCallRecord } from '../../stores/chatStore'
 
 function CodeBlock({ language, children }: { language?: string; children: ReactNode }) {
   const [copied, setCopied] = useState(false)
@@ -64,6 +65,8 @@ const markdownComponents = {
   },
 }
 
+const IMAGE_EXTENSIONS = ['png', 'jpg', 'jpeg', 'gif', 'webp']
+
 function ChatMessage({ message, isStreaming }: { message: Message; isStreaming?: boolean }) {
   const isUser = message.role === 'user'
 
@@ -107,6 +110,23 @@ function ChatMessage({ message, isStreaming }: { message: Message; isStreaming?:
             ))}
           </div>
         )}
+        {message.thinking && (
+          <details className="text-sm text-slate-400" style={{ marginBottom: 8 }}>
+            <summary className="cursor-pointer select-none text-xs text-slate-500">Reasoning</summary>
+            <div className="whitespace-pre-wrap" style={{ marginTop: 6, paddingLeft: 10, borderLeft: '2px solid rgba(255,255,255,0.08)' }}>
+              {message.thinking}
+            </div>
+          </details>
+        )}
+        {message.toolCalls && message.toolCalls.length > 0 && (
+          <div className="flex flex-col text-xs text-slate-500" style={{ gap: 4, marginBottom: 8 }}>
+            {message.toolCalls.map((call, i) => (
+              <span key={i} className={call.isError ? 'text-red-400' : undefined}>
+                {call.result === undefined ? 'Running' : call.isError ? 'Failed' : 'Used'} {call.name}
+              </span>
+            ))}
+          </div>
+        )}
         {message.content ? (
           <div className="chat-markdown text-[15px] text-slate-200">
             <Markdown components={markdownComponents}>{message.content}</Markdown>
@@ -117,13 +137,37 @@ function ChatMessage({ message, isStreaming }: { message: Message; isStreaming?:
             <span className="text-sm text-slate-400">Thinking...</span>
           </div>
         ) : null}
+        {message.citations && message.citations.length > 0 && (
+          <details className="text-xs text-slate-500" style={{ marginTop: 8 }}>
+            <summary className="cursor-pointer select-none">Sources ({message.citations.length})</summary>
+            <div className="flex flex-col" style={{ gap: 4, marginTop: 6 }}>
+              {message.citations.map((citation, i) => (
+                <span key={i} title={citation.text}>
+                  [{i + 1}] {citation.name}:{citation.startLine}-{citation.endLine}
+                </span>
+              ))}
+            </div>
+          </details>
+        )}
+        {message.warnings?.map((warning, i) => (
+          <p key={i} className="text-[11px] text-amber-400/80" style={{ marginTop: 6 }}>
+            {warning}
+          </p>
+        ))}
+        {(message.metrics || message.partial) && (
+          <p className="text-[11px] text-slate-500" style={{ marginTop: 6 }}>
+            {message.partial && 'Stopped early'}
+            {message.metrics?.tokensPerSecond != null &&
+              `${message.metrics.tokensPerSecond.toFixed(1)} tok/s · ${message.metrics.evalCount} tokens · ${message.metrics.promptEvalCount} prompt tokens`}
+          </p>
+        )}
       </div>
     </div>
   )
 }
 
 export function ChatPanel() {
-  const { chats, activeChatId, createChat, setActiveChat, addMessage, updateLastMessage } = useChatStore()
+  const { chats, activeChatId, createChat, setActiveChat, addMessage, updateLastMessage, updateLastMessageThinking, updateLastMessageToolCalls, updateLastMessageCitations, addLastMessageWarning, finishLastMessage } = useChatStore()
   const { selectedModel, setSidebarTab, ollamaStatus } = useAppStore()
   const messagesEndRef = useRef<HTMLDivElement>(null)
   const scrollContainerRef = useRef<HTMLDivElement>(null)
@@ -143,6 +187,7 @@ export function ChatPanel() {
         filters: [
           { name: 'Text files', extensions: ['txt', 'md', 'json', 'csv', 'xml', 'yaml', 'yml', 'toml', 'ini', 'cfg', 'log'] },
           { name: 'Code', extensions: ['js', 'ts', 'tsx', 'jsx', 'py', 'rs', 'go', 'java', 'c', 'cpp', 'h', 'css', 'html', 'sql', 'sh'] },
+          { name: 'Images', extensions: IMAGE_EXTENSIONS },
           { name: 'All files', extensions: ['*'] },
         ],
       })
@@ -151,6 +196,12 @@ export function ChatPanel() {
       for (const filePath of paths) {
         const name = filePath.split('/').pop() || filePath
         if (attachments.some(a => a.path === filePath)) continue
+        const ext = name.split('.').pop()?.toLowerCase() ?? ''
+        if (IMAGE_EXTENSIONS.includes(ext)) {
+          // Images are read, validated and downscaled by the backend when sent
+          setAttachments(prev => [...prev, { name, path: filePath, content: '', kind: 'image' }])
+          continue
+        }
         try {
           const content = await readFileContent(filePath)
           setAttachments(prev => [...prev, { name, path: filePath, content }])
@@ -232,12 +283,15 @@ export function ChatPanel() {
 
     // Build messages array for Ollama (before adding empty assistant msg)
     const currentChat = useChatStore.getState().chats.find(c => c.id === chatId)
-    const ollamaMessages: { role: string; content: string }[] = []
+    const ollamaMessages: OllamaMessage[] = []
 
-    // Add context folder files as a system-level context (auto-synced by FilesPanel)
+    // Indexed folders are searched per question; the rest go in whole as
+    // system-level context (auto-synced by FilesPanel)
     const folders = useAppStore.getState().contextFolders
-    if (folders.length > 0) {
-      const folderContext = folders
+    const indexedFolders = folders.filter(f => f.indexed).map(f => f.path)
+    const unindexedFolders = folders.filter(f => !f.indexed)
+    if (unindexedFolders.length > 0) {
+      const folderContext = unindexedFolders
         .flatMap(f => f.files.map(file => `--- ${file.name} ---\n${file.content}`))
         .join('\n\n')
       ollamaMessages.push({
@@ -249,11 +303,17 @@ export function ChatPanel() {
     // Add chat messages
     for (const m of currentChat?.messages ?? []) {
       let content = m.content
-      if (m.attachments?.length) {
-        const fileContext = m.attachments.map(a => `--- File: ${a.name} ---\n${a.content}`).join('\n\n')
+      const textAttachments = m.attachments?.filter(a => a.kind !== 'image') ?? []
+      const imageAttachments = m.attachments?.filter(a => a.kind === 'image') ?? []
+      if (textAttachments.length) {
+        const fileContext = textAttachments.map(a => `--- File: ${a.name} ---\n${a.content}`).join('\n\n')
         content = `${fileContext}\n\n${content}`
       }
-      ollamaMessages.push({ role: m.role, content })
+      ollamaMessages.push({
+        role: m.role,
+        content,
+        ...(imageAttachments.length ? { images: imageAttachments.map(a => ({ path: a.path })) } : {}),
+      })
     }
 
     // Add empty assistant message as placeholder for streaming
@@ -262,25 +322,65 @@ export function ChatPanel() {
     setStreamingChatId(chatId)
 
     let accumulated = ''
+    let accumulatedThinking = ''
+    let toolCalls: ToolCallRecord[] = []
 
     try {
       await ollamaChat(
+        chatId,
         selectedModel,
         ollamaMessages,
-        (token) => {
-          accumulated += token
-          updateLastMessage(chatId!, accumulated)
-        },
-        () => {
-          setIsStreaming(false); setStreamingChatId(null)
+        {
+          options: currentChat?.options,
+          think: currentChat?.think,
+          tools: currentChat?.toolsEnabled ? { folders: folders.map(f => f.path) } : undefined,
+          format: currentChat?.format,
+          retrieval: indexedFolders.length > 0 ? { folders: indexedFolders } : undefined,
+          keepAlive: currentChat?.keepAlive,
         },
-        (error) => {
-          updateLastMessage(chatId!, `Error: ${error}`)
-          setIsStreaming(false); setStreamingChatId(null)
+        {
+          onToken: (token) => {
+            accumulated += token
+            updateLastMessage(chatId!, accumulated)
+          },
+          onThinking: (thinking) => {
+            accumulatedThinking += thinking
+            updateLastMessageThinking(chatId!, accumulatedThinking)
+          },
+          onToolCall: (name, args) => {
+            toolCalls = [...toolCalls, { name, arguments: args }]
+            updateLastMessageToolCalls(chatId!, toolCalls)
+          },
+          onToolResult: (name, result, isError) => {
+            const idx = toolCalls.findIndex(c => c.name === name && c.result === undefined)
+            toolCalls = toolCalls.map((c, i) => (i === idx ? { ...c, result, isError } : c))
+            updateLastMessageToolCalls(chatId!, toolCalls)
+          },
+          onCitations: (citations) => {
+            updateLastMessageCitations(chatId!, citations)
+          },
+          onWarning: (warning) => {
+            addLastMessageWarning(chatId!, warning)
+          },
+          onInvalidOutput: (error) => {
+            const detail = error.kind === 'invalidJson'
+              ? error.message
+              : error.violations.map(v => `${v.path || '/'}: ${v.message}`).join('\n')
+            updateLastMessage(chatId!, `${accumulated}\n\n**Reply does not match the requested format**\n\n${detail}`)
+          },
+          onDone: (partial, metrics) => {
+            finishLastMessage(chatId!, partial, metrics)
+            setIsStreaming(false); setStreamingChatId(null)
+          },
+          onError: (error) => {
+            const hint = errorHint(error)
+            updateLastMessage(chatId!, `Error: ${error.message}${hint ? `\n\n${hint}` : ''}`)
+            setIsStreaming(false); setStreamingChatId(null)
+          },
         },
       )
     } catch (e) {
-      const errorMsg = e instanceof Error ? e.message : 'Failed to connect to Ollama'
+      const errorMsg = errorMessage(e, 'Failed to connect to Ollama')
       updateLastMessage(chatId!, `Error: ${errorMsg}`)
       setIsStreaming(false)
       setStreamingChatId(null)
@@ -289,7 +389,7 @@ export function ChatPanel() {
 
   const handleCancel = async () => {
     try {
-      await cancelChat()
+      if (streamingChatId) await cancelChat(streamingChatId)
     } catch (e) {
       console.error('Cancel error:', e)
     }
@@ -3,16 +3,45 @@ import { useState, useEffect, useRef } from 'react'
 import { open } from '@tauri-apps/plugin-dialog'
 import { listen } from '@tauri-apps/api/event'
 import { useAppStore } from '../../stores/appStore'
-import { readFolderFiles, watchFolder, unwatchFolder } from '../../lib/ollama'
+import { readFolderFiles, watchFolder, unwatchFolder, indexFolder, removeFolderIndex } from '../../lib/ollama'
+import type { FolderUpdate } from '../../lib/ollama'
+import type { ContextFolder } from '../../stores/appStore'
 
 interface FilesPanelProps {
   embedded?: boolean
 }
 
+const skippedCount = (folder: ContextFolder) => Object.keys(folder.skipped ?? {}).length
+
+/** Tooltip naming each skipped file and why. */
+const skippedTitle = (folder: ContextFolder) =>
+  Object.entries(folder.skipped ?? {})
+    .map(([path, reason]) => `${path.split(/[\\/]/).pop()}: ${reason === 'tooLarge' ? 'over 1 MB' : 'not readable as text'}`)
+    .join('\n')
+
 export function FilesPanel({ embedded }: FilesPanelProps) {
-  const { contextFolders, addContextFolder, removeContextFolder, updateContextFolder } = useAppStore()
+  const { contextFolders, addContextFolder, removeContextFolder, updateContextFolder, setContextFolderIndexed } = useAppStore()
   const [loading, setLoading] = useState(false)
   const [refreshingId, setRefreshingId] = useState<string | null>(null)
+  const [indexing, setIndexing] = useState<{ id: string; completed: number; total: number } | null>(null)
+
+  // Embed the folder for retrieval when an embedding model is available;
+  // without one, chat falls back to sending every file
+  const reindex = async (folder: { id: string; path: string }) => {
+    const model = useAppStore.getState().embeddingModel
+    if (!model) return
+    setIndexing({ id: folder.id, completed: 0, total: 0 })
+    try {
+      await indexFolder(folder.path, model, (completed, total) =>
+        setIndexing({ id: folder.id, completed, total }))
+      setContextFolderIndexed(folder.id, true)
+    } catch (e) {
+      console.error('Folder index error:', e)
+      setContextFolderIndexed(folder.id, false)
+    } finally {
+      setIndexing(null)
+    }
+  }
 
   const handleAddFolder = async () => {
     try {
@@ -28,12 +57,15 @@ export function FilesPanel({ embedded }: FilesPanelProps) {
       const files = await readFolderFiles(folderPath)
       const folderName = folderPath.split('/').pop() || folderPath
 
-      addContextFolder({
+      const folder = {
         id: Math.random().toString(36).substring(2),
         name: folderName,
         path: folderPath,
         files,
-      })
+      }
+      addContextFolder(folder)
+      setLoading(false)
+      await reindex(folder)
     } catch (e) {
       console.error('Folder dialog error:', e)
     } finally {
@@ -46,6 +78,7 @@ export function FilesPanel({ embedded }: FilesPanelProps) {
     try {
       const files = await readFolderFiles(folder.path)
       updateContextFolder(folder.id, files)
+      await reindex(folder)
     } catch (e) {
       console.error('Folder refresh error:', e)
     } finally {
@@ -68,15 +101,32 @@ export function FilesPanel({ embedded }: FilesPanelProps) {
       }
     }
 
-    // Listen for change events from the native watcher
-    const unlisten = listen<string>('folder-changed', async (event) => {
-      const changedPath = event.payload
+    // The native watcher reports per-file changes and has already updated
+    // the retrieval index for them
+    const unlisten = listen<FolderUpdate>('folder-index-updated', (event) => {
+      const { folder: changedPath, changes, indexed, error } = event.payload
       const folder = foldersRef.current.find(f => f.path === changedPath)
-      if (folder) {
-        try {
-          const files = await readFolderFiles(folder.path)
-          useAppStore.getState().updateContextFolder(folder.id, files)
-        } catch { /* ignore */ }
+      if (!folder) return
+
+      const changed = new Set(changes.map(c => c.path))
+      const files = folder.files.filter(f => !changed.has(f.path))
+      const skipped = { ...folder.skipped }
+      for (const change of changes) {
+        delete skipped[change.path]
+        if (change.change === 'skipped') {
+          skipped[change.path] = change.reason
+        } else if (change.change !== 'deleted') {
+          files.push({ name: change.name, path: change.path, content: change.content })
+        }
+      }
+      const store = useAppStore.getState()
+      store.updateContextFolder(folder.id, files, skipped)
+      if (error) {
+        console.error('Folder index error:', error.message)
+        store.setContextFolderIndexed(folder.id, false)
+      } else if (folder.indexed && !indexed) {
+        // The index file is gone; fall back to sending whole files
+        store.setContextFolderIndexed(folder.id, false)
       }
     })
 
@@ -125,6 +175,7 @@ export function FilesPanel({ embedded }: FilesPanelProps) {
           <div className="flex flex-col" style={{ gap: 6 }}>
             {contextFolders.map(folder => {
               const isRefreshing = refreshingId === folder.id
+              const progress = indexing?.id === folder.id ? indexing : null
               return (
                 <div
                   key={folder.id}
@@ -141,6 +192,14 @@ export function FilesPanel({ embedded }: FilesPanelProps) {
                     <p className="text-sm font-medium text-slate-200 truncate">{folder.name}</p>
                     <p className="text-xs text-slate-500 truncate">
                       {folder.files.length} file{folder.files.length !== 1 ? 's' : ''}
+                      {skippedCount(folder) > 0 && (
+                        <span className="text-amber-400/80" title={skippedTitle(folder)}>
+                          {` · ${skippedCount(folder)} skipped`}
+                        </span>
+                      )}
+                      {progress
+                        ? ` · Indexing${progress.total > 0 ? ` ${progress.completed}/${progress.total}` : '...'}`
+                        : folder.indexed && ' · Indexed'}
                     </p>
                   </div>
                   <div className="flex items-center opacity-0 group-hover:opacity-100 transition-all" style={{ gap: 2 }}>
@@ -156,6 +215,7 @@ export function FilesPanel({ embedded }: FilesPanelProps) {
                     <button
                       onClick={() => {
                         unwatchFolder(folder.path).catch(() => {})
+                        removeFolderIndex(folder.path).catch(() => {})
                         removeContextFolder(folder.id)
                       }}
                       className="flex items-center justify-center rounded-md hover:bg-white/[0.06] text-slate-500 hover:text-red-400 transition-colors"
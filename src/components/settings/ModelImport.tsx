@@ -0,0 +1,109 @@
+import { FileUp, Loader2, X } from 'lucide-react'
+import { useState } from 'react'
+import { errorMessage, importBundle, importGguf } from '../../lib/ollama'
+import type { CreateProgress } from '../../lib/ollama'
+
+interface ModelImportProps {
+  path: string
+  onImported: (names: string[]) => void
+  onClose: () => void
+}
+
+/** Bundles carry their own model names; GGUF files need one. */
+const isBundle = (path: string) => /\.tar$/i.test(path)
+
+/** Ollama model names are lowercase with no spaces; start from the file name. */
+const suggestName = (path: string) =>
+  (path.split(/[\\/]/).pop() ?? 'model')
+    .replace(/\.gguf$/i, '')
+    .toLowerCase()
+    .replace(/[^a-z0-9._-]+/g, '-')
+    .replace(/^-+|-+$/g, '')
+
+/** Import a picked `.gguf` file under a chosen name, or install a model bundle. */
+export function ModelImport({ path, onImported, onClose }: ModelImportProps) {
+  const bundle = isBundle(path)
+  const [name, setName] = useState(() => suggestName(path))
+  const [progress, setProgress] = useState<CreateProgress | null>(null)
+  const [error, setError] = useState<string | null>(null)
+
+  const busy = progress !== null
+  const percent = progress?.total ? Math.round(((progress.completed ?? 0) / progress.total) * 100) : null
+
+  const handleImport = async () => {
+    setError(null)
+    setProgress({ status: 'Starting...' })
+    try {
+      if (bundle) {
+        const names = await importBundle(path, p => setProgress({ status: 'Verifying and installing', ...p }))
+        onImported(names)
+      } else {
+        await importGguf(path, name.trim(), setProgress)
+        onImported([name.trim()])
+      }
+    } catch (e) {
+      setError(errorMessage(e, 'Import failed.'))
+    } finally {
+      setProgress(null)
+    }
+  }
+
+  return (
+    <div
+      className="flex flex-col rounded-xl ring-1 ring-indigo-500/20"
+      style={{ padding: '16px 18px', gap: 10, marginBottom: 16, background: 'rgba(99,102,241,0.05)' }}
+    >
+      <div className="flex items-center" style={{ gap: 8 }}>
+        <FileUp size={14} className="text-indigo-400" />
+        <p className="text-sm font-medium text-white truncate flex-1" title={path}>
+          Import {path.split(/[\\/]/).pop()}
+        </p>
+        <button onClick={onClose} disabled={busy} className="text-slate-500 hover:text-white transition-colors">
+          <X size={14} />
+        </button>
+      </div>
+
+      <div className="flex items-center" style={{ gap: 8 }}>
+        {bundle ? (
+          <p className="flex-1 text-xs text-slate-500">
+            A model bundle. Every file is checked before the model appears.
+          </p>
+        ) : (
+          <input
+            value={name}
+            onChange={e => setName(e.target.value)}
+            onKeyDown={e => { if (e.key === 'Enter' && name.trim() && !busy) handleImport() }}
+            placeholder="Model name"
+            disabled={busy}
+            className="flex-1 rounded-lg bg-white/[0.04] ring-1 ring-white/[0.08] text-sm text-white placeholder-slate-500 outline-none focus:ring-indigo-500/50"
+            style={{ padding: '8px 10px' }}
+          />
+        )}
+        <button
+          onClick={handleImport}
+          disabled={busy || (!bundle && !name.trim())}
+          className="flex items-center rounded-lg bg-indigo-600 text-white text-xs font-semibold hover:bg-indigo-500 transition-colors disabled:opacity-30 disabled:cursor-not-allowed shrink-0"
+          style={{ padding: '8px 14px', gap: 6 }}
+        >
+          {busy && <Loader2 size={12} className="animate-spin" />}
+          Import
+        </button>
+      </div>
+
+      {progress && (
+        <div className="flex flex-col" style={{ gap: 4 }}>
+          {percent !== null && (
+            <div className="rounded-full overflow-hidden" style={{ height: 6, background: 'rgba(255,255,255,0.08)' }}>
+              <div className="h-full rounded-full bg-indigo-500 transition-all" style={{ width: `${percent}%` }} />
+            </div>
+          )}
+          <span className="text-[10px] text-slate-500 truncate">
+            {progress.status}{percent !== null && ` · ${percent}%`}
+          </span>
+        </div>
+      )}
+
+      {error && <p className="text-xs text-red-300">{error}</p>}
+    </div>
+  )
+}
@@ -0,0 +1,177 @@
+import { FileUp, Loader2, Sparkles, X } from 'lucide-react'
+import { useState } from 'react'
+import { open } from '@tauri-apps/plugin-dialog'
+import { copyModel, createModel, errorMessage } from '../../lib/ollama'
+
+interface PersonaFormProps {
+  models: string[]
+  initialBase?: string
+  onCreated: (name: string) => void
+  onClose: () => void
+}
+
+const inputClass =
+  'w-full rounded-lg bg-white/[0.04] ring-1 ring-white/[0.08] text-sm text-white placeholder-slate-500 outline-none focus:ring-indigo-500/50'
+
+/** Build a model from an installed one with its own system prompt and defaults. */
+export function PersonaForm({ models, initialBase, onCreated, onClose }: PersonaFormProps) {
+  const [name, setName] = useState('')
+  const [base, setBase] = useState(initialBase ?? models[0] ?? '')
+  const [system, setSystem] = useState('')
+  const [temperature, setTemperature] = useState('')
+  const [contextLength, setContextLength] = useState('')
+  const [template, setTemplate] = useState('')
+  const [adapter, setAdapter] = useState<string | null>(null)
+  const [status, setStatus] = useState<string | null>(null)
+  const [error, setError] = useState<string | null>(null)
+
+  const busy = status !== null
+
+  const pickAdapter = async () => {
+    const selected = await open({
+      multiple: false,
+      filters: [{ name: 'LoRA adapter', extensions: ['gguf', 'safetensors'] }],
+    })
+    if (typeof selected === 'string') setAdapter(selected)
+  }
+
+  const handleSubmit = async () => {
+    const parameters: Record<string, number> = {}
+    if (temperature.trim()) parameters.temperature = Number(temperature)
+    if (contextLength.trim()) parameters.num_ctx = Number(contextLength)
+    if (Object.values(parameters).some(v => Number.isNaN(v))) {
+      setError('Temperature and context length must be numbers.')
+      return
+    }
+
+    setError(null)
+    setStatus('Starting...')
+    try {
+      const unchanged = !system.trim() && !template.trim() && !adapter && Object.keys(parameters).length === 0
+      // Nothing to change: a copy shares every blob and is instant
+      if (unchanged) {
+        await copyModel(base, name.trim())
+      } else {
+        await createModel(
+          { name: name.trim(), from: base, system, parameters, template, adapter },
+          progress => setStatus(progress.total
+            ? `${progress.status} (${Math.round(((progress.completed ?? 0) / progress.total) * 100)}%)`
+            : progress.status),
+        )
+      }
+      onCreated(name.trim())
+    } catch (e) {
+      setError(errorMessage(e, 'Could not create the model.'))
+    } finally {
+      setStatus(null)
+    }
+  }
+
+  return (
+    <div
+      className="flex flex-col rounded-xl ring-1 ring-indigo-500/20"
+      style={{ padding: '16px 18px', gap: 10, marginBottom: 16, background: 'rgba(99,102,241,0.05)' }}
+    >
+      <div className="flex items-center" style={{ gap: 8 }}>
+        <Sparkles size={14} className="text-indigo-400" />
+        <p className="text-sm font-medium text-white flex-1">New persona</p>
+        <button onClick={onClose} disabled={busy} className="text-slate-500 hover:text-white transition-colors">
+          <X size={14} />
+        </button>
+      </div>
+
+      <div className="flex" style={{ gap: 8 }}>
+        <input
+          value={name}
+          onChange={e => setName(e.target.value)}
+          placeholder="Name, e.g. code-reviewer"
+          className={inputClass}
+          style={{ padding: '8px 10px' }}
+        />
+        <select
+          value={base}
+          onChange={e => setBase(e.target.value)}
+          className={inputClass}
+          style={{ padding: '8px 10px' }}
+        >
+          {models.map(m => <option key={m} value={m}>{m}</option>)}
+        </select>
+      </div>
+
+      <textarea
+        value={system}
+        onChange={e => setSystem(e.target.value)}
+        placeholder="System prompt"
+        rows={3}
+        className={inputClass}
+        style={{ padding: '8px 10px', resize: 'vertical' }}
+      />
+
+      <div className="flex" style={{ gap: 8 }}>
+        <input
+          value={temperature}
+          onChange={e => setTemperature(e.target.value)}
+          placeholder="Temperature"
+          inputMode="decimal"
+          className={inputClass}
+          style={{ padding: '8px 10px' }}
+        />
+        <input
+          value={contextLength}
+          onChange={e => setContextLength(e.target.value)}
+          placeholder="Context length"
+          inputMode="numeric"
+          className={inputClass}
+          style={{ padding: '8px 10px' }}
+        />
+      </div>
+
+      <details>
+        <summary className="cursor-pointer select-none text-xs text-slate-500">Template and adapter</summary>
+        <div className="flex flex-col" style={{ gap: 8, marginTop: 8 }}>
+          <textarea
+            value={template}
+            onChange={e => setTemplate(e.target.value)}
+            placeholder="Prompt template (Go template syntax); leave empty to keep the base model's"
+            rows={3}
+            className={`${inputClass} font-mono text-xs`}
+            style={{ padding: '8px 10px', resize: 'vertical' }}
+          />
+          <div className="flex items-center" style={{ gap: 8 }}>
+            <button
+              onClick={pickAdapter}
+              className="flex items-center rounded-lg bg-white/[0.04] text-slate-400 hover:bg-white/[0.08] hover:text-white text-xs transition-colors"
+              style={{ padding: '6px 12px', gap: 6 }}
+            >
+              <FileUp size={12} />
+              {adapter ? 'Change LoRA adapter' : 'Add LoRA adapter'}
+            </button>
+            {adapter && (
+              <>
+                <span className="text-xs text-slate-500 truncate flex-1" title={adapter}>{adapter.split(/[\\/]/).pop()}</span>
+                <button onClick={() => setAdapter(null)} className="text-slate-500 hover:text-white transition-colors">
+                  <X size={12} />
+                </button>
+              </>
+            )}
+          </div>
+        </div>
+      </details>
+
+      {error && <p className="text-xs text-red-300">{error}</p>}
+
+      <div className="flex items-center" style={{ gap: 10 }}>
+        <button
+          onClick={handleSubmit}
+          disabled={busy || !name.trim() || !base}
+          className="flex items-center rounded-lg bg-indigo-600 text-white text-xs font-semibold hover:bg-indigo-500 transition-colors disabled:opacity-30 disabled:cursor-not-allowed"
+          style={{ padding: '8px 14px', gap: 6 }}
+        >
+          {busy && <Loader2 size={12} className="animate-spin" />}
+          Create
+        </button>
+        {status && <span className="text-xs text-slate-500 truncate">{status}</span>}
+      </div>
+    </div>
+  )
+}
@@ -1,37 +1,20 @@
-import { X, Trash2, HardDrive, Download, Search, Cpu, Zap, MemoryStick, Loader2, TriangleAlert, RefreshCw, AlertCircle } from 'lucide-react'
+import { X, Trash2, HardDrive, Download, Search, Pause, Play, Sparkles, FileUp, PackageOpen, Cpu, Zap, MemoryStick, Loader2, TriangleAlert, RefreshCw, AlertCircle } from 'lucide-react'
 import { useState, useEffect } from 'react'
+import { open, save } from '@tauri-apps/plugin-dialog'
 import { useAppStore } from '../../stores/appStore'
-import { pullModel, deleteModel, listModels, getStorageInfo, restartOllama, checkOllama } from '../../lib/ollama'
+import { queuePull, cancelPull, pausePull, resumePull, deleteModel, listModels, getStorageInfo, cleanOrphanedBlobs, restartOllama, checkOllama, errorMessage, listRunningModels, loadModel, unloadModel, showModel, recommendModels, estimateModelFit, exportModel } from '../../lib/ollama'
+import { ModelImport } from './ModelImport'
+import { PersonaForm } from './PersonaForm'
+import type { Download as ModelDownload, FitEstimate, OllamaModel, Recommendation, RunningModel, StorageInfo } from '../../lib/ollama'
 
 interface SettingsModalProps {
   onClose: () => void
 }
 
-interface ModelInfo {
-  name: string
-  params: string
-  size: string
-  description: string
-  category: 'small' | 'medium' | 'large'
-  maxRam: number // minimum RAM in GB to run comfortably
-}
+type Category = 'small' | 'medium' | 'large'
 
-const MODEL_CATALOG: ModelInfo[] = [
-  // Small (1-4B)
-  { name: 'gemma2:2b', params: '2B', size: '1.6 GB', description: 'Google Gemma 2', category: 'small', maxRam: 8 },
-  { name: 'llama3.2:3b', params: '3B', size: '2.0 GB', description: 'Meta Llama 3.2', category: 'small', maxRam: 8 },
-  { name: 'phi3:mini', params: '3.8B', size: '2.3 GB', description: 'Microsoft Phi-3 Mini', category: 'small', maxRam: 8 },
-  // Medium (7-10B)
-  { name: 'mistral', params: '7B', size: '4.1 GB', description: 'Mistral 7B', category: 'medium', maxRam: 16 },
-  { name: 'llama3.1:8b', params: '8B', size: '4.7 GB', description: 'Meta Llama 3.1', category: 'medium', maxRam: 16 },
-  { name: 'gemma2:9b', params: '9B', size: '5.4 GB', description: 'Google Gemma 2', category: 'medium', maxRam: 16 },
-  { name: 'qwen2.5:7b', params: '7B', size: '4.4 GB', description: 'Alibaba Qwen 2.5', category: 'medium', maxRam: 16 },
-  { name: 'deepseek-r1:8b', params: '8B', size: '4.9 GB', description: 'DeepSeek R1', category: 'medium', maxRam: 16 },
-  // Large (14B+)
-  { name: 'qwen2.5:14b', params: '14B', size: '9.0 GB', description: 'Alibaba Qwen 2.5', category: 'large', maxRam: 32 },
-  { name: 'deepseek-r1:14b', params: '14B', size: '9.0 GB', description: 'DeepSeek R1', category: 'large', maxRam: 32 },
-  { name: 'llama3.1:70b', params: '70B', size: '40 GB', description: 'Meta Llama 3.1', category: 'large', maxRam: 64 },
-]
+const categoryOf = (model: Recommendation): Category =>
+  model.parametersBillion < 5 ? 'small' : model.parametersBillion <= 10 ? 'medium' : 'large'
 
 const CATEGORY_LABELS = {
   small: 'Small (1-4B)',
@@ -42,16 +25,25 @@ const CATEGORY_LABELS = {
 export function SettingsModal({ onClose }: SettingsModalProps) {
   const {
     availableModels, setAvailableModels, systemRam, ollamaStatus,
-    pullingModel, pullPercent, pullError,
-    setPullingModel, setPullPercent, setPullError,
+    downloads, pullError, setPullError,
   } = useAppStore()
   const [modelSearch, setModelSearch] = useState('')
   const [deletingModel, setDeletingModel] = useState<string | null>(null)
   const [confirmDelete, setConfirmDelete] = useState<string | null>(null)
   const [showAllModels, setShowAllModels] = useState(false)
-  const [storageSize, setStorageSize] = useState(0)
+  const [storage, setStorage] = useState<StorageInfo | null>(null)
+  const [cleaning, setCleaning] = useState(false)
+  const [personaBase, setPersonaBase] = useState<string | null>(null)
+  const [importPath, setImportPath] = useState<string | null>(null)
+  const [exporting, setExporting] = useState<{ model: string; percent: number } | null>(null)
   const [restarting, setRestarting] = useState(false)
   const [confirmClear, setConfirmClear] = useState(false)
+  const [runningModels, setRunningModels] = useState<RunningModel[]>([])
+  const [togglingModel, setTogglingModel] = useState<string | null>(null)
+  const [catalog, setCatalog] = useState<Recommendation[]>([])
+  const [expandedModel, setExpandedModel] = useState<string | null>(null)
+  const [modelDetails, setModelDetails] = useState<Record<string, OllamaModel>>({})
+  const [modelFits, setModelFits] = useState<Record<string, FitEstimate>>({})
 
   const handleRestart = async () => {
     setRestarting(true)
@@ -77,13 +69,81 @@ export function SettingsModal({ onClose }: SettingsModalProps) {
 
   const refreshStorage = async () => {
     try {
-      const info = await getStorageInfo()
-      setStorageSize(info.sizeBytes)
+      setStorage(await getStorageInfo())
     } catch { /* ignore */ }
   }
 
+  const handleCleanup = async () => {
+    setCleaning(true)
+    setPullError(null)
+    try {
+      await cleanOrphanedBlobs()
+      await refreshStorage()
+    } catch (e) {
+      setPullError(errorMessage(e, 'Cleanup failed.'))
+    } finally {
+      setCleaning(false)
+    }
+  }
+
   useEffect(() => { refreshStorage() }, [availableModels])
 
+  // Free memory changes as models load and unload, so re-rank alongside them
+  useEffect(() => {
+    recommendModels().then(setCatalog).catch(() => {})
+  }, [availableModels, runningModels])
+
+  const refreshRunning = async () => {
+    try {
+      setRunningModels(await listRunningModels())
+    } catch { /* not supported by every server */ }
+  }
+
+  // Models unload on their own when keep_alive expires, so keep polling
+  useEffect(() => {
+    if (ollamaStatus !== 'connected') return
+    refreshRunning()
+    const timer = setInterval(refreshRunning, 10_000)
+    return () => clearInterval(timer)
+  }, [ollamaStatus])
+
+  const handleToggleLoaded = async (model: string, loaded: boolean) => {
+    setTogglingModel(model)
+    try {
+      if (loaded) await unloadModel(model)
+      else await loadModel(model)
+    } catch (e) {
+      console.error('Load/unload failed:', e)
+    } finally {
+      await refreshRunning()
+      setTogglingModel(null)
+    }
+  }
+
+  const handleToggleDetails = async (model: string) => {
+    if (expandedModel === model) {
+      setExpandedModel(null)
+      return
+    }
+    setExpandedModel(model)
+    if (modelDetails[model]) return
+    try {
+      const info = await showModel(model)
+      setModelDetails(prev => ({ ...prev, [model]: info }))
+      const fit = await estimateModelFit(model)
+      setModelFits(prev => ({ ...prev, [model]: fit }))
+    } catch (e) {
+      console.error('Failed to load model details:', e)
+    }
+  }
+
+  const formatExpiry = (expiresAt: string | null) => {
+    if (!expiresAt) return ''
+    const minutes = Math.round((new Date(expiresAt).getTime() - Date.now()) / 60_000)
+    if (minutes > 60 * 24 * 365) return ' · stays loaded'
+    return minutes <= 1 ? ' · unloads soon' : ` · unloads in ${minutes} min`
+  }
+
   const ramGB = systemRam || 16 // fallback to 16 if not detected yet
 
   const formatSize = (bytes: number) => {
@@ -92,7 +152,15 @@ export function SettingsModal({ onClose }: SettingsModalProps) {
     return `${(bytes / (1024 * 1024 * 1024)).toFixed(1)} GB`
   }
 
-  const filteredModels = MODEL_CATALOG
+  const formatEta = (seconds: number) => {
+    if (seconds < 60) return `${seconds}s`
+    if (seconds < 3600) return `${Math.ceil(seconds / 60)} min`
+    return `${Math.floor(seconds / 3600)}h ${Math.round((seconds % 3600) / 60)}m`
+  }
+
+  const largestFit = catalog.find(m => m.fit.verdict === 'fits' && !m.capabilities.embedding)
+
+  const filteredModels = catalog
     .filter(m => !availableModels.some(a => a.startsWith(m.name.split(':')[0])))
     .filter(m =>
       m.name.toLowerCase().includes(modelSearch.toLowerCase()) ||
@@ -102,43 +170,115 @@ export function SettingsModal({ onClose }: SettingsModalProps) {
   const groupedModels = (['small', 'medium', 'large'] as const).map(cat => ({
     category: cat,
     label: CATEGORY_LABELS[cat],
-    models: filteredModels.filter(m => m.category === cat),
+    models: filteredModels.filter(m => categoryOf(m) === cat),
   })).filter(g => g.models.length > 0)
 
   const handlePull = async (modelName: string) => {
-    if (pullingModel) return
-    setPullingModel(modelName)
-    setPullPercent(0)
     setPullError(null)
+    try {
+      await queuePull(modelName)
+    } catch (e) {
+      setPullError(errorMessage(e, 'Could not start the download.'))
+    }
+  }
+
+  const downloadFor = (modelName: string) => downloads.find(d => d.model === modelName)
+
+  const percentOf = (download: ModelDownload) =>
+    download.completed && download.total ? Math.round((download.completed / download.total) * 100) : 0
+
+  const downloadLabel = (download: ModelDownload) => {
+    switch (download.state) {
+      case 'queued': return 'Queued'
+      case 'paused': return 'Paused'
+      case 'failed': return 'Failed'
+    }
+    switch (download.phase) {
+      case 'pullingManifest': return 'Fetching manifest'
+      case 'verifying': return 'Verifying'
+      case 'writingManifest': return 'Writing manifest'
+      case 'cleanup': return 'Cleaning up'
+    }
+    if (!download.bytesPerSecond) return 'Starting...'
+    const eta = download.etaSeconds != null ? ` · ${formatEta(download.etaSeconds)} left` : ''
+    return `${formatSize(download.bytesPerSecond)}/s${eta}`
+  }
 
+  const renderDownload = (download: ModelDownload) => (
+    <div className="flex items-center shrink-0" style={{ gap: 10, minWidth: 140 }}>
+      {download.state === 'downloading' && <Loader2 size={16} className="text-indigo-400 animate-spin shrink-0" />}
+      <div className="flex-1 flex flex-col" style={{ gap: 4 }}>
+        <div className="rounded-full overflow-hidden" style={{ width: 100, height: 6, background: 'rgba(255,255,255,0.08)' }}>
+          <div
+            className={`h-full rounded-full transition-all ${download.state === 'downloading' ? 'bg-indigo-500' : 'bg-slate-500'}`}
+            style={{ width: `${percentOf(download)}%` }}
+          />
+        </div>
+        <span className="text-[10px] text-slate-500">
+          {downloadLabel(download)}
+        </span>
+      </div>
+      <span className="text-sm text-indigo-400 font-semibold tabular-nums shrink-0">{percentOf(download)}%</span>
+      {download.state === 'paused' || download.state === 'failed' ? (
+        <button
+          onClick={() => resumePull(download.id).catch(e => setPullError(errorMessage(e, 'Could not resume the download.')))}
+          className="flex items-center justify-center rounded-md hover:bg-white/[0.06] text-slate-400 hover:text-white transition-colors"
+          style={{ width: 28, height: 28 }}
+          title={download.state === 'failed' ? 'Retry' : 'Resume'}
+        >
+          <Play size={13} />
+        </button>
+      ) : (
+        <button
+          onClick={() => pausePull(download.id).catch(() => {})}
+          className="flex items-center justify-center rounded-md hover:bg-white/[0.06] text-slate-400 hover:text-white transition-colors"
+          style={{ width: 28, height: 28 }}
+          title="Pause"
+        >
+          <Pause size={13} />
+        </button>
+      )}
+      <button
+        onClick={() => cancelPull(download.id).catch(() => {})}
+        className="flex items-center justify-center rounded-md hover:bg-white/[0.06] text-slate-400 hover:text-red-400 transition-colors"
+        style={{ width: 28, height: 28 }}
+        title="Cancel download"
+      >
+        <X size={13} />
+      </button>
+    </div>
+  )
+
+  const refreshModels = async () => {
     try {
-      await pullModel(
-        modelName,
-        (_status, completed, total) => {
-          if (completed && total && total > 0) {
-            setPullPercent(Math.round((completed / total) * 100))
-          }
-        },
-        async () => {
-          setPullingModel(null)
-          setPullPercent(0)
-          try {
-            const models = await listModels()
-            setAvailableModels(models.map(m => m.name))
-          } catch { /* ignore */ }
-          refreshStorage()
-        },
-        (error) => {
-          setPullingModel(null)
-          setPullPercent(0)
-          setPullError(error)
-        },
-      )
+      const models = await listModels()
+      setAvailableModels(models.map(m => m.name))
+    } catch { /* ignore */ }
+  }
+
+  const pickImport = async () => {
+    const selected = await open({
+      multiple: false,
+      filters: [{ name: 'GGUF model or model bundle', extensions: ['gguf', 'tar'] }],
+    })
+    if (typeof selected === 'string') setImportPath(selected)
+  }
+
+  const handleExport = async (model: string) => {
+    const path = await save({
+      defaultPath: `${model.replace(/[:/]/g, '-')}.tar`,
+      filters: [{ name: 'Model bundle', extensions: ['tar'] }],
+    })
+    if (!path) return
+    setPullError(null)
+    setExporting({ model, percent: 0 })
+    try {
+      await exportModel(model, path, p =>
+        setExporting({ model, percent: p.total ? Math.round((p.completed / p.total) * 100) : 0 }))
     } catch (e) {
-      const msg = e instanceof Error ? e.message : 'Download failed. Check your internet connection.'
-      setPullingModel(null)
-      setPullPercent(0)
-      setPullError(msg)
+      setPullError(errorMessage(e, 'Export failed.'))
+    } finally {
+      setExporting(null)
     }
   }
 
@@ -190,17 +330,22 @@ export function SettingsModal({ onClose }: SettingsModalProps) {
               <span className="text-xs text-slate-400">RAM</span>
               <span className="text-xs font-semibold text-white">{ramGB} GB</span>
               <span className="text-[10px] text-slate-500">&middot;</span>
-              <span className="text-xs text-slate-500">Up to ~{ramGB <= 8 ? '4B' : ramGB <= 16 ? '10B' : ramGB <= 32 ? '20B' : '70B'} recommended</span>
+              {largestFit && (
+                <span className="text-xs text-slate-500">Up to ~{largestFit.parameterSize} fits now</span>
+              )}
               <span className="text-[10px] text-slate-500">&middot;</span>
               <span className={`text-xs font-medium ${ollamaStatus === 'connected' ? 'text-emerald-400' : 'text-red-400'}`}>
                 {restarting ? 'Restarting...' : ollamaStatus === 'connected' ? 'Ollama running' : 'Ollama offline'}
               </span>
             </div>
-            {storageSize > 0 && (
-              <div className="flex items-center" style={{ gap: 10 }}>
+            {storage && storage.sizeBytes > 0 && (
+              <div className="flex items-center" style={{ gap: 10 }} title={storage.path}>
                 <HardDrive size={15} className="text-slate-500" />
                 <span className="text-xs text-slate-400">Storage used</span>
-                <span className="text-xs font-semibold text-white">{formatSize(storageSize)}</span>
+                <span className="text-xs font-semibold text-white">{formatSize(storage.sizeBytes)}</span>
+                {storage.availableBytes != null && (
+                  <span className="text-xs text-slate-500">&middot; {formatSize(storage.availableBytes)} free</span>
+                )}
               </div>
             )}
           </div>
@@ -224,6 +369,20 @@ export function SettingsModal({ onClose }: SettingsModalProps) {
               <Trash2 size={12} />
               Clear App Data
             </button>
+            {storage && storage.orphanedBytes > 0 && (
+              <button
+                onClick={handleCleanup}
+                disabled={cleaning || downloads.length > 0}
+                className="flex items-center rounded-lg bg-white/[0.04] text-slate-400 hover:bg-white/[0.08] hover:text-white transition-colors disabled:opacity-50"
+                style={{ gap: 6, padding: '7px 14px', fontSize: 12 }}
+                title={downloads.length > 0
+                  ? 'Finish or cancel downloads first'
+                  : `${storage.orphanedBlobs} file${storage.orphanedBlobs === 1 ? '' : 's'} no model uses`}
+              >
+                {cleaning ? <Loader2 size={12} className="animate-spin" /> : <Trash2 size={12} />}
+                Free {formatSize(storage.orphanedBytes)} of unused data
+              </button>
+            )}
           </div>
 
           {/* Clear data confirmation */}
@@ -290,8 +449,56 @@ export function SettingsModal({ onClose }: SettingsModalProps) {
             >
               {availableModels.length}
             </span>
+            <div className="flex items-center" style={{ gap: 4, marginLeft: 'auto' }}>
+              {availableModels.length > 0 && personaBase === null && (
+                <button
+                  onClick={() => setPersonaBase(availableModels[0])}
+                  className="flex items-center rounded-lg text-xs text-slate-400 hover:text-white hover:bg-white/[0.06] transition-colors"
+                  style={{ padding: '4px 10px', gap: 5 }}
+                >
+                  <Sparkles size={12} />
+                  New persona
+                </button>
+              )}
+              {ollamaStatus === 'connected' && importPath === null && (
+                <button
+                  onClick={pickImport}
+                  className="flex items-center rounded-lg text-xs text-slate-400 hover:text-white hover:bg-white/[0.06] transition-colors"
+                  style={{ padding: '4px 10px', gap: 5 }}
+                  title="Use a .gguf file or a model bundle already on this computer"
+                >
+                  <FileUp size={12} />
+                  Import
+                </button>
+              )}
+            </div>
           </div>
 
+          {importPath !== null && (
+            <ModelImport
+              key={importPath}
+              path={importPath}
+              onClose={() => setImportPath(null)}
+              onImported={() => {
+                setImportPath(null)
+                refreshModels()
+              }}
+            />
+          )}
+
+          {personaBase !== null && (
+            <PersonaForm
+              key={personaBase}
+              models={availableModels}
+              initialBase={personaBase}
+              onClose={() => setPersonaBase(null)}
+              onCreated={() => {
+                setPersonaBase(null)
+                refreshModels()
+              }}
+            />
+          )}
+
           <div className="flex flex-col" style={{ gap: 10, marginBottom: 32 }}>
             {availableModels.length === 0 ? (
               <div
@@ -304,11 +511,15 @@ export function SettingsModal({ onClose }: SettingsModalProps) {
               </div>
             ) : (
               availableModels.map(model => {
-                const info = MODEL_CATALOG.find(m => m.name === model || model.startsWith(m.name.split(':')[0]))
+                const info = catalog.find(m => m.name === model || model.startsWith(m.name.split(':')[0]))
                 const isDeleting = deletingModel === model
+                const running = runningModels.find(r => r.name === model)
+                const isToggling = togglingModel === model
+                const detail = expandedModel === model ? modelDetails[model] : undefined
+                const usage = storage?.models.find(m => m.name === model)
                 return (
+                  <div key={model} className="flex flex-col" style={{ gap: 6 }}>
                   <div
-                    key={model}
                     className={`flex items-center justify-between rounded-xl ring-1 ${isDeleting ? 'ring-red-500/20' : 'ring-white/[0.06]'}`}
                     style={{ padding: '16px 18px', background: isDeleting ? 'rgba(239,68,68,0.06)' : 'rgba(255,255,255,0.02)' }}
                   >
@@ -324,17 +535,38 @@ export function SettingsModal({ onClose }: SettingsModalProps) {
                         )}
                       </div>
                       <div>
-                        <p className={`text-sm font-medium ${isDeleting ? 'text-red-300' : 'text-white'}`}>{model}</p>
+                        <button
+                          onClick={() => handleToggleDetails(model)}
+                          className={`text-sm font-medium text-left hover:underline ${isDeleting ? 'text-red-300' : 'text-white'}`}
+                          title="Show details"
+                        >
+                          {model}
+                        </button>
                         <p className="text-xs text-slate-500" style={{ marginTop: 3 }}>
                           {isDeleting ? 'Removing model...' : (
                             <>
-                              {info && <>{info.params} params &middot; </>}
-                              {info?.size ?? 'Local model'}
+                              {info && <>{info.parameterSize} params &middot; </>}
+                              {usage ? formatSize(usage.sizeBytes) : info ? formatSize(info.sizeBytes) : 'Local model'}
+                              {usage && usage.uniqueBytes < usage.sizeBytes && (
+                                <> &middot; {formatSize(usage.uniqueBytes)} not shared</>
+                              )}
+                              {running && <> &middot; {formatSize(running.size)} in memory{formatExpiry(running.expiresAt)}</>}
                             </>
                           )}
                         </p>
                       </div>
                     </div>
+                    {!isDeleting && (
+                      <button
+                        onClick={() => handleToggleLoaded(model, !!running)}
+                        disabled={isToggling}
+                        className={`flex items-center justify-center rounded-xl hover:bg-white/[0.06] transition-colors shrink-0 ${running ? 'text-emerald-400' : 'text-slate-500 hover:text-slate-300'}`}
+                        style={{ width: 36, height: 36, marginLeft: 'auto' }}
+                        title={running ? 'Unload from memory' : 'Load into memory'}
+                      >
+                        {isToggling ? <Loader2 size={15} className="animate-spin" /> : <MemoryStick size={15} />}
+                      </button>
+                    )}
                     {!isDeleting && (confirmDelete === model ? (
                       <div className="flex items-center shrink-0" style={{ gap: 6 }}>
                         <button
@@ -363,6 +595,73 @@ export function SettingsModal({ onClose }: SettingsModalProps) {
                       </button>
                     ))}
                   </div>
+                  {expandedModel === model && (
+                    <div
+                      className="rounded-xl ring-1 ring-white/[0.06] text-xs text-slate-400"
+                      style={{ padding: '12px 18px', background: 'rgba(255,255,255,0.02)' }}
+                    >
+                      {!detail ? (
+                        <span className="flex items-center" style={{ gap: 6 }}>
+                          <Loader2 size={12} className="animate-spin" /> Loading details...
+                        </span>
+                      ) : (
+                        <div className="flex flex-col" style={{ gap: 6 }}>
+                          <p>
+                            {[detail.family, detail.parameterSize, detail.quantizationLevel].filter(Boolean).join(' · ')}
+                            {detail.details?.contextLength != null && ` · ${detail.details.contextLength.toLocaleString()} token context`}
+                          </p>
+                          {modelFits[model] && (
+                            <p className={modelFits[model].verdict === 'fits' ? 'text-emerald-400' : 'text-amber-400'}>
+                              Needs about {formatSize(modelFits[model].requiredBytes)} at {modelFits[model].contextLength.toLocaleString()} tokens of context
+                              {modelFits[model].verdict === 'fits' ? ' · fits in free memory'
+                                : modelFits[model].verdict === 'slow' ? ' · may run slowly until other apps free memory'
+                                : ' · more than this machine can hold'}
+                            </p>
+                          )}
+                          {detail.details && (
+                            <p>
+                              {(['vision', 'tools', 'thinking', 'embedding'] as const)
+                                .filter(c => detail.details!.capabilities[c])
+                                .map(c => ({ vision: 'Images', tools: 'Tools', thinking: 'Reasoning', embedding: 'Embeddings only' })[c])
+                                .join(' · ') || 'Text chat'}
+                            </p>
+                          )}
+                          {detail.details && detail.details.parameters.length > 0 && (
+                            <p className="font-mono text-[11px] text-slate-500">
+                              {detail.details.parameters.map(p => `${p.name} ${p.value}`).join(' · ')}
+                            </p>
+                          )}
+                          <button
+                            onClick={() => setPersonaBase(model)}
+                            className="flex items-center self-start text-indigo-400 hover:text-indigo-300 transition-colors"
+                            style={{ gap: 5 }}
+                          >
+                            <Sparkles size={11} />
+                            Make a persona from this model
+                          </button>
+                          <button
+                            onClick={() => handleExport(model)}
+                            disabled={exporting !== null}
+                            className="flex items-center self-start text-indigo-400 hover:text-indigo-300 transition-colors disabled:opacity-50 disabled:cursor-not-allowed"
+                            style={{ gap: 5 }}
+                            title="Save this model as one file to install on a computer without internet"
+                          >
+                            {exporting?.model === model ? <Loader2 size={11} className="animate-spin" /> : <PackageOpen size={11} />}
+                            {exporting?.model === model ? `Exporting... ${exporting.percent}%` : 'Export as a bundle'}
+                          </button>
+                          {detail.details?.license && (
+                            <details>
+                              <summary className="cursor-pointer select-none text-slate-500">License</summary>
+                              <pre className="whitespace-pre-wrap text-[11px] text-slate-500" style={{ marginTop: 6, maxHeight: 160, overflowY: 'auto' }}>
+                                {detail.details.license}
+                              </pre>
+                            </details>
+                          )}
+                        </div>
+                      )}
+                    </div>
+                  )}
+                  </div>
                 )
               })
             )}
@@ -415,7 +714,7 @@ export function SettingsModal({ onClose }: SettingsModalProps) {
               value={modelSearch}
               onChange={e => setModelSearch(e.target.value)}
               onKeyDown={e => {
-                if (e.key === 'Enter' && modelSearch.trim() && !pullingModel && filteredModels.length === 0) {
+                if (e.key === 'Enter' && modelSearch.trim() && !downloadFor(modelSearch.trim()) && filteredModels.length === 0) {
                   handlePull(modelSearch.trim())
                 }
               }}
@@ -433,10 +732,11 @@ export function SettingsModal({ onClose }: SettingsModalProps) {
                 </p>
                 <div className="flex flex-col" style={{ gap: 8 }}>
                   {group.models.map(model => {
-                    const canRun = ramGB >= model.maxRam
-                    const isRecommended = canRun && model.maxRam <= ramGB
+                    const canRun = model.fit.verdict !== 'wontFit'
+                    const isRecommended = model.fit.verdict === 'fits'
+                    const isSlow = model.fit.verdict === 'slow'
                     const isOversized = !canRun
-                    const isPulling = pullingModel === model.name
+                    const download = downloadFor(model.name)
                     const canPull = ollamaStatus === 'connected' && (canRun || showAllModels)
                     return (
                       <div
@@ -471,33 +771,31 @@ export function SettingsModal({ onClose }: SettingsModalProps) {
                                   Recommended
                                 </span>
                               )}
+                              {isSlow && (
+                                <span
+                                  className="flex items-center text-[10px] font-semibold text-amber-400 bg-amber-500/10 rounded-full"
+                                  style={{ padding: '1px 7px', gap: 3 }}
+                                  title="Fits in RAM, but not in what is free right now"
+                                >
+                                  May run slowly
+                                </span>
+                              )}
                               {isOversized && showAllModels && (
                                 <span
                                   className="flex items-center text-[10px] font-semibold text-amber-400 bg-amber-500/10 rounded-full"
                                   style={{ padding: '1px 7px', gap: 3 }}
                                 >
-                                  Needs {model.maxRam}GB+
+                                  Needs {formatSize(model.fit.requiredBytes)}
                                 </span>
                               )}
                             </div>
                             <p className="text-xs text-slate-500" style={{ marginTop: 3 }}>
-                              {model.description} &middot; {model.params} &middot; {model.size}
+                              {model.description} &middot; {model.parameterSize} &middot; {formatSize(model.sizeBytes)}
                             </p>
                           </div>
                         </div>
-                        {isPulling ? (
-                          <div className="flex items-center shrink-0" style={{ gap: 10, minWidth: 140 }}>
-                            <Loader2 size={16} className="text-indigo-400 animate-spin shrink-0" />
-                            <div className="flex-1 flex flex-col" style={{ gap: 4 }}>
-                              <div className="rounded-full overflow-hidden" style={{ width: 100, height: 6, background: 'rgba(255,255,255,0.08)' }}>
-                                <div
-                                  className="h-full bg-indigo-500 rounded-full transition-all"
-                                  style={{ width: `${pullPercent}%` }}
-                                />
-                              </div>
-                            </div>
-                            <span className="text-sm text-indigo-400 font-semibold tabular-nums shrink-0">{pullPercent}%</span>
-                          </div>
+                        {download ? (
+                          renderDownload(download)
                         ) : (
                           <button
                             className={`flex items-center rounded-lg transition-colors text-xs font-semibold shrink-0 ${
@@ -506,7 +804,7 @@ export function SettingsModal({ onClose }: SettingsModalProps) {
                                 : 'bg-white/[0.04] text-slate-500 cursor-not-allowed'
                             }`}
                             style={{ padding: '8px 14px', gap: 6 }}
-                            disabled={!canPull || !!pullingModel}
+                            disabled={!canPull}
                             onClick={() => handlePull(model.name)}
                           >
                             <Download size={12} />
@@ -537,24 +835,13 @@ export function SettingsModal({ onClose }: SettingsModalProps) {
                     <p className="text-xs text-slate-500" style={{ marginTop: 3 }}>Not in catalog &middot; Pull from Ollama library</p>
                   </div>
                 </div>
-                {pullingModel === modelSearch.trim() ? (
-                  <div className="flex items-center shrink-0" style={{ gap: 10, minWidth: 140 }}>
-                    <Loader2 size={16} className="text-indigo-400 animate-spin shrink-0" />
-                    <div className="flex-1 flex flex-col" style={{ gap: 4 }}>
-                      <div className="rounded-full overflow-hidden" style={{ width: 100, height: 6, background: 'rgba(255,255,255,0.08)' }}>
-                        <div
-                          className="h-full bg-indigo-500 rounded-full transition-all"
-                          style={{ width: `${pullPercent}%` }}
-                        />
-                      </div>
-                    </div>
-                    <span className="text-sm text-indigo-400 font-semibold tabular-nums shrink-0">{pullPercent}%</span>
-                  </div>
+                {downloadFor(modelSearch.trim()) ? (
+                  renderDownload(downloadFor(modelSearch.trim())!)
                 ) : (
                   <button
                     className="flex items-center rounded-lg bg-indigo-600 text-white text-xs font-semibold hover:bg-indigo-500 transition-colors disabled:opacity-30 disabled:cursor-not-allowed shrink-0"
                     style={{ padding: '8px 14px', gap: 6 }}
-                    disabled={!!pullingModel || ollamaStatus !== 'connected'}
+                    disabled={ollamaStatus !== 'connected'}
                     onClick={() => handlePull(modelSearch.trim())}
                   >
                     <Download size={12} />
@@ -73,7 +73,7 @@ function ChatsTab() {
                 <button
                   onClick={(e) => {
                     e.stopPropagation()
-                    cancelChat().catch(() => {})
+                    cancelChat(chat.id).catch(() => {})
                     deleteChat(chat.id)
                   }}
                   className="opacity-0 group-hover:opacity-100 flex items-center justify-center rounded-md hover:bg-white/[0.06] transition-all text-slate-500 hover:text-red-400 shrink-0"
@@ -1,24 +1,135 @@
 import { invoke, Channel } from '@tauri-apps/api/core'
+import { listen } from '@tauri-apps/api/event'
+
+/** Error returned by every backend command; `kind` is a stable code. */
+export interface AppError {
+  kind:
+    | 'ollamaUnreachable'
+    | 'connectTimeout'
+    | 'timeout'
+    | 'stalled'
+    | 'connectionLost'
+    | 'modelNotFound'
+    | 'ollamaError'
+    | 'badResponse'
+    | 'remoteEndpoint'
+    | 'unsupported'
+    | 'sidecar'
+    | 'toolRoundsExceeded'
+    | 'invalidInput'
+    | 'notFound'
+    | 'permissionDenied'
+    | 'diskFull'
+    | 'insufficientSpace'
+    | 'io'
+    | 'internal'
+  message: string
+  details?: string
+}
+
+export function isAppError(e: unknown): e is AppError {
+  return typeof e === 'object' && e !== null && 'kind' in e && 'message' in e
+}
+
+/** Human-readable text for anything thrown by `invoke`. */
+export function errorMessage(e: unknown, fallback = 'Something went wrong'): string {
+  if (isAppError(e)) return e.message
+  if (e instanceof Error) return e.message
+  if (typeof e === 'string') return e
+  return fallback
+}
+
+/** A suggested fix the UI can show next to the error, if there is one. */
+export function errorHint(error: AppError): string | null {
+  switch (error.kind) {
+    case 'ollamaUnreachable':
+    case 'connectTimeout':
+    case 'stalled':
+    case 'connectionLost':
+      return 'Make sure Ollama is running, or restart it from Settings.'
+    case 'modelNotFound':
+      return 'Download the model from Settings → Models.'
+    case 'diskFull':
+    case 'insufficientSpace':
+      return 'Free up disk space and try again.'
+    case 'permissionDenied':
+      return 'Check the file permissions and try again.'
+    default:
+      return null
+  }
+}
+
+export interface ModelCapabilities {
+  vision: boolean
+  tools: boolean
+  thinking: boolean
+  embedding: boolean
+}
+
+/** What only `showModel` returns. */
+export interface ModelDetails {
+  /** Longest context the model was trained for, in tokens. */
+  contextLength: number | null
+  license: string | null
+  template: string | null
+  /** Defaults baked into the model; names like `stop` can repeat. */
+  parameters: { name: string; value: string }[]
+  capabilities: ModelCapabilities
+}
 
 export interface OllamaModel {
   name: string
   size: number
+  /** Embedding-only model, not usable for chat. */
+  embedding: boolean
+  family: string
+  /** Like "8.0B". */
+  parameterSize: string
+  /** Like "Q4_K_M". */
+  quantizationLevel: string
+  digest: string
+  modifiedAt: string | null
+  details?: ModelDetails
 }
 
-interface ChatMessage {
+export type ImageAttachment = { path: string } | { data: number[] }
+
+export interface ChatMessage {
   role: string
   content: string
+  images?: ImageAttachment[]
+}
+
+export interface ChatOptions {
+  temperature?: number
+  numCtx?: number
+  seed?: number
+  topP?: number
+  stop?: string[]
+  numPredict?: number
+}
+
+export interface GenerationMetrics {
+  totalDuration: number
+  loadDuration: number
+  promptEvalCount: number
+  promptEvalDuration: number
+  evalCount: number
+  evalDuration: number
+  tokensPerSecond: number | null
+  promptTokensPerSecond: number | null
 }
 
 type StreamEvent =
   | { event: 'token'; data: { content: string } }
-  | { event: 'done'; data: { totalDuration: number } }
-  | { event: 'error'; data: { message: string } }
-
-type PullEvent =
-  | { event: 'progress'; data: { status: string; completed: number | null; total: number | null } }
-  | { event: 'done'; data: Record<string, never> }
-  | { event: 'error'; data: { message: string } }
+  | { event: 'thinking'; data: { content: string } }
+  | { event: 'toolCall'; data: { name: string; arguments: unknown } }
+  | { event: 'toolResult'; data: { name: string; result: string; isError: boolean } }
+  | { event: 'citations'; data: { citations: Citation[] } }
+  | { event: 'invalidOutput'; data: { error: OutputError } }
+  | { event: 'warning'; data: { message: string } }
+  | { event: 'done'; data: { partial: boolean; metrics: GenerationMetrics | null } }
+  | { event: 'error'; data: AppError }
 
 export async function checkOllama(): Promise<boolean> {
   return invoke<boolean>('check_ollama')
@@ -32,72 +143,393 @@ export async function getSystemRam(): Promise<number> {
   return invoke<number>('get_system_ram')
 }
 
+export interface ToolSettings {
+  folders: string[]
+}
+
+/** `'json'` for any JSON, or a JSON Schema the reply must match. */
+export type OutputFormat = 'json' | Record<string, unknown>
+
+export type OutputError =
+  | { kind: 'invalidJson'; message: string }
+  | { kind: 'schemaMismatch'; violations: { path: string; message: string }[] }
+
+/** A chunk of an attached file retrieved as context for a reply. */
+export interface Citation {
+  path: string
+  name: string
+  startLine: number
+  endLine: number
+  text: string
+  score: number
+}
+
+export interface RetrievalSettings {
+  /** Folders previously indexed with `indexFolder`. */
+  folders: string[]
+  topK?: number
+}
+
+export interface ChatSettings {
+  options?: ChatOptions
+  think?: boolean
+  tools?: ToolSettings
+  format?: OutputFormat
+  retrieval?: RetrievalSettings
+  /** How long the model stays loaded after this reply. */
+  keepAlive?: KeepAlive
+}
+
+export interface ChatHandlers {
+  onToken: (content: string) => void
+  onThinking?: (content: string) => void
+  onToolCall?: (name: string, args: unknown) => void
+  onToolResult?: (name: string, result: string, isError: boolean) => void
+  onCitations?: (citations: Citation[]) => void
+  onInvalidOutput?: (error: OutputError) => void
+  /** Something the user should know that doesn't stop the reply. */
+  onWarning?: (message: string) => void
+  onDone: (partial: boolean, metrics: GenerationMetrics | null) => void
+  onError: (error: AppError) => void
+}
+
 export async function chat(
+  requestId: string,
   model: string,
   messages: ChatMessage[],
-  onToken: (content: string) => void,
-  onDone: () => void,
-  onError: (message: string) => void,
+  settings: ChatSettings,
+  handlers: ChatHandlers,
 ): Promise<void> {
   const onEvent = new Channel<StreamEvent>()
   onEvent.onmessage = (msg) => {
     if (msg.event === 'token') {
-      onToken(msg.data.content)
+      handlers.onToken(msg.data.content)
+    } else if (msg.event === 'thinking') {
+      handlers.onThinking?.(msg.data.content)
+    } else if (msg.event === 'toolCall') {
+      handlers.onToolCall?.(msg.data.name, msg.data.arguments)
+    } else if (msg.event === 'toolResult') {
+      handlers.onToolResult?.(msg.data.name, msg.data.result, msg.data.isError)
+    } else if (msg.event === 'citations') {
+      handlers.onCitations?.(msg.data.citations)
+    } else if (msg.event === 'invalidOutput') {
+      handlers.onInvalidOutput?.(msg.data.error)
+    } else if (msg.event === 'warning') {
+      handlers.onWarning?.(msg.data.message)
     } else if (msg.event === 'done') {
-      onDone()
+      handlers.onDone(msg.data.partial, msg.data.metrics)
     } else if (msg.event === 'error') {
-      onError(msg.data.message)
+      handlers.onError(msg.data)
     }
   }
 
-  await invoke('chat', { model, messages, onEvent })
+  await invoke('chat', {
+    requestId,
+    model,
+    messages,
+    options: settings.options ?? null,
+    think: settings.think ?? null,
+    tools: settings.tools ?? null,
+    format: settings.format ?? null,
+    retrieval: settings.retrieval ?? null,
+    keepAlive: settings.keepAlive ?? null,
+    onEvent,
+  })
+}
+
+export type DownloadState = 'queued' | 'downloading' | 'paused' | 'done' | 'failed' | 'cancelled'
+
+/** A model pull in the download queue. Updates arrive as `download-updated` events. */
+export type PullPhase = 'pullingManifest' | 'pulling' | 'verifying' | 'writingManifest' | 'cleanup'
+
+export interface Download {
+  id: string
+  model: string
+  state: DownloadState
+  /** Ollama's last status line, like "pulling manifest". */
+  status: string
+  phase: PullPhase | null
+  /** Bytes across every layer seen so far. */
+  completed: number | null
+  total: number | null
+  bytesPerSecond: number | null
+  etaSeconds: number | null
+  error?: AppError
+}
+
+/** Queue a pull; returns the existing download if the model is already queued. */
+export async function queuePull(name: string): Promise<Download> {
+  return invoke<Download>('queue_pull', { name })
+}
+
+export async function listDownloads(): Promise<Download[]> {
+  return invoke<Download[]>('list_downloads')
+}
+
+export async function cancelPull(id: string): Promise<void> {
+  await invoke('cancel_pull', { id })
+}
+
+export async function pausePull(id: string): Promise<void> {
+  await invoke('pause_pull', { id })
+}
+
+/** Requeue a paused or failed download; Ollama resumes partial layers. */
+export async function resumePull(id: string): Promise<void> {
+  await invoke('resume_pull', { id })
+}
+
+export function onDownloadUpdated(handler: (download: Download) => void): Promise<() => void> {
+  return listen<Download>('download-updated', (event) => handler(event.payload))
+}
+
+/** Vectors are L2-normalised, one per input, in input order. */
+export async function embed(
+  model: string,
+  inputs: string[],
+  onProgress?: (completed: number, total: number) => void,
+): Promise<number[][]> {
+  const onEvent = new Channel<{ completed: number; total: number }>()
+  onEvent.onmessage = (msg) => onProgress?.(msg.completed, msg.total)
+  return invoke<number[][]>('embed', { model, inputs, onProgress: onEvent })
+}
+
+export interface IndexSummary {
+  files: number
+  chunks: number
+}
+
+/** Chunk and embed a folder's text files so chat can retrieve from it. */
+export async function indexFolder(
+  path: string,
+  model: string,
+  onProgress?: (completed: number, total: number) => void,
+): Promise<IndexSummary> {
+  const onEvent = new Channel<{ completed: number; total: number }>()
+  onEvent.onmessage = (msg) => onProgress?.(msg.completed, msg.total)
+  return invoke<IndexSummary>('index_folder', { path, model, onProgress: onEvent })
+}
+
+export async function removeFolderIndex(path: string): Promise<void> {
+  await invoke('remove_folder_index', { path })
+}
+
+export async function searchFolders(folders: string[], query: string, topK?: number): Promise<Citation[]> {
+  return invoke<Citation[]>('search_folders', { folders, query, topK: topK ?? null })
+}
+
+export interface DeletedModel {
+  /** Disk space given back; only known for the bundled Ollama. */
+  freedBytes: number | null
+}
+
+/** Delete a model, unloading it from memory first if it is loaded. */
+export async function deleteModel(name: string): Promise<DeletedModel> {
+  return invoke<DeletedModel>('delete_model', { name })
+}
+
+/** A model to build from an installed one. Anything left out is inherited. */
+export interface CreateModelRequest {
+  name: string
+  /** Installed model to start from. */
+  from: string
+  system?: string
+  /** Default options such as `temperature`, `num_ctx` or `stop`. */
+  parameters?: Record<string, unknown>
+  /** Prompt template, in Go template syntax. */
+  template?: string
+  /** Path to a LoRA adapter, as a GGUF or safetensors file. */
+  adapter?: string | null
 }
 
-export async function pullModel(
+/** A status line; byte counts only while a local file is hashed or uploaded. */
+export interface CreateProgress {
+  status: string
+  completed?: number
+  total?: number
+}
+
+/** Create a model via Ollama's `/api/create`, reporting uploads and each status line. */
+export async function createModel(
+  request: CreateModelRequest,
+  onProgress?: (progress: CreateProgress) => void,
+): Promise<void> {
+  const onEvent = new Channel<CreateProgress>()
+  onEvent.onmessage = (msg) => onProgress?.(msg)
+  await invoke('create_model', { request, onProgress: onEvent })
+}
+
+/** Make a model from a `.gguf` file on this machine. Works offline. */
+export async function importGguf(
+  path: string,
   name: string,
-  onProgress: (status: string, completed: number | null, total: number | null) => void,
-  onDone: () => void,
-  onError: (message: string) => void,
+  onProgress?: (progress: CreateProgress) => void,
 ): Promise<void> {
-  const onEvent = new Channel<PullEvent>()
-  onEvent.onmessage = (msg) => {
-    if (msg.event === 'progress') {
-      onProgress(msg.data.status, msg.data.completed, msg.data.total)
-    } else if (msg.event === 'done') {
-      onDone()
-    } else if (msg.event === 'error') {
-      onError(msg.data.message)
-    }
-  }
+  const onEvent = new Channel<CreateProgress>()
+  onEvent.onmessage = (msg) => onProgress?.(msg)
+  await invoke('import_gguf', { path, name, onProgress: onEvent })
+}
+
+/** Copy a model under a new name; blobs are shared, so it takes no space. */
+export async function copyModel(source: string, destination: string): Promise<void> {
+  await invoke('copy_model', { source, destination })
+}
+
+export interface BundleProgress {
+  completed: number
+  total: number
+}
+
+/** Write an installed model to a single `.tar` bundle for machines without internet. */
+export async function exportModel(
+  name: string,
+  path: string,
+  onProgress?: (progress: BundleProgress) => void,
+): Promise<void> {
+  const onEvent = new Channel<BundleProgress>()
+  onEvent.onmessage = (msg) => onProgress?.(msg)
+  await invoke('export_model', { name, path, onProgress: onEvent })
+}
+
+/** Install the models in a bundle made by `exportModel`, returning their names. */
+export async function importBundle(
+  path: string,
+  onProgress?: (progress: BundleProgress) => void,
+): Promise<string[]> {
+  const onEvent = new Channel<BundleProgress>()
+  onEvent.onmessage = (msg) => onProgress?.(msg)
+  return invoke<string[]>('import_bundle', { path, onProgress: onEvent })
+}
+
+/** Duration like '10m' or '1h', or seconds; 0 unloads at once, negative keeps the model loaded. */
+export type KeepAlive = string | number
+
+export interface RunningModel {
+  name: string
+  /** Bytes of memory the loaded model takes. */
+  size: number
+  sizeVram: number
+  /** RFC 3339 time the model unloads if left idle. */
+  expiresAt: string | null
+}
+
+export async function showModel(name: string): Promise<OllamaModel> {
+  return invoke<OllamaModel>('show_model', { name })
+}
+
+export interface SystemMemory {
+  totalBytes: number
+  availableBytes: number
+}
+
+/** `fits` in free memory now, `slow` only by pushing other apps to swap, `wontFit` at all. */
+export type FitVerdict = 'fits' | 'slow' | 'wontFit'
 
-  await invoke('pull_model', { name, onEvent })
+export interface FitEstimate extends SystemMemory {
+  verdict: FitVerdict
+  requiredBytes: number
+  /** Context the estimate assumes, in tokens. */
+  contextLength: number
 }
 
-export async function deleteModel(name: string): Promise<void> {
-  await invoke('delete_model', { name })
+export interface CatalogModel {
+  name: string
+  description: string
+  parameterSize: string
+  parametersBillion: number
+  quantizationLevel: string
+  sizeBytes: number
+  contextLength: number
+  capabilities: ModelCapabilities
+}
+
+export interface Recommendation extends CatalogModel {
+  fit: FitEstimate
+}
+
+export async function getSystemMemory(): Promise<SystemMemory> {
+  return invoke<SystemMemory>('get_system_memory')
+}
+
+export async function estimateModelFit(name: string, contextLength?: number): Promise<FitEstimate> {
+  return invoke<FitEstimate>('estimate_model_fit', { name, contextLength: contextLength ?? null })
+}
+
+/** The bundled catalog, best fit for this machine first. */
+export async function recommendModels(contextLength?: number): Promise<Recommendation[]> {
+  return invoke<Recommendation[]>('recommend_models', { contextLength: contextLength ?? null })
+}
+
+export async function listRunningModels(): Promise<RunningModel[]> {
+  return invoke<RunningModel[]>('list_running_models')
+}
+
+export async function loadModel(name: string, keepAlive?: KeepAlive): Promise<void> {
+  await invoke('load_model', { name, keepAlive: keepAlive ?? null })
+}
+
+export async function unloadModel(name: string): Promise<void> {
+  await invoke('unload_model', { name })
 }
 
 export async function waitForOllama(): Promise<boolean> {
   return invoke<boolean>('wait_for_ollama')
 }
 
+export interface ModelUsage {
+  name: string
+  /** Every blob the model uses. */
+  sizeBytes: number
+  /** Blobs no other model shares, i.e. what deleting it frees. */
+  uniqueBytes: number
+}
+
 export interface StorageInfo {
   path: string
   sizeBytes: number
+  /** Largest first. */
+  models: ModelUsage[]
+  /** Blobs no model uses, including abandoned partial downloads. */
+  orphanedBytes: number
+  orphanedBlobs: number
+  availableBytes: number | null
 }
 
 export async function getStorageInfo(): Promise<StorageInfo> {
   return invoke<StorageInfo>('get_storage_info')
 }
 
-export async function cancelChat(): Promise<void> {
-  await invoke('cancel_chat')
+/** Delete blobs no model uses. Fails while downloads are unfinished. */
+export async function cleanOrphanedBlobs(): Promise<{ removedBlobs: number; freedBytes: number }> {
+  return invoke('clean_orphaned_blobs')
+}
+
+export async function cancelChat(requestId: string): Promise<void> {
+  await invoke('cancel_chat', { requestId })
 }
 
 export async function restartOllama(): Promise<void> {
   await invoke('restart_ollama')
 }
 
+/** `openai` covers any OpenAI-compatible server (llama.cpp, LM Studio, vLLM). */
+export type ProviderKind = 'ollama' | 'openai'
+
+export interface EndpointConfig {
+  url: string
+  provider: ProviderKind
+  apiKey?: string
+}
+
+export async function getEndpoint(): Promise<EndpointConfig> {
+  return invoke<EndpointConfig>('get_endpoint')
+}
+
+/** Returns the normalised config that was saved. */
+export async function setEndpoint(config: EndpointConfig): Promise<EndpointConfig> {
+  return invoke<EndpointConfig>('set_endpoint', { config })
+}
+
 export async function readFileContent(path: string): Promise<string> {
   return invoke<string>('read_file_content', { path })
 }
@@ -106,10 +538,29 @@ export interface FolderFile {
   name: string
   path: string
   content: string
+  kind: 'text' | 'image'
+}
+
+/** Why a text file was left out of context and the index. */
+export type SkipReason = 'tooLarge' | 'unreadable'
+
+export type FileChange =
+  | ({ change: 'added' | 'modified' } & FolderFile)
+  | { change: 'deleted'; path: string }
+  /** Still there, but now over 1 MB or not valid text. */
+  | { change: 'skipped'; path: string; reason: SkipReason }
+
+/** Payload of the `folder-index-updated` event emitted by a watched folder. */
+export interface FolderUpdate {
+  folder: string
+  changes: FileChange[]
+  /** The folder's retrieval index was updated to match. */
+  indexed: boolean
+  error?: AppError
 }
 
-export async function readFolderFiles(path: string): Promise<FolderFile[]> {
-  return invoke<FolderFile[]>('read_folder_files', { path })
+export async function readFolderFiles(path: string, includeImages = false): Promise<FolderFile[]> {
+  return invoke<FolderFile[]>('read_folder_files', { path, includeImages })
 }
 
 export async function watchFolder(path: string): Promise<void> {
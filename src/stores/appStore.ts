@@ -1,6 +1,7 @@
 import { create } from 'zustand'
 import { persist, createJSONStorage } from 'zustand/middleware'
 import { tauriStorage } from '../lib/storage'
+import type { Download, SkipReason } from '../lib/ollama'
 
 export type Tab = 'chat' | 'files' | 'settings'
 export type SidebarTab = 'chats' | 'files'
@@ -17,33 +18,40 @@ export interface ContextFolder {
   name: string
   path: string
   files: ContextFile[]
+  /** Embedded for retrieval; chat then sends relevant chunks instead of every file. */
+  indexed?: boolean
+  /** Files that stopped being usable since the folder was watched, by path. */
+  skipped?: Record<string, SkipReason>
 }
 
 interface AppState {
   activeTab: Tab
   sidebarTab: SidebarTab
   selectedModel: string
+  /** Model used to index folders for retrieval; empty disables it. */
+  embeddingModel: string
   availableModels: string[]
   sidebarCollapsed: boolean
   ollamaStatus: OllamaStatus
   systemRam: number
   contextFolders: ContextFolder[]
-  // Pull state (lives in store so it survives settings modal close)
-  pullingModel: string | null
-  pullPercent: number
+  // Download queue, mirrored from the backend's `download-updated` events
+  downloads: Download[]
   pullError: string | null
   setActiveTab: (tab: Tab) => void
   setSidebarTab: (tab: SidebarTab) => void
   setSelectedModel: (model: string) => void
+  setEmbeddingModel: (model: string) => void
   setAvailableModels: (models: string[]) => void
   toggleSidebar: () => void
   setOllamaStatus: (status: OllamaStatus) => void
   setSystemRam: (ram: number) => void
   addContextFolder: (folder: ContextFolder) => void
   removeContextFolder: (id: string) => void
-  updateContextFolder: (id: string, files: ContextFile[]) => void
-  setPullingModel: (model: string | null) => void
-  setPullPercent: (percent: number) => void
+  updateContextFolder: (id: string, files: ContextFile[], skipped?: Record<string, SkipReason>) => void
+  setContextFolderIndexed: (id: string, indexed: boolean) => void
+  setDownloads: (downloads: Download[]) => void
+  upsertDownload: (download: Download) => void
   setPullError: (error: string | null) => void
 }
 
@@ -53,29 +61,38 @@ export const useAppStore = create<AppState>()(
       activeTab: 'chat',
       sidebarTab: 'chats',
       selectedModel: '',
+      embeddingModel: '',
       availableModels: [],
       sidebarCollapsed: false,
       ollamaStatus: 'checking',
       systemRam: 0,
       contextFolders: [],
-      pullingModel: null,
-      pullPercent: 0,
+      downloads: [],
       pullError: null,
 
       setActiveTab: (tab) => set({ activeTab: tab }),
       setSidebarTab: (tab) => set({ sidebarTab: tab }),
       setSelectedModel: (model) => set({ selectedModel: model }),
+      setEmbeddingModel: (model) => set({ embeddingModel: model }),
       setAvailableModels: (models) => set({ availableModels: models }),
       toggleSidebar: () => set(state => ({ sidebarCollapsed: !state.sidebarCollapsed })),
       setOllamaStatus: (status) => set({ ollamaStatus: status }),
       setSystemRam: (ram) => set({ systemRam: ram }),
       addContextFolder: (folder) => set(state => ({ contextFolders: [...state.contextFolders, folder] })),
       removeContextFolder: (id) => set(state => ({ contextFolders: state.contextFolders.filter(f => f.id !== id) })),
-      updateContextFolder: (id, files) => set(state => ({
-        contextFolders: state.contextFolders.map(f => f.id === id ? { ...f, files } : f),
+      updateContextFolder: (id, files, skipped) => set(state => ({
+        contextFolders: state.contextFolders.map(f => f.id === id ? { ...f, files, ...(skipped && { skipped }) } : f),
       })),
-      setPullingModel: (model) => set({ pullingModel: model }),
-      setPullPercent: (percent) => set({ pullPercent: percent }),
+      setContextFolderIndexed: (id, indexed) => set(state => ({
+        contextFolders: state.contextFolders.map(f => f.id === id ? { ...f, indexed } : f),
+      })),
+      setDownloads: (downloads) => set({ downloads }),
+      upsertDownload: (download) => set(state => {
+        const others = state.downloads.filter(d => d.id !== download.id)
+        // Finished downloads leave the backend queue too
+        const finished = download.state === 'done' || download.state === 'cancelled'
+        return { downloads: finished ? others : [...others, download].sort((a, b) => a.id.localeCompare(b.id)) }
+      }),
       setPullError: (error) => set({ pullError: error }),
     }),
     {
@@ -83,11 +100,13 @@ export const useAppStore = create<AppState>()(
       storage: createJSONStorage(() => tauriStorage),
       partialize: (state) => ({
         selectedModel: state.selectedModel,
+        embeddingModel: state.embeddingModel,
         sidebarCollapsed: state.sidebarCollapsed,
         contextFolders: state.contextFolders.map(f => ({
           id: f.id,
           name: f.name,
           path: f.path,
+          indexed: f.indexed,
           files: [], // Don't persist file contents, they'll be re-read on watch
         })),
       }),
@@ -1,18 +1,34 @@
 import { create } from 'zustand'
 import { persist, createJSONStorage } from 'zustand/middleware'
 import { tauriStorage } from '../lib/storage'
+import type { ChatOptions, Citation, GenerationMetrics, KeepAlive, OutputFormat } from '../lib/ollama'
 
 export interface Attachment {
   name: string
   path: string
   content: string
+  kind?: 'text' | 'image'
+}
+
+export interface ToolCallRecord {
+  name: string
+  arguments: unknown
+  result?: string
+  isError?: boolean
 }
 
 export interface Message {
   id: string
   role: 'user' | 'assistant'
   content: string
+  thinking?: string
+  toolCalls?: ToolCallRecord[]
+  citations?: Citation[]
+  /** Non-fatal notices from the backend, such as ignored settings. */
+  warnings?: string[]
   attachments?: Attachment[]
+  metrics?: GenerationMetrics
+  partial?: boolean
   timestamp: number
 }
 
@@ -21,6 +37,11 @@ export interface Chat {
   title: string
   messages: Message[]
   model: string
+  options?: ChatOptions
+  think?: boolean
+  toolsEnabled?: boolean
+  format?: OutputFormat
+  keepAlive?: KeepAlive
   createdAt: number
   updatedAt: number
 }
@@ -34,7 +55,16 @@ interface ChatState {
   setActiveChat: (id: string) => void
   addMessage: (chatId: string, role: 'user' | 'assistant', content: string, attachments?: Attachment[]) => void
   updateLastMessage: (chatId: string, content: string) => void
+  updateLastMessageThinking: (chatId: string, thinking: string) => void
+  updateLastMessageToolCalls: (chatId: string, toolCalls: ToolCallRecord[]) => void
+  updateLastMessageCitations: (chatId: string, citations: Citation[]) => void
+  addLastMessageWarning: (chatId: string, warning: string) => void
+  finishLastMessage: (chatId: string, partial: boolean, metrics: GenerationMetrics | null) => void
   updateChatModel: (chatId: string, model: string) => void
+  updateChatOptions: (chatId: string, options: ChatOptions) => void
+  setChatThink: (chatId: string, think: boolean) => void
+  setChatToolsEnabled: (chatId: string, enabled: boolean) => void
+  setChatKeepAlive: (chatId: string, keepAlive: KeepAlive | undefined) => void
 }
 
 const generateId = () => Math.random().toString(36).substring(2, 15)
@@ -119,6 +149,87 @@ export const useChatStore = create<ChatState>()(
         }))
       },
 
+      updateLastMessageThinking: (chatId: string, thinking: string) => {
+        set(state => ({
+          chats: state.chats.map(chat =>
+            chat.id === chatId
+              ? {
+                  ...chat,
+                  messages: chat.messages.map((msg, i) =>
+                    i === chat.messages.length - 1 ? { ...msg, thinking } : msg
+                  ),
+                  updatedAt: Date.now(),
+                }
+              : chat
+          ),
+        }))
+      },
+
+      updateLastMessageToolCalls: (chatId: string, toolCalls: ToolCallRecord[]) => {
+        set(state => ({
+          chats: state.chats.map(chat =>
+            chat.id === chatId
+              ? {
+                  ...chat,
+                  messages: chat.messages.map((msg, i) =>
+                    i === chat.messages.length - 1 ? { ...msg, toolCalls } : msg
+                  ),
+                  updatedAt: Date.now(),
+                }
+              : chat
+          ),
+        }))
+      },
+
+      updateLastMessageCitations: (chatId: string, citations: Citation[]) => {
+        set(state => ({
+          chats: state.chats.map(chat =>
+            chat.id === chatId
+              ? {
+                  ...chat,
+                  messages: chat.messages.map((msg, i) =>
+                    i === chat.messages.length - 1 ? { ...msg, citations } : msg
+                  ),
+                  updatedAt: Date.now(),
+                }
+              : chat
+          ),
+        }))
+      },
+
+      addLastMessageWarning: (chatId: string, warning: string) => {
+        set(state => ({
+          chats: state.chats.map(chat =>
+            chat.id === chatId
+              ? {
+                  ...chat,
+                  messages: chat.messages.map((msg, i) =>
+                    i === chat.messages.length - 1 ? { ...msg, warnings: [...(msg.warnings ?? []), warning] } : msg
+                  ),
+                  updatedAt: Date.now(),
+                }
+              : chat
+          ),
+        }))
+      },
+
+      finishLastMessage: (chatId: string, partial: boolean, metrics: GenerationMetrics | null) => {
+        set(state => ({
+          chats: state.chats.map(chat =>
+            chat.id === chatId
+              ? {
+                  ...chat,
+                  messages: chat.messages.map((msg, i) =>
+                    i === chat.messages.length - 1
+                      ? { ...msg, ...(metrics ? { metrics } : {}), ...(partial ? { partial } : {}) }
+                      : msg
+                  ),
+                }
+              : chat
+          ),
+        }))
+      },
+
       updateChatModel: (chatId: string, model: string) => {
         set(state => ({
           chats: state.chats.map(chat =>
@@ -126,6 +237,38 @@ export const useChatStore = create<ChatState>()(
           ),
         }))
       },
+
+      updateChatOptions: (chatId: string, options: ChatOptions) => {
+        set(state => ({
+          chats: state.chats.map(chat =>
+            chat.id === chatId ? { ...chat, options } : chat
+          ),
+        }))
+      },
+
+      setChatThink: (chatId: string, think: boolean) => {
+        set(state => ({
+          chats: state.chats.map(chat =>
+            chat.id === chatId ? { ...chat, think } : chat
+          ),
+        }))
+      },
+
+      setChatToolsEnabled: (chatId: string, enabled: boolean) => {
+        set(state => ({
+          chats: state.chats.map(chat =>
+            chat.id === chatId ? { ...chat, toolsEnabled: enabled } : chat
+          ),
+        }))
+      },
+
+      setChatKeepAlive: (chatId: string, keepAlive: KeepAlive | undefined) => {
+        set(state => ({
+          chats: state.chats.map(chat =>
+            chat.id === chatId ? { ...chat, keepAlive } : chat
+          ),
+        }))
+      },
     }),
     {
       name: 'innertalk-chats',